@@ -0,0 +1,60 @@
+use blox_interpreter::{execute_program, Backend, EvaluationContext};
+use blox_language::parser::Parser;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const PROGRAMS: [(&str, &str); 3] = [
+    (
+        "fib",
+        "
+        def fib(x) {
+          if x == 0 { 0 } else if x == 1 { 1 } else { fib(x: x - 2) + fib(x: x - 1) }
+        }
+        fib(x: 18)
+        ",
+    ),
+    (
+        "count",
+        "
+        def count(n, total) {
+          if n == 0 { total } else { count(n: n - 1, total: total + 1) }
+        }
+        count(n: 10000, total: 0)
+        ",
+    ),
+    (
+        "build_list",
+        "
+        def build(n, list) {
+          if n == 0 { list } else { build(n: n - 1, list: list ++ [{ index: n, name: 'item' }]) }
+        }
+        build(n: 1000, list: [])
+        ",
+    ),
+];
+
+fn engines(c: &mut Criterion) {
+    for (name, code) in PROGRAMS {
+        let program = Parser::new(name, code)
+            .and_then(|parser| parser.parse())
+            .expect("parse error");
+        let mut group = c.benchmark_group(name);
+
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            group.bench_with_input(
+                BenchmarkId::from_parameter(format!("{backend:?}")),
+                &program,
+                |b, program| {
+                    b.iter(|| {
+                        let mut context = EvaluationContext::default().with_backend(backend);
+                        execute_program(program, &mut context).expect("execution error")
+                    })
+                },
+            );
+        }
+
+        group.finish();
+    }
+}
+
+criterion_group!(benches, engines);
+criterion_main!(benches);
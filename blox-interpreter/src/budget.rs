@@ -0,0 +1,229 @@
+use std::{
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use blox_language::ast;
+use im::{OrdMap, Vector};
+
+use crate::{RuntimeError, Value};
+
+// how much work a program may do before it's stopped. every limit is
+// optional; the default is no limits at all.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Limits {
+    // expressions evaluated, or instructions run by the bytecode machine
+    pub max_steps: Option<u64>,
+    // blox function calls in progress; tail calls don't add to this
+    pub max_call_depth: Option<usize>,
+    // an estimate of the bytes allocated by strings, arrays and objects over
+    // the whole run. memory isn't given back when a value is dropped, so this
+    // bounds how much a program builds in total, not how much it holds at once.
+    pub max_bytes: Option<usize>,
+    // wall-clock time, measured from when the budget was created
+    pub timeout: Option<Duration>,
+}
+
+// the limit that stopped a program
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Limit {
+    Steps(u64),
+    CallDepth(usize),
+    Memory(usize),
+    Timeout(Duration),
+}
+
+impl std::fmt::Display for Limit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Limit::Steps(steps) => write!(f, "more than {steps} steps"),
+            Limit::CallDepth(depth) => write!(f, "call depth deeper than {depth}"),
+            Limit::Memory(bytes) => write!(f, "more than {bytes} bytes allocated"),
+            Limit::Timeout(timeout) => write!(f, "ran for longer than {timeout:?}"),
+        }
+    }
+}
+
+// the deadline is only checked this often, since reading the clock is much
+// slower than counting a step
+const DEADLINE_CHECK_INTERVAL: u64 = 1024;
+
+// what a program has used of its limits so far. one budget is shared by an
+// evaluation context and all of its children, including the contexts modules
+// are imported in.
+#[derive(Debug)]
+pub struct Budget {
+    limits: Limits,
+    deadline: Option<Instant>,
+    steps: AtomicU64,
+    depth: AtomicUsize,
+    bytes: AtomicUsize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget::new(Limits::default())
+    }
+}
+
+impl Budget {
+    pub fn new(limits: Limits) -> Self {
+        Self {
+            deadline: limits.timeout.map(|timeout| Instant::now() + timeout),
+            limits,
+            steps: AtomicU64::new(0),
+            depth: AtomicUsize::new(0),
+            bytes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    pub fn steps(&self) -> u64 {
+        self.steps.load(Ordering::Relaxed)
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn step(&self) -> Result<(), RuntimeError> {
+        let steps = self.steps.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(max_steps) = self.limits.max_steps {
+            if steps > max_steps {
+                return Err(RuntimeError::LimitExceeded(Limit::Steps(max_steps)));
+            }
+        }
+
+        if steps.is_multiple_of(DEADLINE_CHECK_INTERVAL) {
+            self.check_deadline()?;
+        }
+
+        Ok(())
+    }
+
+    // every call that's entered must be exited, even if it fails
+    pub(crate) fn enter_call(&self) -> Result<(), RuntimeError> {
+        let depth = self.depth.fetch_add(1, Ordering::Relaxed) + 1;
+
+        if let Some(max_call_depth) = self.limits.max_call_depth {
+            if depth > max_call_depth {
+                self.depth.fetch_sub(1, Ordering::Relaxed);
+                return Err(RuntimeError::LimitExceeded(Limit::CallDepth(
+                    max_call_depth,
+                )));
+            }
+        }
+
+        self.check_deadline()
+    }
+
+    pub(crate) fn exit_call(&self) {
+        self.depth.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.depth.load(Ordering::Relaxed)
+    }
+
+    // restores the call depth after calls were abandoned by an error
+    pub(crate) fn unwind_to(&self, depth: usize) {
+        self.depth.store(depth, Ordering::Relaxed);
+    }
+
+    pub(crate) fn allocate(&self, bytes: usize) -> Result<(), RuntimeError> {
+        let total = self.bytes.fetch_add(bytes, Ordering::Relaxed) + bytes;
+
+        match self.limits.max_bytes {
+            Some(max_bytes) if total > max_bytes => {
+                Err(RuntimeError::LimitExceeded(Limit::Memory(max_bytes)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // charges a value built by native code, like an intrinsic's result, and
+    // hands it back
+    pub(crate) fn charge<T: Allocation>(&self, value: T) -> Result<T, RuntimeError> {
+        self.allocate(value.allocation_size())?;
+        Ok(value)
+    }
+
+    fn check_deadline(&self) -> Result<(), RuntimeError> {
+        match (self.deadline, self.limits.timeout) {
+            (Some(deadline), Some(timeout)) if Instant::now() > deadline => {
+                Err(RuntimeError::LimitExceeded(Limit::Timeout(timeout)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// roughly how many bytes a newly built value takes up, not counting members
+// that were already allocated elsewhere
+pub(crate) fn allocation_size(value: &Value) -> usize {
+    match value {
+        Value::String(string) => string.allocation_size(),
+        Value::Array(members) => members.allocation_size(),
+        Value::Object(fields) => fields.allocation_size(),
+        _ => 0,
+    }
+}
+
+// the rust types native code builds values from, measured the same way as
+// the values they become
+pub(crate) trait Allocation {
+    fn allocation_size(&self) -> usize;
+}
+
+impl Allocation for Value {
+    fn allocation_size(&self) -> usize {
+        allocation_size(self)
+    }
+}
+
+impl Allocation for String {
+    fn allocation_size(&self) -> usize {
+        self.len()
+    }
+}
+
+impl<T: Clone> Allocation for Vector<T> {
+    fn allocation_size(&self) -> usize {
+        self.len() * std::mem::size_of::<Value>()
+    }
+}
+
+impl<T> Allocation for Vec<T> {
+    fn allocation_size(&self) -> usize {
+        self.len() * std::mem::size_of::<Value>()
+    }
+}
+
+impl Allocation for OrdMap<String, Value> {
+    fn allocation_size(&self) -> usize {
+        self.keys()
+            .map(|key| key.len() + std::mem::size_of::<(String, Value)>())
+            .sum()
+    }
+}
+
+// roughly how many bytes applying an operator allocated. arrays share
+// structure with their operands, so only their new members are counted.
+pub(crate) fn operator_allocation_size(
+    operator: &ast::Operator,
+    rhs: &Value,
+    result: &Value,
+) -> usize {
+    match (operator, rhs, result) {
+        (ast::Operator::Concatenate, Value::Array(rhs), Value::Array(_)) => {
+            rhs.len() * std::mem::size_of::<Value>()
+        }
+        (ast::Operator::Concatenate, _, Value::String(string)) => string.len(),
+        (ast::Operator::Append, _, Value::Array(_)) => std::mem::size_of::<Value>(),
+        _ => 0,
+    }
+}
@@ -0,0 +1,16 @@
+//! A compiler from the AST to a flat list of instructions for a stack
+//! machine, as a faster alternative to walking the tree.
+//!
+//! Both engines share `Scope`, `Value` and the evaluation helpers in
+//! `expression`, so they produce the same values and errors, and functions
+//! defined by one can be called by the other.
+
+mod chunk;
+mod compiler;
+mod machine;
+
+pub use self::{
+    chunk::Chunk,
+    compiler::compile_program,
+    machine::{call, run},
+};
@@ -0,0 +1,120 @@
+use std::sync::{Arc, OnceLock};
+
+use blox_language::{ast, location::Location};
+
+use crate::Value;
+
+// instructions refer to names, expressions and so on by their index in the
+// tables of the chunk they belong to, which keeps them small. expressions are
+// only kept around to describe where an error happened.
+#[derive(Debug, Clone)]
+pub enum Instruction {
+    // pushes `constants[i]`
+    Constant(usize),
+    Void,
+    Pop,
+    Duplicate,
+
+    // reads, declares or updates `variables[i]`; declarations leave the value
+    // on the stack and assignments consume it
+    Load(usize),
+    Bind(usize),
+    BindMutable(usize),
+    Assign(usize),
+
+    // creates a function from `functions[i]`; `Define` closes over a new
+    // scope and binds the function's name in the current one
+    Define(usize),
+    Lambda(usize),
+    Import(usize),
+
+    // collects the top `n` values into an array, or the top values into an
+    // object with the keys in `object_keys[i]`
+    Array(usize),
+    Object(usize),
+
+    Binary {
+        operator: ast::Operator,
+        lhs: usize,
+        rhs: usize,
+    },
+    Index {
+        base: usize,
+        index: usize,
+        optional: bool,
+    },
+    Field {
+        key: usize,
+        base: usize,
+        optional: bool,
+    },
+    ToArray(usize),
+    ToNumber(usize),
+    // slices the array under two bounds, which are void when they're missing;
+    // `start` and `end` are the expressions the bounds came from
+    Slice {
+        start: Option<usize>,
+        end: Option<usize>,
+    },
+
+    // replace the container under the top value with a copy where the
+    // member is set to that value
+    SetIndex {
+        base: usize,
+        index: usize,
+    },
+    SetField {
+        key: usize,
+        base: usize,
+    },
+    NotAssignable(usize),
+
+    // looks up the method `variables[i]` for the receiver on top of the
+    // stack, and pushes it under the receiver
+    Method(usize),
+    // calls the function under the arguments named by `arguments[i]`; method
+    // calls also pass the receiver before them. tail calls replace the
+    // current frame instead of pushing a new one. `locations[location]` is
+    // where the call was made, for stack traces.
+    Call {
+        callee: usize,
+        arguments: usize,
+        method: bool,
+        tail: bool,
+        location: usize,
+    },
+    Return,
+
+    Jump(usize),
+    JumpIfFalse {
+        target: usize,
+        condition: usize,
+    },
+    // used by `??`: jumps keeping the top value unless it's void, otherwise
+    // pops it
+    JumpIfNotVoid(usize),
+    // used by optional chaining: jumps keeping the top value if it's void
+    JumpIfVoid(usize),
+}
+
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub instructions: Vec<Instruction>,
+    pub constants: Vec<Value>,
+    pub names: Vec<ast::Identifier>,
+    pub variables: Vec<(ast::Identifier, ast::Address)>,
+    pub expressions: Vec<ast::Expression>,
+    pub arguments: Vec<Vec<ast::Identifier>>,
+    pub object_keys: Vec<Vec<String>>,
+    pub functions: Vec<Prototype>,
+    pub imports: Vec<ast::Import>,
+    pub locations: Vec<Location>,
+}
+
+// a function definition in a chunk, along with its compiled body, which is
+// shared by all the functions created from it
+#[derive(Debug)]
+pub struct Prototype {
+    pub definition: Arc<ast::Definition>,
+    pub bytecode: Arc<OnceLock<Arc<Chunk>>>,
+}
@@ -0,0 +1,447 @@
+use std::sync::{Arc, OnceLock};
+
+use blox_language::ast;
+
+use crate::{expression::is_assignable, stack, RuntimeError, Value};
+
+use super::chunk::{Chunk, Instruction, Prototype};
+
+pub fn compile_program(program: &ast::Program) -> Result<Chunk, RuntimeError> {
+    let mut compiler = Compiler::default();
+    compiler.block(&program.block, false);
+    compiler.finish()
+}
+
+// function bodies are compiled with calls in tail position made as tail calls
+pub fn compile_function(definition: &ast::Definition) -> Result<Chunk, RuntimeError> {
+    let mut compiler = Compiler::default();
+    compiler.block(&definition.body, true);
+    compiler.finish()
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+    // the first thing that went wrong, which is a bug in the compiler
+    error: Option<RuntimeError>,
+}
+
+impl Compiler {
+    fn finish(mut self) -> Result<Chunk, RuntimeError> {
+        self.emit(Instruction::Return);
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(self.chunk),
+        }
+    }
+
+    // every block leaves exactly one value on the stack: its last statement's
+    fn block(&mut self, block: &ast::Block, tail: bool) {
+        let Some((last, statements)) = block.statements.split_last() else {
+            self.emit(Instruction::Void);
+            return;
+        };
+
+        for statement in statements {
+            self.statement(statement, false);
+            self.emit(Instruction::Pop);
+        }
+
+        self.statement(last, tail);
+    }
+
+    fn statement(&mut self, statement: &ast::Statement, tail: bool) {
+        match statement {
+            ast::Statement::Expression(expression) => self.expression(expression, tail),
+            ast::Statement::Binding(lhs, address, rhs) => {
+                self.expression(rhs, false);
+                let variable = self.variable(lhs, address);
+                self.emit(Instruction::Bind(variable));
+            }
+            ast::Statement::MutableBinding(lhs, address, rhs) => {
+                self.expression(rhs, false);
+                let variable = self.variable(lhs, address);
+                self.emit(Instruction::BindMutable(variable));
+            }
+            ast::Statement::Definition(definition) => {
+                let function = self.function(definition);
+                self.emit(Instruction::Define(function));
+            }
+            ast::Statement::Import(import) => {
+                self.chunk.imports.push(import.clone());
+                self.emit(Instruction::Import(self.chunk.imports.len() - 1));
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &ast::Expression, tail: bool) {
+        // grow the stack here if needed, to avoid stack overflows on deeply nested expressions
+        stack::maybe_grow(|| match expression {
+            ast::Expression::Term(term) => self.term(term, tail),
+            ast::Expression::BinaryExpression(lhs, operator, rhs) => {
+                self.binary_expression(lhs, operator, rhs)
+            }
+        })
+    }
+
+    fn binary_expression(
+        &mut self,
+        lhs: &ast::Expression,
+        operator: &ast::Operator,
+        rhs: &ast::Expression,
+    ) {
+        match operator {
+            ast::Operator::Assignment => {
+                self.expression(rhs, false);
+                self.emit(Instruction::Duplicate);
+                self.assign(lhs);
+            }
+            ast::Operator::Coalesce => {
+                self.expression(lhs, false);
+                let jump = self.emit(Instruction::JumpIfNotVoid(0));
+                self.expression(rhs, false);
+                self.patch(jump);
+            }
+            operator => {
+                self.expression(lhs, false);
+                self.expression(rhs, false);
+                let lhs_expression = self.expression_ref(lhs);
+                let rhs_expression = self.expression_ref(rhs);
+                self.emit(Instruction::Binary {
+                    operator: operator.clone(),
+                    lhs: lhs_expression,
+                    rhs: rhs_expression,
+                });
+
+                if *operator == ast::Operator::Append && is_assignable(lhs) {
+                    self.emit(Instruction::Duplicate);
+                    self.assign(lhs);
+                }
+            }
+        }
+    }
+
+    fn term(&mut self, term: &ast::ExpressionTerm, tail: bool) {
+        match term {
+            ast::ExpressionTerm::Identifier(identifier, address) => {
+                let variable = self.variable(identifier, address);
+                self.emit(Instruction::Load(variable));
+            }
+            ast::ExpressionTerm::Literal(literal) => {
+                let value = match literal {
+                    ast::Literal::Boolean(value) => Value::Boolean(*value),
+                    ast::Literal::Number(number) => Value::Number(*number),
+                    ast::Literal::String(string) => Value::String(string.clone()),
+                    ast::Literal::Symbol(string) => Value::Symbol(string.clone()),
+                };
+                self.chunk.constants.push(value);
+                self.emit(Instruction::Constant(self.chunk.constants.len() - 1));
+            }
+            ast::ExpressionTerm::Expression(expression) => self.expression(expression, tail),
+            ast::ExpressionTerm::FunctionCall(ast::FunctionCall(callee, arguments, location)) => {
+                self.expression(callee, false);
+                let mut names = Vec::new();
+                for ast::Argument(name, value) in arguments {
+                    self.expression(value, false);
+                    names.push(name.clone());
+                }
+
+                let callee = self.expression_ref(callee);
+                self.chunk.arguments.push(names);
+                self.chunk.locations.push(location.clone());
+                self.emit(Instruction::Call {
+                    callee,
+                    arguments: self.chunk.arguments.len() - 1,
+                    method: false,
+                    tail,
+                    location: self.chunk.locations.len() - 1,
+                });
+            }
+            ast::ExpressionTerm::Array(ast::Array(members)) => {
+                for member in members {
+                    self.expression(member, false);
+                }
+                self.emit(Instruction::Array(members.len()));
+            }
+            ast::ExpressionTerm::Object(ast::Object(members)) => {
+                let mut keys = Vec::new();
+                for (key, value) in members {
+                    self.expression(value, false);
+                    keys.push(key.clone());
+                }
+                self.chunk.object_keys.push(keys);
+                self.emit(Instruction::Object(self.chunk.object_keys.len() - 1));
+            }
+            ast::ExpressionTerm::MethodCall(_)
+            | ast::ExpressionTerm::ArraySlice(_)
+            | ast::ExpressionTerm::ArrayIndex(_)
+            | ast::ExpressionTerm::ObjectIndex(_) => {
+                for exit in self.chain_link(term, tail) {
+                    self.patch(exit);
+                }
+            }
+            ast::ExpressionTerm::If(if_term) => self.if_term(if_term, tail),
+            ast::ExpressionTerm::Lambda(definition) => {
+                let function = self.function(definition);
+                self.emit(Instruction::Lambda(function));
+            }
+        }
+    }
+
+    // like `evaluate_chain_base`, a chain of member accesses, indexes, slices
+    // and method calls ends as soon as one of its optional links comes up
+    // void. compiles the base of a link, returning the jumps that end the
+    // chain, which the outermost link points past itself.
+    fn chain_base(&mut self, base: &ast::Expression) -> Vec<usize> {
+        let ast::Expression::Term(term) = base else {
+            self.expression(base, false);
+            return Vec::new();
+        };
+        let optional = match term {
+            ast::ExpressionTerm::MethodCall(method_call) => method_call.optional,
+            ast::ExpressionTerm::ArrayIndex(array_index) => array_index.optional,
+            ast::ExpressionTerm::ObjectIndex(object_index) => object_index.optional,
+            ast::ExpressionTerm::ArraySlice(_) => false,
+            _ => {
+                self.expression(base, false);
+                return Vec::new();
+            }
+        };
+
+        let mut exits = stack::maybe_grow(|| self.chain_link(term, false));
+        if optional {
+            exits.push(self.emit(Instruction::JumpIfVoid(0)));
+        }
+        exits
+    }
+
+    // compiles a link of a chain, returning the jumps that end the chain
+    fn chain_link(&mut self, term: &ast::ExpressionTerm, tail: bool) -> Vec<usize> {
+        match term {
+            ast::ExpressionTerm::MethodCall(method_call) => {
+                let mut exits = self.chain_base(&method_call.base);
+                if method_call.optional {
+                    exits.push(self.emit(Instruction::JumpIfVoid(0)));
+                }
+
+                let variable = self.variable(&method_call.function, &method_call.address);
+                self.emit(Instruction::Method(variable));
+
+                let mut names = Vec::new();
+                for ast::Argument(name, value) in &method_call.arguments {
+                    self.expression(value, false);
+                    names.push(name.clone());
+                }
+
+                let callee =
+                    self.expression_ref(&ast::Expression::Term(ast::ExpressionTerm::Identifier(
+                        method_call.function.clone(),
+                        method_call.address.clone(),
+                    )));
+                self.chunk.arguments.push(names);
+                self.chunk.locations.push(method_call.location.clone());
+                self.emit(Instruction::Call {
+                    callee,
+                    arguments: self.chunk.arguments.len() - 1,
+                    method: true,
+                    tail,
+                    location: self.chunk.locations.len() - 1,
+                });
+
+                exits
+            }
+            ast::ExpressionTerm::ArraySlice(ast::ArraySlice { base, start, end }) => {
+                let exits = self.chain_base(base);
+                let base = self.expression_ref(base);
+                self.emit(Instruction::ToArray(base));
+
+                let start = self.slice_bound(start.as_deref());
+                let end = self.slice_bound(end.as_deref());
+                self.emit(Instruction::Slice { start, end });
+
+                exits
+            }
+            ast::ExpressionTerm::ArrayIndex(ast::ArrayIndex {
+                base,
+                index,
+                optional,
+            }) => {
+                let mut exits = self.chain_base(base);
+                if *optional {
+                    exits.push(self.emit(Instruction::JumpIfVoid(0)));
+                }
+
+                self.expression(index, false);
+                let base = self.expression_ref(base);
+                let index = self.expression_ref(index);
+                self.emit(Instruction::Index {
+                    base,
+                    index,
+                    optional: *optional,
+                });
+
+                exits
+            }
+            ast::ExpressionTerm::ObjectIndex(ast::ObjectIndex {
+                base,
+                index,
+                optional,
+            }) => {
+                let exits = self.chain_base(base);
+                let key = self.name(index);
+                let base = self.expression_ref(base);
+                self.emit(Instruction::Field {
+                    key,
+                    base,
+                    optional: *optional,
+                });
+
+                exits
+            }
+            term => {
+                self.term(term, tail);
+                Vec::new()
+            }
+        }
+    }
+
+    fn if_term(&mut self, if_term: &ast::If, tail: bool) {
+        let ast::If {
+            condition,
+            body,
+            elseif_branches,
+            else_branch,
+        } = if_term;
+
+        let branches = std::iter::once((&**condition, body))
+            .chain(elseif_branches.iter().map(|branch| (&branch.0, &branch.1)));
+
+        let mut exits = Vec::new();
+        for (condition, body) in branches {
+            self.expression(condition, false);
+            let condition = self.expression_ref(condition);
+            let next = self.emit(Instruction::JumpIfFalse {
+                target: 0,
+                condition,
+            });
+
+            self.block(body, tail);
+            exits.push(self.emit(Instruction::Jump(0)));
+            self.patch(next);
+        }
+
+        match else_branch {
+            Some(else_branch) => self.block(else_branch, tail),
+            None => {
+                self.emit(Instruction::Void);
+            }
+        }
+
+        for exit in exits {
+            self.patch(exit);
+        }
+    }
+
+    // pushes a slice bound cast to a number, or void if it's missing, and
+    // returns the expression it came from
+    fn slice_bound(&mut self, bound: Option<&ast::Expression>) -> Option<usize> {
+        let Some(bound) = bound else {
+            self.emit(Instruction::Void);
+            return None;
+        };
+
+        self.expression(bound, false);
+        let bound = self.expression_ref(bound);
+        self.emit(Instruction::ToNumber(bound));
+        Some(bound)
+    }
+
+    // stores the value on top of the stack into `target`, consuming it. like
+    // `assign_to_expression`, indexed targets are updated by evaluating their
+    // base, replacing the member and then assigning the result to the base.
+    fn assign(&mut self, target: &ast::Expression) {
+        match target {
+            ast::Expression::Term(ast::ExpressionTerm::Identifier(identifier, address)) => {
+                let variable = self.variable(identifier, address);
+                self.emit(Instruction::Assign(variable));
+            }
+            ast::Expression::Term(ast::ExpressionTerm::ArrayIndex(array_index))
+                if !array_index.optional =>
+            {
+                self.expression(&array_index.base, false);
+                self.expression(&array_index.index, false);
+                let base = self.expression_ref(&array_index.base);
+                let index = self.expression_ref(&array_index.index);
+                self.emit(Instruction::SetIndex { base, index });
+                self.assign(&array_index.base);
+            }
+            ast::Expression::Term(ast::ExpressionTerm::ObjectIndex(object_index))
+                if !object_index.optional =>
+            {
+                self.expression(&object_index.base, false);
+                let key = self.name(&object_index.index);
+                let base = self.expression_ref(&object_index.base);
+                self.emit(Instruction::SetField { key, base });
+                self.assign(&object_index.base);
+            }
+            target => {
+                let target = self.expression_ref(target);
+                self.emit(Instruction::NotAssignable(target));
+            }
+        }
+    }
+
+    fn function(&mut self, definition: &ast::Definition) -> usize {
+        self.chunk.functions.push(Prototype {
+            definition: Arc::new(definition.clone()),
+            bytecode: Arc::new(OnceLock::new()),
+        });
+        self.chunk.functions.len() - 1
+    }
+
+    fn name(&mut self, identifier: &ast::Identifier) -> usize {
+        match self.chunk.names.iter().position(|name| name == identifier) {
+            Some(index) => index,
+            None => {
+                self.chunk.names.push(identifier.clone());
+                self.chunk.names.len() - 1
+            }
+        }
+    }
+
+    fn variable(&mut self, identifier: &ast::Identifier, address: &ast::Address) -> usize {
+        let variable = (identifier.clone(), address.clone());
+        match self.chunk.variables.iter().position(|v| *v == variable) {
+            Some(index) => index,
+            None => {
+                self.chunk.variables.push(variable);
+                self.chunk.variables.len() - 1
+            }
+        }
+    }
+
+    fn expression_ref(&mut self, expression: &ast::Expression) -> usize {
+        self.chunk.expressions.push(expression.clone());
+        self.chunk.expressions.len() - 1
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.chunk.instructions.push(instruction);
+        self.chunk.instructions.len() - 1
+    }
+
+    // points the jump at `index` to the next instruction to be emitted
+    fn patch(&mut self, index: usize) {
+        let next = self.chunk.instructions.len();
+        match &mut self.chunk.instructions[index] {
+            Instruction::Jump(target)
+            | Instruction::JumpIfNotVoid(target)
+            | Instruction::JumpIfVoid(target)
+            | Instruction::JumpIfFalse { target, .. } => *target = next,
+            instruction => {
+                let error = RuntimeError::Internal(format!("{instruction:?} is not a jump"));
+                self.error.get_or_insert(error);
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use std::{collections::HashMap, sync::Arc, vec::Drain};
+
+use blox_language::{ast, location::Location};
+use im::{OrdMap, Vector};
+use rust_decimal::Decimal;
+
+use crate::{
+    budget::{allocation_size, operator_allocation_size},
+    expression::{
+        apply_operator, cast_to_array, cast_to_number, index_array, index_object, lookup_method,
+        set_array_index, set_object_field, slice_array, truthiness,
+    },
+    stack_trace::StackFrame,
+    statement::execute_import,
+    value::Function,
+    EvaluationContext, RuntimeError, Scope, Value,
+};
+
+use super::{
+    chunk::{Chunk, Instruction},
+    compiler::compile_function,
+};
+
+struct Frame {
+    chunk: Arc<Chunk>,
+    ip: usize,
+    scope: Arc<Scope>,
+    // the height of the stack when the frame was entered
+    base: usize,
+    // the function running in the frame and where it was called from, or
+    // `None` for the program itself
+    call: Option<(Function, Location)>,
+}
+
+// runs a compiled program in the context's scope. calls between blox
+// functions push frames onto a heap-allocated list rather than recursing, so
+// deep recursion doesn't overflow the Rust stack.
+pub fn run(chunk: Arc<Chunk>, context: &mut EvaluationContext) -> Result<Value, RuntimeError> {
+    let frame = Frame {
+        chunk,
+        ip: 0,
+        scope: context.scope.clone(),
+        base: 0,
+        call: None,
+    };
+
+    run_frame(frame, context)
+}
+
+// calls a function from rust, like an intrinsic calling a lambda it was
+// passed, running its compiled body as the first frame
+pub fn call(
+    function: Function,
+    arguments: Vec<Value>,
+    call_site: Location,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    let depth = context.budget.depth();
+    context.budget.enter_call()?;
+    let result = function_frame(function, arguments, call_site, 0)
+        .and_then(|frame| run_frame(frame, context));
+    context.budget.unwind_to(depth);
+
+    result
+}
+
+fn run_frame(mut frame: Frame, context: &mut EvaluationContext) -> Result<Value, RuntimeError> {
+    let mut callers: Vec<Frame> = Vec::new();
+
+    // frames still on the list when an error stops the program never return
+    let depth = context.budget.depth();
+    let result = execute(&mut frame, &mut callers, context);
+    context.budget.unwind_to(depth);
+
+    result.map_err(|error| {
+        std::iter::once(&frame)
+            .chain(callers.iter().rev())
+            .filter_map(|frame| frame.call.as_ref())
+            .fold(error, |error, (function, call_site)| {
+                error.traced(StackFrame::function(function, call_site))
+            })
+    })
+}
+
+// a frame that runs `function` with its parameters bound to `arguments`,
+// compiling its body the first time it's called
+fn function_frame(
+    function: Function,
+    arguments: Vec<Value>,
+    call_site: Location,
+    base: usize,
+) -> Result<Frame, RuntimeError> {
+    let definition = &function.definition;
+    let scope = function.closure.child_with_locals(definition.locals);
+    for (parameter, value) in definition.parameters.iter().zip(arguments) {
+        scope.declare(&parameter.0, &parameter.1, value, false)?;
+    }
+
+    let chunk = match function.bytecode.get() {
+        Some(chunk) => chunk.clone(),
+        None => {
+            let chunk = Arc::new(compile_function(definition)?);
+            function.bytecode.get_or_init(|| chunk).clone()
+        }
+    };
+
+    Ok(Frame {
+        chunk,
+        ip: 0,
+        scope,
+        base,
+        call: Some((function, call_site)),
+    })
+}
+
+fn execute(
+    frame: &mut Frame,
+    callers: &mut Vec<Frame>,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    let mut stack: Vec<Value> = Vec::new();
+
+    'frames: loop {
+        let chunk = frame.chunk.clone();
+
+        loop {
+            let instruction = &chunk.instructions[frame.ip];
+            frame.ip += 1;
+            context.budget.step()?;
+
+            match instruction {
+                Instruction::Constant(index) => stack.push(chunk.constants[*index].clone()),
+                Instruction::Void => stack.push(Value::Void),
+                Instruction::Pop => {
+                    pop(&mut stack)?;
+                }
+                Instruction::Duplicate => {
+                    let value = peek(&stack)?.clone();
+                    stack.push(value);
+                }
+
+                Instruction::Load(variable) => {
+                    let (name, address) = &chunk.variables[*variable];
+                    stack.push(frame.scope.lookup(name, address)?);
+                }
+                Instruction::Bind(variable) => {
+                    let (name, address) = &chunk.variables[*variable];
+                    let value = peek(&stack)?.clone();
+                    frame.scope.declare(name, address, value, false)?;
+                }
+                Instruction::BindMutable(variable) => {
+                    let (name, address) = &chunk.variables[*variable];
+                    let value = peek(&stack)?.clone();
+                    frame.scope.declare(name, address, value, true)?;
+                }
+                Instruction::Assign(variable) => {
+                    let (name, address) = &chunk.variables[*variable];
+                    let value = pop(&mut stack)?;
+                    frame.scope.assign(name, address, value)?;
+                }
+
+                Instruction::Define(index) => {
+                    let prototype = &chunk.functions[*index];
+                    let function = Value::Function(Function::with_bytecode(
+                        prototype.definition.clone(),
+                        frame.scope.clone(),
+                        prototype.bytecode.clone(),
+                    ));
+
+                    if let Some(name) = &prototype.definition.name {
+                        let address = &prototype.definition.address;
+                        frame
+                            .scope
+                            .declare(name, address, function.clone(), false)?;
+                    }
+
+                    stack.push(function);
+                }
+                Instruction::Lambda(index) => {
+                    let prototype = &chunk.functions[*index];
+                    stack.push(Value::Function(Function::with_bytecode(
+                        prototype.definition.clone(),
+                        frame.scope.clone(),
+                        prototype.bytecode.clone(),
+                    )));
+                }
+                Instruction::Import(index) => {
+                    let mut import_context = context.child_with_scope(frame.scope.clone());
+                    let module = execute_import(&chunk.imports[*index], &mut import_context)?;
+                    stack.push(module);
+                }
+
+                Instruction::Array(length) => {
+                    let members: Vector<Value> = pop_many(&mut stack, *length)?.collect();
+                    let array = Value::Array(members);
+                    context.budget.allocate(allocation_size(&array))?;
+                    stack.push(array);
+                }
+                Instruction::Object(index) => {
+                    let keys = &chunk.object_keys[*index];
+                    let values = pop_many(&mut stack, keys.len())?;
+                    let object: OrdMap<String, Value> = keys.iter().cloned().zip(values).collect();
+                    let object = Value::Object(object);
+                    context.budget.allocate(allocation_size(&object))?;
+                    stack.push(object);
+                }
+
+                Instruction::Binary { operator, lhs, rhs } => {
+                    let rhs_value = pop(&mut stack)?;
+                    let lhs_value = pop(&mut stack)?;
+
+                    let result = match apply_operator(&lhs_value, operator, &rhs_value) {
+                        Ok(result) => result,
+                        Err(error) => {
+                            return Err(error.into_runtime_error(
+                                &chunk.expressions[*lhs],
+                                lhs_value,
+                                operator,
+                                &chunk.expressions[*rhs],
+                                rhs_value,
+                            ));
+                        }
+                    };
+
+                    context
+                        .budget
+                        .allocate(operator_allocation_size(operator, &rhs_value, &result))?;
+                    stack.push(result);
+                }
+                Instruction::Index {
+                    base,
+                    index,
+                    optional,
+                } => {
+                    let index_value = pop(&mut stack)?;
+                    let array_value = pop(&mut stack)?;
+                    stack.push(index_array(
+                        array_value,
+                        index_value,
+                        *optional,
+                        &chunk.expressions[*base],
+                        &chunk.expressions[*index],
+                    )?);
+                }
+                Instruction::Field {
+                    key,
+                    base,
+                    optional,
+                } => {
+                    let object_value = pop(&mut stack)?;
+                    stack.push(index_object(
+                        object_value,
+                        &chunk.names[*key],
+                        *optional,
+                        &chunk.expressions[*base],
+                    )?);
+                }
+                Instruction::ToArray(expression) => {
+                    let value = pop(&mut stack)?;
+                    let array = cast_to_array(value, &chunk.expressions[*expression])?;
+                    stack.push(Value::Array(array));
+                }
+                Instruction::ToNumber(expression) => {
+                    let value = pop(&mut stack)?;
+                    let number = cast_to_number(value, &chunk.expressions[*expression])?;
+                    stack.push(Value::Number(number));
+                }
+                Instruction::Slice { start, end } => {
+                    let end_value = pop(&mut stack)?;
+                    let start_value = pop(&mut stack)?;
+                    let Value::Array(base) = pop(&mut stack)? else {
+                        return Err(RuntimeError::Internal(
+                            "a slice base wasn't cast to an array".to_string(),
+                        ));
+                    };
+
+                    stack.push(slice_array(
+                        base,
+                        slice_bound(start_value, *start, &chunk),
+                        slice_bound(end_value, *end, &chunk),
+                    )?);
+                }
+
+                Instruction::SetIndex { base, index } => {
+                    let index_value = pop(&mut stack)?;
+                    let base_value = pop(&mut stack)?;
+                    let value = pop(&mut stack)?;
+                    stack.push(set_array_index(
+                        base_value,
+                        index_value,
+                        value,
+                        &chunk.expressions[*base],
+                        &chunk.expressions[*index],
+                    )?);
+                }
+                Instruction::SetField { key, base } => {
+                    let base_value = pop(&mut stack)?;
+                    let value = pop(&mut stack)?;
+                    stack.push(set_object_field(
+                        base_value,
+                        &chunk.names[*key],
+                        value,
+                        &chunk.expressions[*base],
+                    )?);
+                }
+                Instruction::NotAssignable(expression) => {
+                    let value = pop(&mut stack)?;
+                    return Err(RuntimeError::LhsNotAssignable {
+                        expression: chunk.expressions[*expression].clone(),
+                        value,
+                    });
+                }
+
+                Instruction::Method(variable) => {
+                    let (name, address) = &chunk.variables[*variable];
+                    let receiver = pop(&mut stack)?;
+                    let (function, _) = lookup_method(name, address, &receiver, &frame.scope)?;
+                    stack.push(function);
+                    stack.push(receiver);
+                }
+                Instruction::Call {
+                    callee,
+                    arguments,
+                    method,
+                    tail,
+                    location,
+                } => {
+                    let names = &chunk.arguments[*arguments];
+                    let count = names.len() + usize::from(*method);
+                    let values: Vec<Value> = pop_many(&mut stack, count)?.collect();
+                    let function = pop(&mut stack)?;
+
+                    match function {
+                        Value::Function(function) => {
+                            let callee_frame = function_frame(
+                                function,
+                                values,
+                                chunk.locations[*location].clone(),
+                                stack.len(),
+                            )?;
+
+                            if *tail {
+                                stack.truncate(frame.base);
+                                *frame = Frame {
+                                    base: frame.base,
+                                    ..callee_frame
+                                };
+                            } else {
+                                context.budget.enter_call()?;
+                                callers.push(std::mem::replace(frame, callee_frame));
+                            }
+
+                            continue 'frames;
+                        }
+                        Value::Intrinsic(intrinsic) => {
+                            let receiver = method.then(|| ast::Identifier {
+                                name: intrinsic.receiver().to_string(),
+                            });
+                            let parameters: HashMap<_, _> = receiver
+                                .into_iter()
+                                .chain(names.iter().cloned())
+                                .zip(values)
+                                .collect();
+                            let call_site = &chunk.locations[*location];
+
+                            // like the tree walker, an intrinsic called in tail position
+                            // takes the place of its caller rather than adding to the depth
+                            if !*tail {
+                                context.budget.enter_call()?;
+                            }
+                            let result =
+                                intrinsic
+                                    .call(parameters, call_site, context)
+                                    .map_err(|error| {
+                                        error.traced(StackFrame::intrinsic(&intrinsic, call_site))
+                                    });
+                            if !*tail {
+                                context.budget.exit_call();
+                            }
+                            let result = result?;
+
+                            if *tail {
+                                stack.truncate(frame.base);
+                                match callers.pop() {
+                                    Some(caller) => {
+                                        context.budget.exit_call();
+                                        *frame = caller;
+                                        stack.push(result);
+                                        continue 'frames;
+                                    }
+                                    None => return Ok(result),
+                                }
+                            }
+
+                            stack.push(result);
+                        }
+                        function => {
+                            return Err(RuntimeError::NotAFunction {
+                                callee: chunk.expressions[*callee].clone(),
+                                value: function,
+                            });
+                        }
+                    }
+                }
+                Instruction::Return => {
+                    let value = pop(&mut stack)?;
+                    stack.truncate(frame.base);
+
+                    match callers.pop() {
+                        Some(caller) => {
+                            context.budget.exit_call();
+                            *frame = caller;
+                            stack.push(value);
+                            continue 'frames;
+                        }
+                        None => return Ok(value),
+                    }
+                }
+
+                Instruction::Jump(target) => frame.ip = *target,
+                Instruction::JumpIfFalse { target, condition } => {
+                    let condition_value = pop(&mut stack)?;
+                    match truthiness(&condition_value) {
+                        Some(true) => {}
+                        Some(false) => frame.ip = *target,
+                        None => {
+                            return Err(RuntimeError::InvalidCondition {
+                                condition_expression: chunk.expressions[*condition].clone(),
+                                condition_value,
+                            })
+                        }
+                    }
+                }
+                Instruction::JumpIfNotVoid(target) => {
+                    if *peek(&stack)? == Value::Void {
+                        pop(&mut stack)?;
+                    } else {
+                        frame.ip = *target;
+                    }
+                }
+                Instruction::JumpIfVoid(target) => {
+                    if *peek(&stack)? == Value::Void {
+                        frame.ip = *target;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// pairs a slice bound, which has been cast to a number unless it's missing,
+// with the expression it came from
+fn slice_bound(
+    value: Value,
+    expression: Option<usize>,
+    chunk: &Chunk,
+) -> Option<(Decimal, &ast::Expression)> {
+    match (value, expression) {
+        (Value::Number(bound), Some(expression)) => Some((bound, &chunk.expressions[expression])),
+        _ => None,
+    }
+}
+
+fn pop(stack: &mut Vec<Value>) -> Result<Value, RuntimeError> {
+    stack
+        .pop()
+        .ok_or_else(|| RuntimeError::Internal("bytecode popped an empty stack".to_string()))
+}
+
+// the top `count` values, in the order they were pushed
+fn pop_many(stack: &mut Vec<Value>, count: usize) -> Result<Drain<'_, Value>, RuntimeError> {
+    let start = stack.len().checked_sub(count).ok_or_else(|| {
+        RuntimeError::Internal(format!(
+            "bytecode popped {count} values off a shorter stack"
+        ))
+    })?;
+    Ok(stack.drain(start..))
+}
+
+fn peek(stack: &[Value]) -> Result<&Value, RuntimeError> {
+    stack
+        .last()
+        .ok_or_else(|| RuntimeError::Internal("bytecode peeked at an empty stack".to_string()))
+}
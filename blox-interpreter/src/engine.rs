@@ -0,0 +1,540 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use blox_language::{ast, error::ParseError, location::Location, parser::Parser};
+use jiff::Timestamp;
+
+use crate::{
+    budget::{Budget, Limits},
+    module::{load_stdlib_module, stdlib_modules, Backend, Clock, EvaluationContext},
+    native::NativeModule,
+    program::{call_function_value_at, execute_program},
+    RuntimeError, Scope, Value,
+};
+
+#[derive(Debug)]
+pub enum EngineError {
+    // a file given to `Engine::load_file` couldn't be read
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    ParseError(ParseError),
+    RuntimeError(RuntimeError),
+}
+
+impl std::fmt::Display for EngineError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::Io { path, error } => {
+                write!(f, "could not read {}: {error}", path.display())
+            }
+            EngineError::ParseError(error) => write!(f, "parse error: {error}"),
+            EngineError::RuntimeError(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for EngineError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EngineError::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseError> for EngineError {
+    fn from(error: ParseError) -> Self {
+        EngineError::ParseError(error)
+    }
+}
+
+impl From<RuntimeError> for EngineError {
+    fn from(error: RuntimeError) -> Self {
+        EngineError::RuntimeError(error)
+    }
+}
+
+// how an `Engine` is set up; see `Engine::builder`
+pub struct EngineBuilder {
+    import_roots: Vec<PathBuf>,
+    // the stdlib modules programs can import, or all of them
+    stdlib: Option<Vec<String>>,
+    native_modules: Vec<NativeModule>,
+    backend: Backend,
+    limits: Limits,
+    random_seed: Option<u64>,
+    clock: Option<Arc<Clock>>,
+}
+
+impl EngineBuilder {
+    // a directory modules are imported from. roots are searched in the order
+    // they're added; without any, modules are imported relative to the
+    // working directory.
+    pub fn import_root(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.import_roots.push(directory.into());
+        self
+    }
+
+    // only these stdlib modules, like `stdlib/math`, and the stdlib modules
+    // they import themselves, can be imported
+    pub fn stdlib(mut self, modules: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.stdlib = Some(
+            modules
+                .into_iter()
+                .map(|module| module.to_string())
+                .collect(),
+        );
+        self
+    }
+
+    pub fn without_stdlib(self) -> Self {
+        self.stdlib(std::iter::empty::<String>())
+    }
+
+    pub fn native_module(mut self, module: NativeModule) -> Self {
+        self.native_modules.push(module);
+        self
+    }
+
+    pub fn backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    // the limits each call into the engine runs with
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    // makes `random` and `random_int` from `stdlib/math` repeatable, e.g. for
+    // tests
+    pub fn random_seed(mut self, seed: u64) -> Self {
+        self.random_seed = Some(seed);
+        self
+    }
+
+    // where `now` from `stdlib/time` reads the time, e.g. a fixed instant so
+    // tests don't depend on when they run
+    pub fn clock(mut self, clock: impl Fn() -> Timestamp + Send + Sync + 'static) -> Self {
+        self.clock = Some(Arc::new(clock));
+        self
+    }
+
+    pub fn build(self) -> Result<Engine, EngineError> {
+        let mut roots = self
+            .import_roots
+            .iter()
+            .map(|root| root.to_string_lossy().to_string());
+
+        let mut context = EvaluationContext::new(
+            roots.next().unwrap_or_else(|| ".".to_string()),
+            Arc::new(Scope::default()),
+            Default::default(),
+        )
+        .with_backend(self.backend);
+        context.import_paths = roots.collect();
+        if let Some(seed) = self.random_seed {
+            context = context.with_random_seed(seed);
+        }
+        if let Some(clock) = self.clock {
+            context = context.with_clock(clock);
+        }
+
+        let stdlib = match self.stdlib {
+            Some(modules) => modules,
+            None => stdlib_modules().map(str::to_string).collect(),
+        };
+        for module in stdlib {
+            load_stdlib_module(&module, &mut context)?;
+        }
+
+        for module in self.native_modules {
+            context.native_modules.register(module)?;
+        }
+
+        Ok(Engine {
+            context,
+            limits: self.limits,
+        })
+    }
+}
+
+// runs blox for a rust program that embeds it, e.g. as a rules or
+// configuration language. bindings made by evaluated code, and globals set by
+// the host, last from one call into the engine to the next.
+pub struct Engine {
+    context: EvaluationContext,
+    limits: Limits,
+}
+
+impl Engine {
+    // an engine with the whole stdlib, importing relative to the working
+    // directory, without any limits
+    pub fn new() -> Result<Self, EngineError> {
+        Self::builder().build()
+    }
+
+    pub fn builder() -> EngineBuilder {
+        EngineBuilder {
+            import_roots: Vec::new(),
+            stdlib: None,
+            native_modules: Vec::new(),
+            backend: Backend::default(),
+            limits: Limits::default(),
+            random_seed: None,
+            clock: None,
+        }
+    }
+
+    pub fn eval(&mut self, source: &str) -> Result<Value, EngineError> {
+        self.run("<eval>", source)
+    }
+
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Value, EngineError> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|error| EngineError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        self.run(&path.to_string_lossy(), &source)
+    }
+
+    // calls a global blox function with named arguments, which can be given
+    // in any order
+    pub fn call<'a>(
+        &mut self,
+        function: &str,
+        arguments: impl IntoIterator<Item = (&'a str, Value)>,
+    ) -> Result<Value, EngineError> {
+        self.reset_budget();
+
+        let name = function;
+        let function = self.get_global(name)?;
+        let arguments = bind_arguments(name, &function, arguments)?;
+        Ok(call_function_value_at(
+            function,
+            arguments,
+            Location::synthetic("<engine>"),
+            &mut self.context,
+        )?)
+    }
+
+    pub fn set_global(&mut self, name: &str, value: Value) -> Result<(), EngineError> {
+        Ok(self
+            .context
+            .scope
+            .insert_binding(&identifier(name), value)?)
+    }
+
+    pub fn get_global(&self, name: &str) -> Result<Value, EngineError> {
+        Ok(self.context.scope.get_binding(&identifier(name))?)
+    }
+
+    // the context code is evaluated in, for anything the engine doesn't
+    // cover, like running programs with `execute_program_async`
+    pub fn context(&self) -> &EvaluationContext {
+        &self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut EvaluationContext {
+        &mut self.context
+    }
+
+    fn run(&mut self, file: &str, source: &str) -> Result<Value, EngineError> {
+        let program = Parser::new(file, source)?.parse()?;
+
+        self.reset_budget();
+        Ok(execute_program(&program, &mut self.context)?)
+    }
+
+    // every call into the engine gets the whole of its limits
+    fn reset_budget(&mut self) {
+        self.context.budget = Arc::new(Budget::new(self.limits.clone()));
+    }
+}
+
+// puts the arguments of a call from rust in the order of the function's
+// parameters, since blox functions take their arguments by position.
+// intrinsics take theirs by name already.
+fn bind_arguments<'a>(
+    name: &str,
+    function: &Value,
+    arguments: impl IntoIterator<Item = (&'a str, Value)>,
+) -> Result<Vec<(&'a str, Value)>, RuntimeError> {
+    let mut arguments: Vec<_> = arguments.into_iter().collect();
+    let Value::Function(function) = function else {
+        return Ok(arguments);
+    };
+
+    let mut bound = Vec::with_capacity(arguments.len());
+    for ast::Parameter(parameter, _) in &function.definition.parameters {
+        let Some(index) = arguments
+            .iter()
+            .position(|(argument, _)| *argument == parameter.name)
+        else {
+            return Err(RuntimeError::MissingArgument {
+                function: name.to_string(),
+                argument: parameter.name.clone(),
+            });
+        };
+        bound.push(arguments.remove(index));
+    }
+
+    match arguments.first() {
+        Some((argument, _)) => Err(RuntimeError::UnknownArgument {
+            function: name.to_string(),
+            argument: argument.to_string(),
+        }),
+        None => Ok(bound),
+    }
+}
+
+fn identifier(name: &str) -> ast::Identifier {
+    ast::Identifier {
+        name: name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Limit;
+
+    use super::*;
+
+    const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
+
+    fn engine(backend: Backend) -> Engine {
+        Engine::builder()
+            .backend(backend)
+            .build()
+            .expect("engine builds")
+    }
+
+    #[test]
+    fn test_globals() {
+        for backend in BACKENDS {
+            let mut engine = engine(backend);
+
+            engine
+                .set_global("discount", Value::Number(10.into()))
+                .expect("set global");
+            engine
+                .eval("let price = 100 - discount")
+                .expect("eval succeeds");
+
+            assert_eq!(
+                engine.get_global("price").expect("price is bound"),
+                Value::Number(90.into()),
+                "{backend:?}"
+            );
+            assert!(matches!(
+                engine.get_global("missing"),
+                Err(EngineError::RuntimeError(RuntimeError::UndefinedVariable(
+                    _
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_call() {
+        for backend in BACKENDS {
+            let mut engine = engine(backend);
+            engine
+                .eval("def total(price, quantity) { price * quantity }")
+                .expect("eval succeeds");
+
+            assert_eq!(
+                engine
+                    .call(
+                        "total",
+                        [
+                            ("price", Value::Number(3.into())),
+                            ("quantity", Value::Number(4.into())),
+                        ],
+                    )
+                    .expect("call succeeds"),
+                Value::Number(12.into()),
+                "{backend:?}"
+            );
+
+            assert_eq!(
+                engine
+                    .call(
+                        "total",
+                        [
+                            ("quantity", Value::Number(4.into())),
+                            ("price", Value::Number(3.into())),
+                        ],
+                    )
+                    .expect("call succeeds"),
+                Value::Number(12.into()),
+                "{backend:?}"
+            );
+            assert!(matches!(
+                engine.call("total", [("price", Value::Number(3.into()))]),
+                Err(EngineError::RuntimeError(RuntimeError::MissingArgument { argument, .. }))
+                    if argument == "quantity"
+            ));
+            assert!(matches!(
+                engine.call(
+                    "total",
+                    [
+                        ("price", Value::Number(3.into())),
+                        ("quantity", Value::Number(4.into())),
+                        ("discount", Value::Number(1.into())),
+                    ],
+                ),
+                Err(EngineError::RuntimeError(RuntimeError::UnknownArgument { argument, .. }))
+                    if argument == "discount"
+            ));
+
+            let Err(EngineError::RuntimeError(error)) = engine.call(
+                "total",
+                [
+                    ("price", Value::String("3".to_string())),
+                    ("quantity", Value::Number(4.into())),
+                ],
+            ) else {
+                panic!("call should fail ({backend:?})");
+            };
+            assert_eq!(error.stack_trace()[0].call_site.file.as_ref(), "<engine>");
+
+            assert!(matches!(
+                engine.call("missing", []),
+                Err(EngineError::RuntimeError(RuntimeError::UndefinedVariable(
+                    _
+                )))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_import_roots_and_stdlib() {
+        let first = std::env::temp_dir().join(format!("blox-engine-{}-a", std::process::id()));
+        let second = std::env::temp_dir().join(format!("blox-engine-{}-b", std::process::id()));
+        std::fs::create_dir_all(&first).expect("create first root");
+        std::fs::create_dir_all(&second).expect("create second root");
+        std::fs::write(second.join("rules.blox"), "let limit = 5").expect("write module");
+        std::fs::write(
+            first.join("main.blox"),
+            "import { limit } from 'rules'\nimport { pi } from 'stdlib/math'\nlimit",
+        )
+        .expect("write program");
+
+        let mut engine = Engine::builder()
+            .import_root(&first)
+            .import_root(&second)
+            .stdlib(["stdlib/math"])
+            .build()
+            .expect("engine builds");
+        assert_eq!(
+            engine
+                .load_file(first.join("main.blox"))
+                .expect("load succeeds"),
+            Value::Number(5.into())
+        );
+
+        let mut engine = Engine::builder()
+            .import_root(&first)
+            .import_root(&second)
+            .without_stdlib()
+            .build()
+            .expect("engine builds");
+        assert!(matches!(
+            engine.load_file(first.join("main.blox")),
+            Err(EngineError::RuntimeError(_))
+        ));
+
+        assert!(matches!(
+            engine.load_file(first.join("missing.blox")),
+            Err(EngineError::Io { .. })
+        ));
+
+        let mut engine = Engine::builder()
+            .stdlib(["stdlib/database"])
+            .build()
+            .expect("engine builds");
+        assert_eq!(
+            engine
+                .eval("import { table, column, select, to_sql } from 'stdlib/database'\nlet users = table(name: 'users')\nusers.select(column: users.column(name: 'name')).to_sql()")
+                .expect("eval succeeds"),
+            Value::String("SELECT users.name FROM users".to_string())
+        );
+
+        assert!(matches!(
+            Engine::builder().stdlib(["stdlib/missing"]).build(),
+            Err(EngineError::RuntimeError(RuntimeError::ModuleNotFound(_)))
+        ));
+
+        std::fs::remove_dir_all(first).expect("remove first root");
+        std::fs::remove_dir_all(second).expect("remove second root");
+    }
+
+    #[test]
+    fn test_native_modules() {
+        let mut engine = Engine::builder()
+            .native_module(
+                NativeModule::new("native/config").value("region", Value::Symbol("eu".to_string())),
+            )
+            .build()
+            .expect("engine builds");
+
+        assert_eq!(
+            engine
+                .eval("import { region } from 'native/config'\nregion")
+                .expect("eval succeeds"),
+            Value::Symbol("eu".to_string())
+        );
+    }
+
+    #[test]
+    fn test_random_seed() {
+        let rolls = || {
+            let mut engine = Engine::builder()
+                .random_seed(7)
+                .build()
+                .expect("engine builds");
+            engine
+                .eval("import { random_int } from 'stdlib/math'\n[random_int(min: 1, max: 1000), random_int(min: 1, max: 1000)]")
+                .expect("eval succeeds")
+        };
+
+        assert_eq!(rolls(), rolls());
+    }
+
+    #[test]
+    fn test_limits_apply_to_each_call() {
+        let mut engine = Engine::builder()
+            .limits(Limits {
+                max_steps: Some(1_000),
+                ..Limits::default()
+            })
+            .build()
+            .expect("engine builds");
+
+        engine
+            .eval("def spin(n) { spin(n: n + 1) }")
+            .expect("eval succeeds");
+
+        for _ in 0..2 {
+            let Err(EngineError::RuntimeError(error)) =
+                engine.call("spin", [("n", Value::Number(0.into()))])
+            else {
+                panic!("spin should run out of steps");
+            };
+            assert_eq!(
+                error.root_cause(),
+                &RuntimeError::LimitExceeded(Limit::Steps(1_000))
+            );
+        }
+
+        assert_eq!(engine.eval("1 + 1").ok(), Some(Value::Number(2.into())));
+    }
+}
@@ -1,11 +1,21 @@
 use blox_language::{ast, error::ParseError};
 
-use crate::{module::Module, Value};
+use crate::{
+    budget::Limit,
+    module::Module,
+    stack_trace::{write_stack_trace, StackFrame},
+    Value,
+};
 
+// an error that stopped a program. errors that pass out of a blox function
+// or intrinsic call come wrapped in `Traced`, along with the calls they passed
+// through, so code that looks for a particular error, like a limit being
+// exceeded, should match on `root_cause()` rather than the error itself.
 #[derive(Debug, PartialEq)]
 pub enum RuntimeError {
     ParseError(ParseError),
     UndefinedVariable(String),
+    ImmutableBinding(String),
     InvalidOperands {
         lhs_expression: ast::Expression,
         lhs_value: Value,
@@ -13,6 +23,13 @@ pub enum RuntimeError {
         rhs_expression: ast::Expression,
         rhs_value: Value,
     },
+    ArithmeticOverflow {
+        lhs_expression: ast::Expression,
+        lhs_value: Value,
+        operator: ast::Operator,
+        rhs_expression: ast::Expression,
+        rhs_value: Value,
+    },
     InvalidCondition {
         condition_expression: ast::Expression,
         condition_value: Value,
@@ -29,6 +46,10 @@ pub enum RuntimeError {
         index_expression: ast::Expression,
         index_value: Value,
     },
+    InvalidSliceBound {
+        expression: ast::Expression,
+        value: Value,
+    },
     NotAnArray {
         expression: ast::Expression,
         value: Value,
@@ -61,6 +82,121 @@ pub enum RuntimeError {
         expression: ast::Expression,
         value: Value,
     },
+    LimitExceeded(Limit),
+    // a lock was poisoned by a thread that panicked while holding it
+    PoisonedLock(&'static str),
+    // a method call's name isn't bound, and the receiver has no built-in
+    // method by that name
+    UnknownMethod {
+        method: ast::Identifier,
+        value: Value,
+    },
+    // an intrinsic, or a function called from rust, was called without an
+    // argument it needs
+    MissingArgument {
+        function: String,
+        argument: String,
+    },
+    // a function called from rust was passed an argument it has no parameter
+    // for
+    UnknownArgument {
+        function: String,
+        argument: String,
+    },
+    // an intrinsic was called with an argument of the wrong type
+    InvalidArgument {
+        function: String,
+        argument: String,
+        // e.g. "a string"
+        expected: String,
+        value: Value,
+    },
+    // a function passed to an intrinsic returned the wrong type of value,
+    // like a `filter` condition that isn't a boolean
+    InvalidCallbackResult {
+        function: String,
+        argument: String,
+        // e.g. "a boolean"
+        expected: String,
+        value: Value,
+    },
+    // an intrinsic's result is too large for a number
+    NumberOverflow {
+        function: String,
+    },
+    // an async intrinsic was called by a program running synchronously on an
+    // async runtime's worker thread, which it would have blocked
+    BlockingAsyncCall(String),
+    // the async runtime couldn't run the program or one of its intrinsics
+    AsyncRuntime(String),
+    // a regular expression couldn't be compiled. the message shows where in
+    // the pattern the problem is.
+    InvalidPattern {
+        pattern: String,
+        message: String,
+    },
+    // `parse` from `stdlib/json` was given text that isn't JSON. lines and
+    // columns count from 1, and columns count bytes.
+    InvalidJson {
+        message: String,
+        line: usize,
+        column: usize,
+    },
+    // a time couldn't be parsed, formatted or moved to another time zone
+    TimeError(String),
+    // an assertion from `stdlib/test` failed
+    AssertionFailed(String),
+    // a value couldn't be converted to or from a rust type through serde
+    SerializationError(String),
+    // the interpreter got into a state it should never be in, like bytecode
+    // popping an empty stack. always a bug in blox, never in the program.
+    Internal(String),
+    // an error that happened inside blox function calls, along with the calls
+    // that were in progress, innermost first. never wraps another `Traced`.
+    Traced {
+        error: Box<RuntimeError>,
+        stack: Vec<StackFrame>,
+    },
+}
+
+impl RuntimeError {
+    // records that the error passed out of a call as it unwinds
+    pub(crate) fn traced(self, frame: StackFrame) -> Self {
+        match self {
+            RuntimeError::Traced { error, mut stack } => {
+                stack.push(frame);
+                RuntimeError::Traced { error, stack }
+            }
+            error => RuntimeError::Traced {
+                error: Box::new(error),
+                stack: vec![frame],
+            },
+        }
+    }
+
+    // the error itself, without the calls it passed through
+    pub fn root_cause(&self) -> &RuntimeError {
+        match self {
+            RuntimeError::Traced { error, .. } => error,
+            error => error,
+        }
+    }
+
+    // `root_cause`, for an error that's no longer needed
+    pub fn into_root_cause(self) -> RuntimeError {
+        match self {
+            RuntimeError::Traced { error, .. } => *error,
+            error => error,
+        }
+    }
+
+    // the calls that were in progress when the error happened, innermost first
+    pub fn stack_trace(&self) -> &[StackFrame] {
+        match self {
+            RuntimeError::Traced { stack, .. } => stack,
+            _ => &[],
+        }
+    }
 }
 
 impl std::error::Error for RuntimeError {}
@@ -76,6 +212,13 @@ impl std::fmt::Display for RuntimeError {
                 write!(f, "undefined variable: {}", name)
             }
 
+            RuntimeError::ImmutableBinding(name) => {
+                write!(
+                    f,
+                    "cannot change {name}: only variables declared with var can change, not let variables or parameters"
+                )
+            }
+
             RuntimeError::InvalidOperands {
                 lhs_expression,
                 lhs_value,
@@ -88,6 +231,18 @@ impl std::fmt::Display for RuntimeError {
                     "invalid operands: {operator} cannot be used for {lhs_expression} (={lhs_value}) and {rhs_expression} (={rhs_value})"
                 )
             }
+            RuntimeError::ArithmeticOverflow {
+                lhs_expression,
+                lhs_value,
+                operator,
+                rhs_expression,
+                rhs_value,
+            } => {
+                write!(
+                    f,
+                    "arithmetic overflow: {lhs_expression} (={lhs_value}) {operator} {rhs_expression} (={rhs_value})"
+                )
+            }
             RuntimeError::InvalidCondition {
                 condition_expression,
                 condition_value,
@@ -116,6 +271,12 @@ impl std::fmt::Display for RuntimeError {
             } => {
                 write!(f, "array index out of bounds: {array_expression} (={array_value})[{index_expression} (={index_value})]")
             }
+            RuntimeError::InvalidSliceBound { expression, value } => {
+                write!(
+                    f,
+                    "invalid slice bound: {expression} (={value}) must not be negative"
+                )
+            }
             RuntimeError::NotAnArray { expression, value } => {
                 write!(f, "{expression} (={value}) is not an array")
             }
@@ -157,6 +318,82 @@ impl std::fmt::Display for RuntimeError {
             RuntimeError::LhsNotAssignable { expression, value } => {
                 write!(f, "cannot assign to {expression} (={value})")
             }
+            RuntimeError::LimitExceeded(limit) => {
+                write!(f, "execution limit exceeded: {limit}")
+            }
+            RuntimeError::PoisonedLock(lock) => {
+                write!(
+                    f,
+                    "the {lock} is unusable because a thread panicked while using it"
+                )
+            }
+            RuntimeError::UnknownMethod { method, value } => {
+                write!(
+                    f,
+                    "{method} is not defined, and {} has no method named {method}",
+                    with_article(value.type_name())
+                )
+            }
+            RuntimeError::MissingArgument { function, argument } => {
+                write!(f, "{function} needs an argument named {argument}")
+            }
+            RuntimeError::UnknownArgument { function, argument } => {
+                write!(f, "{function} has no parameter named {argument}")
+            }
+            RuntimeError::InvalidArgument {
+                function,
+                argument,
+                expected,
+                value,
+            } => {
+                write!(
+                    f,
+                    "argument {argument} of {function} must be {expected}, not {value} ({})",
+                    with_article(value.type_name())
+                )
+            }
+            RuntimeError::InvalidCallbackResult {
+                function,
+                argument,
+                expected,
+                value,
+            } => {
+                write!(
+                    f,
+                    "argument {argument} of {function} must return {expected}, not {value} ({})",
+                    with_article(value.type_name())
+                )
+            }
+            RuntimeError::NumberOverflow { function } => {
+                write!(f, "the result of {function} is too large for a number")
+            }
+            RuntimeError::BlockingAsyncCall(intrinsic) => {
+                write!(
+                    f,
+                    "the async intrinsic {intrinsic} would block the async runtime; run the program with execute_program_async"
+                )
+            }
+            RuntimeError::AsyncRuntime(message) => write!(f, "async runtime error: {message}"),
+            RuntimeError::InvalidPattern { message, .. } => {
+                write!(f, "invalid pattern:\n{message}")
+            }
+            RuntimeError::InvalidJson {
+                message,
+                line,
+                column,
+            } => {
+                write!(f, "invalid JSON at line {line}, column {column}: {message}")
+            }
+            RuntimeError::TimeError(message) => write!(f, "{message}"),
+            RuntimeError::AssertionFailed(message) => write!(f, "assertion failed: {message}"),
+            RuntimeError::SerializationError(message) => {
+                write!(f, "could not convert value: {message}")
+            }
+            RuntimeError::Internal(message) => write!(f, "internal error: {message}"),
+            RuntimeError::Traced { error, stack } => {
+                write!(f, "{error}")?;
+                write_stack_trace(f, stack)
+            }
         }
     }
 }
@@ -172,3 +409,11 @@ impl From<rust_decimal::Error> for RuntimeError {
         RuntimeError::DecimalConversionError(error)
     }
 }
+
+// "a string", "an array"
+pub(crate) fn with_article(noun: &str) -> String {
+    match noun.starts_with(['a', 'e', 'i', 'o', 'u']) {
+        true => format!("an {noun}"),
+        false => format!("a {noun}"),
+    }
+}
@@ -5,11 +5,26 @@ mod condition;
 mod expression_term;
 mod function_call;
 mod method_call;
+mod tail_call;
 
 use blox_language::ast;
 use tracing::Level;
 
-use crate::{module::EvaluationContext, RuntimeError, Value};
+use crate::{module::EvaluationContext, stack, RuntimeError, Value};
+
+pub use self::tail_call::{evaluate_tail_expression, Completion};
+
+// the pieces of evaluation that don't depend on how operands are evaluated,
+// shared with the bytecode machine
+pub(crate) use self::{
+    assignment::{is_assignable, set_array_index, set_object_field},
+    binary_expression::apply_operator,
+    casting::{cast_to_array, cast_to_number},
+    condition::truthiness,
+    expression_term::{index_array, index_object, slice_array},
+    function_call::{call_function, Arguments},
+    method_call::lookup_method,
+};
 
 use self::{
     assignment::assign_to_expression, binary_expression::evaluate_binary_expression,
@@ -21,11 +36,11 @@ pub fn evaluate_expression(
     expression: &ast::Expression,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
+    context.budget.step()?;
+
     // grow the stack here if needed, to avoid stack overflows on deeply nested expressions
-    const STACK_RED_ZONE: usize = 128 * 1024; // grow when there's less than this amount remaining
-    const STACK_BLOCK_SIZE: usize = 1024 * 1024; // grow by 1MB at a time
 
-    stacker::maybe_grow(STACK_RED_ZONE, STACK_BLOCK_SIZE, || match expression {
+    stack::maybe_grow(|| match expression {
         ast::Expression::Term(term) => evaluate_expression_term(term, context),
         ast::Expression::BinaryExpression(lhs, operator, rhs) => {
             evaluate_binary_expression(lhs, operator, rhs, context)
@@ -46,7 +61,7 @@ mod tests {
     use super::evaluate_expression;
 
     fn parse_expression<'a>(code: String) -> Result<Expression, ParseError> {
-        let parser = Parser::new("<test>", &code);
+        let parser = Parser::new("<test>", &code)?;
         Ok(parser.parse_as_expression()?)
     }
 
@@ -55,12 +70,15 @@ mod tests {
         let expression = parse_expression("x + 1".to_string()).expect("parse error");
 
         let mut context = EvaluationContext::default();
-        context.scope.insert_binding(
-            &Identifier {
-                name: "x".to_string(),
-            },
-            Value::Number(55.into()),
-        );
+        context
+            .scope
+            .insert_binding(
+                &Identifier {
+                    name: "x".to_string(),
+                },
+                Value::Number(55.into()),
+            )
+            .expect("insert binding");
 
         let result = evaluate_expression(&expression, &mut context);
         assert_eq!(result, Ok(Value::Number(56.into())));
@@ -71,18 +89,24 @@ mod tests {
         let expression = parse_expression("x + y".to_string()).expect("parse error");
 
         let mut context = EvaluationContext::default();
-        context.scope.insert_binding(
-            &Identifier {
-                name: "x".to_string(),
-            },
-            Value::Number(55.into()),
-        );
-        context.scope.insert_binding(
-            &Identifier {
-                name: "y".to_string(),
-            },
-            Value::Number(42.into()),
-        );
+        context
+            .scope
+            .insert_binding(
+                &Identifier {
+                    name: "x".to_string(),
+                },
+                Value::Number(55.into()),
+            )
+            .expect("insert binding");
+        context
+            .scope
+            .insert_binding(
+                &Identifier {
+                    name: "y".to_string(),
+                },
+                Value::Number(42.into()),
+            )
+            .expect("insert binding");
 
         let result = evaluate_expression(&expression, &mut context);
         assert_eq!(result, Ok(Value::Number(97.into())));
@@ -3,9 +3,11 @@ mod identifier;
 mod object_index;
 
 use array_index::assign_to_array_index;
+pub use array_index::set_array_index;
 use blox_language::ast;
 use identifier::assign_to_identifier;
 use object_index::assign_to_object_index;
+pub use object_index::set_object_field;
 use tracing::Level;
 
 use crate::{EvaluationContext, RuntimeError, Value};
@@ -17,13 +19,17 @@ pub fn assign_to_expression(
     context: &mut EvaluationContext,
 ) -> Result<(), RuntimeError> {
     match target {
-        ast::Expression::Term(ast::ExpressionTerm::Identifier(identifier)) => {
-            assign_to_identifier(identifier, value, context)
+        ast::Expression::Term(ast::ExpressionTerm::Identifier(identifier, address)) => {
+            assign_to_identifier(identifier, address, value, context)
         }
-        ast::Expression::Term(ast::ExpressionTerm::ArrayIndex(array_index)) => {
+        ast::Expression::Term(ast::ExpressionTerm::ArrayIndex(array_index))
+            if !array_index.optional =>
+        {
             assign_to_array_index(array_index, value, context)
         }
-        ast::Expression::Term(ast::ExpressionTerm::ObjectIndex(object_index)) => {
+        ast::Expression::Term(ast::ExpressionTerm::ObjectIndex(object_index))
+            if !object_index.optional =>
+        {
             assign_to_object_index(object_index, value, context)
         }
 
@@ -33,3 +39,17 @@ pub fn assign_to_expression(
         }),
     }
 }
+
+// whether `target` names a binding, or a part of one, that `assign_to_expression` can update
+pub fn is_assignable(target: &ast::Expression) -> bool {
+    match target {
+        ast::Expression::Term(ast::ExpressionTerm::Identifier(..)) => true,
+        ast::Expression::Term(ast::ExpressionTerm::ArrayIndex(array_index)) => {
+            !array_index.optional && is_assignable(&array_index.base)
+        }
+        ast::Expression::Term(ast::ExpressionTerm::ObjectIndex(object_index)) => {
+            !object_index.optional && is_assignable(&object_index.base)
+        }
+        _ => false,
+    }
+}
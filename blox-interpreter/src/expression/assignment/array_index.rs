@@ -9,39 +9,52 @@ pub fn assign_to_array_index(
     value: Value,
     context: &mut EvaluationContext,
 ) -> Result<(), RuntimeError> {
-    let ast::ArrayIndex { base, index } = array_index;
+    let ast::ArrayIndex { base, index, .. } = array_index;
 
-    let base_value = evaluate_expression(&base, context)?;
-    let index_value = evaluate_expression(&index, context)?;
+    let base_value = evaluate_expression(base, context)?;
+    let index_value = evaluate_expression(index, context)?;
 
+    let base_value = set_array_index(base_value, index_value, value, base, index)?;
+    assign_to_expression(base, base_value, context)
+}
+
+// returns a copy of an already-evaluated array with one member replaced;
+// `base` and `index` are the expressions used for error reporting
+pub fn set_array_index(
+    base_value: Value,
+    index_value: Value,
+    value: Value,
+    base: &ast::Expression,
+    index: &ast::Expression,
+) -> Result<Value, RuntimeError> {
     match (&base_value, &index_value) {
         (Value::Array(ref members), Value::Number(idx)) => {
             let Ok(idx): rust_decimal::Result<usize> = (*idx).try_into() else {
                 return Err(RuntimeError::InvalidArrayIndex {
-                    array_expression: *base.clone(),
+                    array_expression: base.clone(),
                     array_value: base_value.clone(),
-                    index_expression: *index.clone(),
+                    index_expression: index.clone(),
                     index_value: index_value.clone(),
                 });
             };
 
             if idx < members.len() {
                 let mut members = members.clone();
-                members[idx] = value;
-                assign_to_expression(&base, Value::Array(members), context)
+                members.set(idx, value);
+                Ok(Value::Array(members))
             } else {
                 Err(RuntimeError::ArrayIndexOutOfBounds {
-                    array_expression: *base.clone(),
+                    array_expression: base.clone(),
                     array_value: base_value.clone(),
-                    index_expression: *index.clone(),
+                    index_expression: index.clone(),
                     index_value: index_value.clone(),
                 })
             }
         }
         (base_value, index_value) => Err(RuntimeError::InvalidArrayIndex {
-            array_expression: *base.clone(),
+            array_expression: base.clone(),
             array_value: base_value.clone(),
-            index_expression: *index.clone(),
+            index_expression: index.clone(),
             index_value: index_value.clone(),
         }),
     }
@@ -1,12 +1,12 @@
-use blox_language::ast::Identifier;
+use blox_language::ast::{Address, Identifier};
 
 use crate::{EvaluationContext, RuntimeError, Value};
 
 pub fn assign_to_identifier(
     identifier: &Identifier,
+    address: &Address,
     value: Value,
     context: &mut EvaluationContext,
 ) -> Result<(), RuntimeError> {
-    context.scope.insert_binding(&identifier, value);
-    Ok(())
+    context.scope.assign(identifier, address, value)
 }
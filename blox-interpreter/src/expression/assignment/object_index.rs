@@ -9,16 +9,28 @@ pub fn assign_to_object_index(
     value: Value,
     context: &mut EvaluationContext,
 ) -> Result<(), RuntimeError> {
-    let ast::ObjectIndex { base, index } = object_index;
-    let base_value = evaluate_expression(&base, context)?;
+    let ast::ObjectIndex { base, index, .. } = object_index;
+    let base_value = evaluate_expression(base, context)?;
 
+    let base_value = set_object_field(base_value, index, value, base)?;
+    assign_to_expression(base, base_value, context)
+}
+
+// returns a copy of an already-evaluated object with one key set; `base` is
+// the expression it came from, used for error reporting
+pub fn set_object_field(
+    base_value: Value,
+    index: &ast::Identifier,
+    value: Value,
+    base: &ast::Expression,
+) -> Result<Value, RuntimeError> {
     match base_value {
         Value::Object(mut members) => {
             members.insert(index.name.clone(), value);
-            assign_to_expression(&base, Value::Object(members), context)
+            Ok(Value::Object(members))
         }
         base_value => Err(RuntimeError::NotAnObject {
-            object_expression: *base.clone(),
+            object_expression: base.clone(),
             object_value: base_value.clone(),
             key: index.name.clone(),
         }),
@@ -2,7 +2,8 @@ use blox_language::ast;
 use tracing::{trace, Level};
 
 use crate::{
-    expression::{assign_to_expression, evaluate_expression},
+    budget::operator_allocation_size,
+    expression::{assign_to_expression, assignment::is_assignable, evaluate_expression},
     EvaluationContext, RuntimeError, Value,
 };
 
@@ -13,107 +14,161 @@ pub fn evaluate_binary_expression(
     rhs: &Box<ast::Expression>,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
+    // the left-hand side of an assignment names the binding to update rather
+    // than producing a value, so only the right-hand side is evaluated
+    if *operator == ast::Operator::Assignment {
+        let value = evaluate_expression(rhs, context)?;
+        assign_to_expression(lhs, value.clone(), context)?;
+        return Ok(value);
+    }
+
     let lhs_value = evaluate_expression(lhs, context)?;
+
+    // `??` only evaluates its right-hand side when the left-hand side is void
+    if *operator == ast::Operator::Coalesce {
+        return match lhs_value {
+            Value::Void => evaluate_expression(rhs, context),
+            lhs_value => Ok(lhs_value),
+        };
+    }
+
     let rhs_value = evaluate_expression(rhs, context)?;
 
-    let result = match (&lhs_value, operator, &rhs_value) {
+    let result = match apply_operator(&lhs_value, operator, &rhs_value) {
+        Ok(result) => result,
+        Err(error) => {
+            return Err(error.into_runtime_error(lhs, lhs_value, operator, rhs, rhs_value));
+        }
+    };
+
+    context
+        .budget
+        .allocate(operator_allocation_size(operator, &rhs_value, &result))?;
+
+    // `<<` writes the new array back when its left-hand side names a binding,
+    // which must be mutable; on any other expression it just produces a new array
+    if *operator == ast::Operator::Append && is_assignable(lhs) {
+        assign_to_expression(lhs, result.clone(), context)?;
+    }
+
+    trace!("{lhs_value} {operator} {rhs_value} => {result}");
+
+    Ok(result)
+}
+
+// why an operator couldn't be applied to its operands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OperatorError {
+    // the operator isn't defined for operands of these types
+    InvalidOperands,
+    // the result of arithmetic is too large to represent
+    Overflow,
+}
+
+impl OperatorError {
+    pub fn into_runtime_error(
+        self,
+        lhs_expression: &ast::Expression,
+        lhs_value: Value,
+        operator: &ast::Operator,
+        rhs_expression: &ast::Expression,
+        rhs_value: Value,
+    ) -> RuntimeError {
+        match self {
+            OperatorError::InvalidOperands => RuntimeError::InvalidOperands {
+                lhs_expression: lhs_expression.clone(),
+                lhs_value,
+                operator: operator.clone(),
+                rhs_expression: rhs_expression.clone(),
+                rhs_value,
+            },
+            OperatorError::Overflow => RuntimeError::ArithmeticOverflow {
+                lhs_expression: lhs_expression.clone(),
+                lhs_value,
+                operator: operator.clone(),
+                rhs_expression: rhs_expression.clone(),
+                rhs_value,
+            },
+        }
+    }
+}
+
+// applies an operator that has already had both of its operands evaluated
+pub fn apply_operator(
+    lhs_value: &Value,
+    operator: &ast::Operator,
+    rhs_value: &Value,
+) -> Result<Value, OperatorError> {
+    let result = match (lhs_value, operator, rhs_value) {
         (Value::Number(lhs), ast::Operator::Add, Value::Number(rhs)) => {
-            Ok(Value::Number(lhs + rhs))
+            let result = lhs.checked_add(*rhs).ok_or(OperatorError::Overflow)?;
+            Some(Value::Number(result))
         }
         (Value::Number(lhs), ast::Operator::Subtract, Value::Number(rhs)) => {
-            Ok(Value::Number(lhs - rhs))
+            let result = lhs.checked_sub(*rhs).ok_or(OperatorError::Overflow)?;
+            Some(Value::Number(result))
         }
         (Value::Number(lhs), ast::Operator::Multiply, Value::Number(rhs)) => {
-            Ok(Value::Number(lhs * rhs))
+            let result = lhs.checked_mul(*rhs).ok_or(OperatorError::Overflow)?;
+            Some(Value::Number(result))
         }
-        (Value::String(lhs), ast::Operator::Concatenate, Value::String(rhs)) => {
-            Ok(Value::String(format!("{lhs}{rhs}")))
+        // times move by durations, and the difference between two times is
+        // the duration between them
+        (Value::Time(lhs), ast::Operator::Add, Value::Duration(rhs))
+        | (Value::Duration(rhs), ast::Operator::Add, Value::Time(lhs)) => {
+            let result = lhs.checked_add(*rhs).map_err(|_| OperatorError::Overflow)?;
+            Some(Value::Time(result))
         }
-        (Value::Array(lhs), ast::Operator::Concatenate, Value::Array(rhs)) => {
-            let mut result = lhs.clone();
-            result.extend(rhs.clone());
-            Ok(Value::Array(result))
+        (Value::Time(lhs), ast::Operator::Subtract, Value::Duration(rhs)) => {
+            let result = lhs.checked_sub(*rhs).map_err(|_| OperatorError::Overflow)?;
+            Some(Value::Time(result))
         }
-
-        (Value::Array(lhs), ast::Operator::Equal, Value::Array(rhs)) => {
-            Ok(Value::Boolean(lhs == rhs))
+        (Value::Time(lhs), ast::Operator::Subtract, Value::Time(rhs)) => {
+            Some(Value::Duration(lhs.duration_since(rhs)))
         }
-        (Value::Boolean(lhs), ast::Operator::Equal, Value::Boolean(rhs)) => {
-            Ok(Value::Boolean(lhs == rhs))
+        (Value::Duration(lhs), ast::Operator::Add, Value::Duration(rhs)) => {
+            let result = lhs.checked_add(*rhs).ok_or(OperatorError::Overflow)?;
+            Some(Value::Duration(result))
         }
-        (Value::Number(lhs), ast::Operator::Equal, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs == rhs))
+        (Value::Duration(lhs), ast::Operator::Subtract, Value::Duration(rhs)) => {
+            let result = lhs.checked_sub(*rhs).ok_or(OperatorError::Overflow)?;
+            Some(Value::Duration(result))
         }
-        (Value::String(lhs), ast::Operator::Equal, Value::String(rhs)) => {
-            Ok(Value::Boolean(lhs == rhs))
+        (Value::String(lhs), ast::Operator::Concatenate, Value::String(rhs)) => {
+            Some(Value::String(format!("{lhs}{rhs}")))
         }
-        (Value::Symbol(lhs), ast::Operator::Equal, Value::Symbol(rhs)) => {
-            Ok(Value::Boolean(lhs == rhs))
+        (Value::Array(lhs), ast::Operator::Concatenate, Value::Array(rhs)) => {
+            let mut result = lhs.clone();
+            result.append(rhs.clone());
+            Some(Value::Array(result))
         }
-        (_, ast::Operator::Equal, _) => Ok(Value::Boolean(false)),
 
-        (Value::Array(lhs), ast::Operator::NotEqual, Value::Array(rhs)) => {
-            Ok(Value::Boolean(lhs != rhs))
-        }
-        (Value::Boolean(lhs), ast::Operator::NotEqual, Value::Boolean(rhs)) => {
-            Ok(Value::Boolean(lhs != rhs))
-        }
-        (Value::Number(lhs), ast::Operator::NotEqual, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs != rhs))
-        }
-        (Value::String(lhs), ast::Operator::NotEqual, Value::String(rhs)) => {
-            Ok(Value::Boolean(lhs != rhs))
-        }
-        (Value::Symbol(lhs), ast::Operator::NotEqual, Value::Symbol(rhs)) => {
-            Ok(Value::Boolean(lhs != rhs))
-        }
-        (_, ast::Operator::NotEqual, _) => Ok(Value::Boolean(false)),
+        (lhs, ast::Operator::Equal, rhs) => Some(Value::Boolean(lhs == rhs)),
+        (lhs, ast::Operator::NotEqual, rhs) => Some(Value::Boolean(lhs != rhs)),
 
-        (Value::Number(lhs), ast::Operator::GreaterOrEqual, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs >= rhs))
+        // ordering is only defined between values of the same type; anything
+        // else is an invalid operands error
+        (lhs, ast::Operator::GreaterOrEqual, rhs) if lhs.compare(rhs).is_some() => {
+            Some(Value::Boolean(lhs >= rhs))
         }
-        (_, ast::Operator::GreaterOrEqual, _) => Ok(Value::Boolean(false)),
-
-        (Value::Number(lhs), ast::Operator::GreaterThan, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs > rhs))
+        (lhs, ast::Operator::GreaterThan, rhs) if lhs.compare(rhs).is_some() => {
+            Some(Value::Boolean(lhs > rhs))
         }
-        (_, ast::Operator::GreaterThan, _) => Ok(Value::Boolean(false)),
-
-        (Value::Number(lhs), ast::Operator::LessOrEqual, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs <= rhs))
+        (lhs, ast::Operator::LessOrEqual, rhs) if lhs.compare(rhs).is_some() => {
+            Some(Value::Boolean(lhs <= rhs))
         }
-        (_, ast::Operator::LessOrEqual, _) => Ok(Value::Boolean(false)),
-
-        (Value::Number(lhs), ast::Operator::LessThan, Value::Number(rhs)) => {
-            Ok(Value::Boolean(lhs < rhs))
+        (lhs, ast::Operator::LessThan, rhs) if lhs.compare(rhs).is_some() => {
+            Some(Value::Boolean(lhs < rhs))
         }
-        (_, ast::Operator::LessThan, _) => Ok(Value::Boolean(false)),
 
         (Value::Array(lhs), ast::Operator::Append, rhs) => {
             let mut lhs = lhs.clone();
-            lhs.push(rhs.clone());
-            Ok(Value::Array(lhs))
+            lhs.push_back(rhs.clone());
+            Some(Value::Array(lhs))
         }
 
-        (lhs_value, operator, rhs_value) => Err(RuntimeError::InvalidOperands {
-            lhs_expression: *lhs.clone(),
-            lhs_value: lhs_value.clone(),
-            operator: operator.clone(),
-            rhs_expression: *rhs.clone(),
-            rhs_value: rhs_value.clone(),
-        }),
-    }?;
-
-    // if operator is a mutating operator, update the lhs value in the scope
-    match operator {
-        ast::Operator::Append => {
-            assign_to_expression(lhs, result.clone(), context)?;
-        }
+        _ => None,
+    };
 
-        _ => {}
-    }
-
-    trace!("{lhs_value} {operator} {rhs_value} => {result}");
-
-    Ok(result)
+    result.ok_or(OperatorError::InvalidOperands)
 }
@@ -1,9 +1,13 @@
 use blox_language::ast;
+use im::Vector;
 use rust_decimal::Decimal;
 
 use crate::{RuntimeError, Value};
 
-pub fn cast_to_array(value: Value, context: &ast::Expression) -> Result<Vec<Value>, RuntimeError> {
+pub fn cast_to_array(
+    value: Value,
+    context: &ast::Expression,
+) -> Result<Vector<Value>, RuntimeError> {
     match value {
         Value::Array(array) => Ok(array),
         value => Err(RuntimeError::NotAnArray {
@@ -12,16 +12,22 @@ pub fn evaluate_condition(
 ) -> Result<bool, RuntimeError> {
     let condition_value = evaluate_expression(expression, context)?;
 
-    let is_truthy = match condition_value {
-        Value::Boolean(value) => value,
-        Value::Number(number) => number.is_sign_positive() && !number.is_zero(),
-        condition_value => {
-            return Err(RuntimeError::InvalidCondition {
-                condition_expression: expression.clone(),
-                condition_value,
-            });
-        }
+    let Some(is_truthy) = truthiness(&condition_value) else {
+        return Err(RuntimeError::InvalidCondition {
+            condition_expression: expression.clone(),
+            condition_value,
+        });
     };
 
     Ok(is_truthy)
 }
+
+// whether a value counts as true when used as a condition, or `None` if it
+// can't be used as one
+pub fn truthiness(value: &Value) -> Option<bool> {
+    match value {
+        Value::Boolean(value) => Some(*value),
+        Value::Number(number) => Some(number.is_sign_positive() && !number.is_zero()),
+        _ => None,
+    }
+}
@@ -5,13 +5,19 @@ mod if_term;
 mod object;
 mod object_index;
 
+use std::sync::Arc;
+
 use array::evaluate_array;
 use array_index::evaluate_array_index;
+pub use array_index::index_array;
 use array_slice::evaluate_array_slice;
+pub use array_slice::slice_array;
 use blox_language::ast;
 use if_term::evaluate_if_term;
+pub use if_term::select_if_branch;
 use object::evaluate_object;
 use object_index::evaluate_object_index;
+pub use object_index::index_object;
 use tracing::{trace, Level};
 
 use crate::{
@@ -19,6 +25,7 @@ use crate::{
         evaluate_expression, function_call::evaluate_function_call,
         method_call::evaluate_method_call,
     },
+    stack,
     value::Function,
     EvaluationContext, RuntimeError, Value,
 };
@@ -29,7 +36,9 @@ pub fn evaluate_expression_term(
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
     let result = match term {
-        ast::ExpressionTerm::Identifier(identifier) => context.scope.get_binding(&identifier),
+        ast::ExpressionTerm::Identifier(identifier, address) => {
+            context.scope.lookup(identifier, address)
+        }
         ast::ExpressionTerm::Literal(ast::Literal::Boolean(value)) => Ok(Value::Boolean(*value)),
         ast::ExpressionTerm::Literal(ast::Literal::Number(number)) => Ok(Value::Number(*number)),
         ast::ExpressionTerm::Literal(ast::Literal::String(string)) => {
@@ -39,25 +48,66 @@ pub fn evaluate_expression_term(
             Ok(Value::Symbol(string.clone()))
         }
         ast::ExpressionTerm::Expression(expression) => evaluate_expression(expression, context),
-        ast::ExpressionTerm::MethodCall(method_call) => evaluate_method_call(method_call, context),
+        ast::ExpressionTerm::MethodCall(_)
+        | ast::ExpressionTerm::ArraySlice(_)
+        | ast::ExpressionTerm::ArrayIndex(_)
+        | ast::ExpressionTerm::ObjectIndex(_) => {
+            evaluate_chain_link(term, context).map(|value| value.unwrap_or(Value::Void))
+        }
         ast::ExpressionTerm::FunctionCall(function_call) => {
             evaluate_function_call(function_call, context)
         }
         ast::ExpressionTerm::Array(array) => evaluate_array(array, context),
-        ast::ExpressionTerm::ArraySlice(array_slice) => evaluate_array_slice(array_slice, context),
-        ast::ExpressionTerm::ArrayIndex(array_index) => evaluate_array_index(array_index, context),
         ast::ExpressionTerm::Object(object) => evaluate_object(object, context),
-        ast::ExpressionTerm::ObjectIndex(object_index) => {
-            evaluate_object_index(object_index, context)
-        }
         ast::ExpressionTerm::If(if_term) => evaluate_if_term(if_term, context),
-        ast::ExpressionTerm::Lambda(definition) => Ok(Value::Function(Function {
-            definition: definition.clone(),
-            closure: context.scope.clone(),
-        })),
+        ast::ExpressionTerm::Lambda(definition) => Ok(Value::Function(Function::new(
+            Arc::new(definition.clone()),
+            context.scope.clone(),
+        ))),
     }?;
 
     trace!("{term} => {result}");
 
     Ok(result)
 }
+
+// a chain of member accesses, indexes, slices and method calls, like
+// `order?.customer.address[0]`, ends as soon as one of its optional links
+// comes up void: the rest of the chain is skipped and the whole chain is void.
+// evaluates the base of a link, or returns `None` when the chain has ended.
+pub fn evaluate_chain_base(
+    base: &ast::Expression,
+    context: &mut EvaluationContext,
+) -> Result<Option<Value>, RuntimeError> {
+    let ast::Expression::Term(term) = base else {
+        return evaluate_expression(base, context).map(Some);
+    };
+    let optional = match term {
+        ast::ExpressionTerm::MethodCall(method_call) => method_call.optional,
+        ast::ExpressionTerm::ArrayIndex(array_index) => array_index.optional,
+        ast::ExpressionTerm::ObjectIndex(object_index) => object_index.optional,
+        ast::ExpressionTerm::ArraySlice(_) => false,
+        // parentheses end a chain, like `(order?.customer).name`
+        _ => return evaluate_expression(base, context).map(Some),
+    };
+
+    context.budget.step()?;
+    let value = stack::maybe_grow(|| evaluate_chain_link(term, context))?;
+
+    Ok(value.filter(|value| !(optional && *value == Value::Void)))
+}
+
+fn evaluate_chain_link(
+    term: &ast::ExpressionTerm,
+    context: &mut EvaluationContext,
+) -> Result<Option<Value>, RuntimeError> {
+    match term {
+        ast::ExpressionTerm::MethodCall(method_call) => evaluate_method_call(method_call, context),
+        ast::ExpressionTerm::ArraySlice(array_slice) => evaluate_array_slice(array_slice, context),
+        ast::ExpressionTerm::ArrayIndex(array_index) => evaluate_array_index(array_index, context),
+        ast::ExpressionTerm::ObjectIndex(object_index) => {
+            evaluate_object_index(object_index, context)
+        }
+        term => evaluate_expression_term(term, context).map(Some),
+    }
+}
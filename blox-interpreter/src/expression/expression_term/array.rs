@@ -1,15 +1,21 @@
 use blox_language::ast;
+use im::Vector;
 
-use crate::{expression::evaluate_expression, EvaluationContext, RuntimeError, Value};
+use crate::{
+    budget::allocation_size, expression::evaluate_expression, EvaluationContext, RuntimeError,
+    Value,
+};
 
 pub fn evaluate_array(
     array: &ast::Array,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
-    let mut members = Vec::new();
+    let mut members = Vector::new();
     for member_expression in array.0.iter() {
         let value = evaluate_expression(member_expression, context)?;
-        members.push(value);
+        members.push_back(value);
     }
-    Ok(Value::Array(members))
+    let value = Value::Array(members);
+    context.budget.allocate(allocation_size(&value))?;
+    Ok(value)
 }
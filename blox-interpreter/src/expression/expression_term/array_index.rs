@@ -2,40 +2,69 @@ use blox_language::ast;
 
 use crate::{expression::evaluate_expression, EvaluationContext, RuntimeError, Value};
 
+use super::evaluate_chain_base;
+
+// `None` when an optional link, this one or one earlier in the chain, has
+// ended it
 pub fn evaluate_array_index(
     array_index: &ast::ArrayIndex,
     context: &mut EvaluationContext,
-) -> Result<Value, RuntimeError> {
-    let ast::ArrayIndex { base, index } = array_index;
-    let array_value = evaluate_expression(base, context)?;
+) -> Result<Option<Value>, RuntimeError> {
+    let ast::ArrayIndex {
+        base,
+        index,
+        optional,
+    } = array_index;
+    let Some(array_value) = evaluate_chain_base(base, context)? else {
+        return Ok(None);
+    };
+
+    if *optional && array_value == Value::Void {
+        return Ok(None);
+    }
+
     let index_value = evaluate_expression(index, context)?;
 
+    index_array(array_value, index_value, *optional, base, index).map(Some)
+}
+
+// looks up an already-evaluated index in an already-evaluated array; `base`
+// and `index` are the expressions they came from, used for error reporting
+pub fn index_array(
+    array_value: Value,
+    index_value: Value,
+    optional: bool,
+    base: &ast::Expression,
+    index: &ast::Expression,
+) -> Result<Value, RuntimeError> {
     match (&array_value, &index_value) {
         (Value::Array(ref members), Value::Number(idx)) => {
             let Ok(idx): rust_decimal::Result<usize> = (*idx).try_into() else {
                 return Err(RuntimeError::InvalidArrayIndex {
-                    array_expression: *base.clone(),
+                    array_expression: base.clone(),
                     array_value: array_value.clone(),
-                    index_expression: *index.clone(),
+                    index_expression: index.clone(),
                     index_value: index_value.clone(),
                 });
             };
 
             if idx < members.len() {
                 Ok(members[idx].clone())
+            } else if optional {
+                Ok(Value::Void)
             } else {
                 Err(RuntimeError::ArrayIndexOutOfBounds {
-                    array_expression: *base.clone(),
+                    array_expression: base.clone(),
                     array_value: array_value.clone(),
-                    index_expression: *index.clone(),
+                    index_expression: index.clone(),
                     index_value: index_value.clone(),
                 })
             }
         }
         (array_value, index_value) => Err(RuntimeError::InvalidArrayIndex {
-            array_expression: *base.clone(),
+            array_expression: base.clone(),
             array_value: array_value.clone(),
-            index_expression: *index.clone(),
+            index_expression: index.clone(),
             index_value: index_value.clone(),
         }),
     }
@@ -1,5 +1,6 @@
 use blox_language::ast;
-use rust_decimal::Decimal;
+use im::Vector;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
 
 use crate::{
     expression::{
@@ -9,36 +10,72 @@ use crate::{
     EvaluationContext, RuntimeError, Value,
 };
 
+use super::evaluate_chain_base;
+
+// `None` when an optional link earlier in the chain has ended it
 pub fn evaluate_array_slice(
     array_slice: &ast::ArraySlice,
     context: &mut EvaluationContext,
-) -> Result<Value, RuntimeError> {
+) -> Result<Option<Value>, RuntimeError> {
     let ast::ArraySlice { base, start, end } = array_slice;
-    let base_value: Vec<Value> = cast_to_array(evaluate_expression(base, context)?, base)?;
-    let start_value: Decimal = if let Some(start) = start {
-        cast_to_number(evaluate_expression(start, context)?, start)?
-    } else {
-        0.into()
+    let Some(base_value) = evaluate_chain_base(base, context)? else {
+        return Ok(None);
+    };
+    let base_value: Vector<Value> = cast_to_array(base_value, base)?;
+    let start_value = match start {
+        Some(start) => Some((
+            cast_to_number(evaluate_expression(start, context)?, start)?,
+            start.as_ref(),
+        )),
+        None => None,
     };
 
-    let end_value: Option<Decimal> = if let Some(end) = end {
-        Some(cast_to_number(evaluate_expression(end, context)?, end)?)
-    } else {
-        None
+    let end_value = match end {
+        Some(end) => Some((
+            cast_to_number(evaluate_expression(end, context)?, end)?,
+            end.as_ref(),
+        )),
+        None => None,
     };
 
-    let iter = base_value.iter().skip(start_value.try_into().unwrap());
+    slice_array(base_value, start_value, end_value).map(Some)
+}
 
-    let result: Vec<Value> = if let Some(end_value) = end_value {
-        let start_index: usize = start_value.try_into().unwrap();
-        let end_index: usize = end_value.try_into().unwrap();
+// slices an already-evaluated array by already-evaluated bounds, each paired
+// with the expression it came from for error reporting. missing bounds are
+// the start and end of the array.
+pub fn slice_array(
+    base_value: Vector<Value>,
+    start_value: Option<(Decimal, &ast::Expression)>,
+    end_value: Option<(Decimal, &ast::Expression)>,
+) -> Result<Value, RuntimeError> {
+    // slicing shares structure with the base array, so it doesn't copy its members
+    let start_index = match start_value {
+        Some((start, expression)) => slice_bound(start, expression)?,
+        None => 0,
+    };
+    let start_index = start_index.min(base_value.len());
 
-        iter.take(end_index - start_index)
-            .cloned()
-            .collect::<Vec<_>>()
-    } else {
-        iter.cloned().collect::<Vec<_>>()
+    let end_index = match end_value {
+        Some((end, expression)) => slice_bound(end, expression)?,
+        None => base_value.len(),
     };
+    let end_index = end_index.clamp(start_index, base_value.len());
+
+    let result = base_value.skip(start_index).take(end_index - start_index);
 
     Ok(Value::Array(result))
 }
+
+// fractional bounds are truncated, and bounds past the end of the array are
+// clamped to it however large they are
+fn slice_bound(bound: Decimal, expression: &ast::Expression) -> Result<usize, RuntimeError> {
+    if bound < Decimal::ZERO {
+        return Err(RuntimeError::InvalidSliceBound {
+            expression: expression.clone(),
+            value: Value::Number(bound),
+        });
+    }
+
+    Ok(bound.to_usize().unwrap_or(usize::MAX))
+}
@@ -9,6 +9,18 @@ pub fn evaluate_if_term(
     if_term: &ast::If,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
+    match select_if_branch(if_term, context)? {
+        Some(branch) => evaluate_block(branch, context),
+        None => Ok(Value::Void),
+    }
+}
+
+// evaluates the conditions of an `if` in order and returns the block of the
+// branch to take, or `None` if no condition matched and there's no `else`
+pub fn select_if_branch<'a>(
+    if_term: &'a ast::If,
+    context: &mut EvaluationContext,
+) -> Result<Option<&'a ast::Block>, RuntimeError> {
     let ast::If {
         condition,
         body,
@@ -17,18 +29,14 @@ pub fn evaluate_if_term(
     } = if_term;
 
     if evaluate_condition(condition, context)? {
-        return evaluate_block(body, context);
+        return Ok(Some(body));
     }
 
     for elseif_branch in elseif_branches {
         if evaluate_condition(&elseif_branch.0, context)? {
-            return evaluate_block(&elseif_branch.1, context);
+            return Ok(Some(&elseif_branch.1));
         }
     }
 
-    if let Some(else_branch) = else_branch {
-        return evaluate_block(else_branch, context);
-    }
-
-    Ok(Value::Void)
+    Ok(else_branch.as_ref())
 }
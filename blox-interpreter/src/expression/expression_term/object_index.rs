@@ -1,28 +1,51 @@
 use blox_language::ast;
 
-use crate::{expression::evaluate_expression, EvaluationContext, RuntimeError, Value};
+use crate::{EvaluationContext, RuntimeError, Value};
 
+use super::evaluate_chain_base;
+
+// `None` when an optional link earlier in the chain has ended it
 pub fn evaluate_object_index(
     object_index: &ast::ObjectIndex,
     context: &mut EvaluationContext,
-) -> Result<Value, RuntimeError> {
-    let ast::ObjectIndex { base, index } = object_index;
-    let object_value = evaluate_expression(base, context)?;
+) -> Result<Option<Value>, RuntimeError> {
+    let ast::ObjectIndex {
+        base,
+        index,
+        optional,
+    } = object_index;
+    let Some(object_value) = evaluate_chain_base(base, context)? else {
+        return Ok(None);
+    };
 
+    index_object(object_value, index, *optional, base).map(Some)
+}
+
+// looks up a key in an already-evaluated object; `base` is the expression it
+// came from, used for error reporting
+pub fn index_object(
+    object_value: Value,
+    index: &ast::Identifier,
+    optional: bool,
+    base: &ast::Expression,
+) -> Result<Value, RuntimeError> {
     match object_value {
+        Value::Void if optional => Ok(Value::Void),
         Value::Object(ref members) => {
             if let Some(value) = members.get(&index.name) {
                 Ok(value.clone())
+            } else if optional {
+                Ok(Value::Void)
             } else {
                 Err(RuntimeError::ObjectKeyNotFound {
-                    object_expression: *base.clone(),
+                    object_expression: base.clone(),
                     object_value: object_value.clone(),
                     key: index.name.clone(),
                 })
             }
         }
         object_value => Err(RuntimeError::NotAnObject {
-            object_expression: *base.clone(),
+            object_expression: base.clone(),
             object_value: object_value.clone(),
             key: index.name.clone(),
         }),
@@ -1,57 +1,125 @@
 use std::collections::HashMap;
 
-use blox_language::ast;
+use blox_language::{ast, location::Location};
 use tracing::{trace, Level};
 
 use crate::{
-    expression::evaluate_expression, program::evaluate_block, value::Function, EvaluationContext,
-    Intrinsic, RuntimeError, Value,
+    expression::evaluate_expression, program::evaluate_tail_block, stack_trace::StackFrame,
+    EvaluationContext, RuntimeError, Value,
 };
 
+use super::tail_call::Completion;
+
+// evaluated arguments of a call, in the order they were written
+pub type Arguments = Vec<(ast::Identifier, Value)>;
+
 #[tracing::instrument(skip(context), ret(level=Level::TRACE), err(level=Level::DEBUG))]
 pub fn evaluate_function_call(
     function_call: &ast::FunctionCall,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
+    let (function, arguments) = prepare_function_call(function_call, context)?;
+    let result = call_function(
+        &function_call.0,
+        &function_call.2,
+        function,
+        arguments,
+        context,
+    )?;
+
+    trace!("{function_call} returned {result}");
+
+    Ok(result)
+}
+
+// evaluates the callee and arguments of a call without making it
+pub fn prepare_function_call(
+    function_call: &ast::FunctionCall,
+    context: &mut EvaluationContext,
+) -> Result<(Value, Arguments), RuntimeError> {
     let function = evaluate_expression(&function_call.0, context)?;
 
-    let result = match function {
-        Value::Function(Function {
-            definition,
-            closure,
-        }) => {
-            let mut call_context = context.child_with_scope(closure.child());
+    let mut arguments = Vec::new();
+    for ast::Argument(name, rhs) in function_call.1.iter() {
+        let value = evaluate_expression(rhs, context)?;
+        arguments.push((name.clone(), value));
+    }
 
-            for (parameter, argument) in definition.parameters.iter().zip(&function_call.1) {
-                let name = &parameter.0;
-                let value = evaluate_expression(&argument.1, context)?;
+    Ok((function, arguments))
+}
 
-                call_context.scope.insert_binding(name, value);
-            }
+// calls an already-evaluated function value with already-evaluated arguments;
+// `callee` is the expression the function came from, used for error reporting,
+// and `call_site` is where the call was made, recorded in stack traces.
+//
+// calls made in tail position by the function body come back here as a
+// `Completion::TailCall` and are made by this loop instead of recursing, so
+// self- and mutually-recursive tail calls run in constant stack space.
+pub fn call_function(
+    callee: &ast::Expression,
+    call_site: &Location,
+    function: Value,
+    arguments: Arguments,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    // the whole chain of tail calls counts as a single level of call depth
+    context.budget.enter_call()?;
+    let result = call_tail_calls(callee, call_site.clone(), function, arguments, context);
+    context.budget.exit_call();
 
-            evaluate_block(&definition.body, &mut call_context)
-        }
-        Value::Intrinsic(Intrinsic {
-            id: _,
-            name: _,
-            function,
-        }) => {
-            let mut parameters = HashMap::new();
-
-            for ast::Argument(name, rhs) in function_call.1.iter() {
-                let value = evaluate_expression(&rhs, context)?;
-                parameters.insert(name.clone(), value);
-            }
+    result
+}
 
-            function(parameters)
-        }
-        _ => Err(RuntimeError::NotAFunction {
-            callee: *function_call.0.clone(),
-            value: function.clone(),
-        }),
-    }?;
+fn call_tail_calls(
+    callee: &ast::Expression,
+    mut call_site: Location,
+    mut function: Value,
+    mut arguments: Arguments,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    loop {
+        match function {
+            Value::Function(function_value) => {
+                let definition = &function_value.definition;
+                let mut call_context = context
+                    .child_with_scope(function_value.closure.child_with_locals(definition.locals));
 
-    trace!("{function_call} returned {result}");
+                for (parameter, (_name, value)) in definition.parameters.iter().zip(arguments) {
+                    call_context
+                        .scope
+                        .declare(&parameter.0, &parameter.1, value, false)?;
+                }
 
-    Ok(result)
+                let completion =
+                    evaluate_tail_block(&definition.body, &mut call_context).map_err(|error| {
+                        error.traced(StackFrame::function(&function_value, &call_site))
+                    })?;
+
+                match completion {
+                    Completion::Value(value) => return Ok(value),
+                    Completion::TailCall {
+                        function: next_function,
+                        arguments: next_arguments,
+                        call_site: next_call_site,
+                    } => {
+                        function = next_function;
+                        arguments = next_arguments;
+                        call_site = next_call_site;
+                    }
+                }
+            }
+            Value::Intrinsic(intrinsic) => {
+                let parameters: HashMap<ast::Identifier, Value> = arguments.into_iter().collect();
+                return intrinsic
+                    .call(parameters, &call_site, context)
+                    .map_err(|error| error.traced(StackFrame::intrinsic(&intrinsic, &call_site)));
+            }
+            function => {
+                return Err(RuntimeError::NotAFunction {
+                    callee: callee.clone(),
+                    value: function,
+                })
+            }
+        }
+    }
 }
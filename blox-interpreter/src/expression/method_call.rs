@@ -1,43 +1,117 @@
 use blox_language::ast;
 use tracing::Level;
 
-use crate::{EvaluationContext, RuntimeError, Value};
+use crate::{
+    expression::{evaluate_expression, expression_term::evaluate_chain_base},
+    methods::builtin_method,
+    EvaluationContext, RuntimeError, Scope, Value,
+};
 
-use super::function_call::evaluate_function_call;
+use super::function_call::{call_function, Arguments};
 
 #[tracing::instrument(skip(context), ret(level=Level::TRACE), err(level=Level::DEBUG))]
 pub fn evaluate_method_call(
     method_call: &ast::MethodCall,
     context: &mut EvaluationContext,
-) -> Result<Value, RuntimeError> {
-    let function = context.scope.get_binding(&method_call.function)?;
+) -> Result<Option<Value>, RuntimeError> {
+    match prepare_method_call(method_call, context)? {
+        Some((callee, function, arguments)) => {
+            call_function(&callee, &method_call.location, function, arguments, context).map(Some)
+        }
+        None => Ok(None),
+    }
+}
 
-    let Value::Function(function) = function else {
-        return Err(RuntimeError::NotAFunction {
-            callee: ast::Expression::Term(ast::ExpressionTerm::Identifier(
-                method_call.function.clone(),
-            )),
-            value: function,
-        });
+// resolves the method and evaluates its arguments, with the base as the first
+// one, without making the call. returns `None` when the call is skipped: an
+// optional method call (`base?.method()`) on a void base, or one in a chain
+// that an earlier optional link has ended.
+pub fn prepare_method_call(
+    method_call: &ast::MethodCall,
+    context: &mut EvaluationContext,
+) -> Result<Option<(ast::Expression, Value, Arguments)>, RuntimeError> {
+    let callee = ast::Expression::Term(ast::ExpressionTerm::Identifier(
+        method_call.function.clone(),
+        method_call.address.clone(),
+    ));
+
+    let Some(self_value) = evaluate_chain_base(&method_call.base, context)? else {
+        return Ok(None);
     };
-    let Some(self_param) = function.definition.parameters.first() else {
-        return Err(RuntimeError::MethodCallWithoutSelf {
-            method: method_call.function.clone(),
-        });
+
+    if method_call.optional && self_value == Value::Void {
+        return Ok(None);
+    }
+
+    let (function, self_param) = lookup_method(
+        &method_call.function,
+        &method_call.address,
+        &self_value,
+        &context.scope,
+    )?;
+
+    let mut arguments = vec![(self_param, self_value)];
+    for ast::Argument(name, rhs) in method_call.arguments.iter() {
+        let value = evaluate_expression(rhs, context)?;
+        arguments.push((name.clone(), value));
+    }
+
+    Ok(Some((callee, function, arguments)))
+}
+
+// finds the function a method call on `receiver` refers to: the function or
+// intrinsic bound to its name, or else the receiver type's built-in method.
+// other values bound to the name, like `let upper = 1`, don't hide it.
+// returns it along with the name the receiver is passed as, which is a
+// function's first parameter or an intrinsic's receiver.
+pub fn lookup_method(
+    method: &ast::Identifier,
+    address: &ast::Address,
+    receiver: &Value,
+    scope: &Scope,
+) -> Result<(Value, ast::Identifier), RuntimeError> {
+    let function = match scope.lookup(method, address) {
+        Ok(function @ (Value::Function(_) | Value::Intrinsic(_))) => function,
+        Ok(value) => match builtin_method(receiver, &method.name) {
+            Some(intrinsic) => Value::Intrinsic(intrinsic),
+            None => value,
+        },
+        Err(RuntimeError::UndefinedVariable(_)) => {
+            let Some(intrinsic) = builtin_method(receiver, &method.name) else {
+                return Err(RuntimeError::UnknownMethod {
+                    method: method.clone(),
+                    value: receiver.clone(),
+                });
+            };
+            Value::Intrinsic(intrinsic)
+        }
+        Err(error) => return Err(error),
     };
 
-    let mut arguments = vec![ast::Argument(
-        self_param.0.clone(),
-        *method_call.base.clone(),
-    )];
-    arguments.append(&mut method_call.arguments.clone());
+    match function {
+        Value::Function(function) => {
+            let Some(self_param) = function.definition.parameters.first() else {
+                return Err(RuntimeError::MethodCallWithoutSelf {
+                    method: method.clone(),
+                });
+            };
+            let self_param = self_param.0.clone();
 
-    let function_call = ast::FunctionCall(
-        Box::new(ast::Expression::Term(ast::ExpressionTerm::Identifier(
-            method_call.function.clone(),
-        ))),
-        arguments,
-    );
+            Ok((Value::Function(function), self_param))
+        }
+        Value::Intrinsic(intrinsic) => {
+            let self_param = ast::Identifier {
+                name: intrinsic.receiver().to_string(),
+            };
 
-    evaluate_function_call(&function_call, context)
+            Ok((Value::Intrinsic(intrinsic), self_param))
+        }
+        function => Err(RuntimeError::NotAFunction {
+            callee: ast::Expression::Term(ast::ExpressionTerm::Identifier(
+                method.clone(),
+                address.clone(),
+            )),
+            value: function,
+        }),
+    }
 }
@@ -0,0 +1,73 @@
+use blox_language::{ast, location::Location};
+
+use crate::{program::evaluate_tail_block, EvaluationContext, RuntimeError, Value};
+
+use super::{
+    evaluate_expression,
+    expression_term::select_if_branch,
+    function_call::{prepare_function_call, Arguments},
+    method_call::prepare_method_call,
+};
+
+// the result of evaluating an expression in tail position: either a value, or
+// a call that the enclosing `call_function` loop should make in its place
+pub enum Completion {
+    Value(Value),
+    TailCall {
+        function: Value,
+        arguments: Arguments,
+        call_site: Location,
+    },
+}
+
+// evaluates the last expression of a function body. calls, and calls at the
+// end of an `if` branch, are returned as `Completion::TailCall` rather than
+// being made here, so they don't grow the stack.
+pub fn evaluate_tail_expression(
+    expression: &ast::Expression,
+    context: &mut EvaluationContext,
+) -> Result<Completion, RuntimeError> {
+    match expression {
+        ast::Expression::Term(ast::ExpressionTerm::FunctionCall(function_call)) => {
+            let (function, arguments) = prepare_function_call(function_call, context)?;
+            tail_call(&function_call.0, &function_call.2, function, arguments)
+        }
+        ast::Expression::Term(ast::ExpressionTerm::MethodCall(method_call)) => {
+            match prepare_method_call(method_call, context)? {
+                Some((callee, function, arguments)) => {
+                    tail_call(&callee, &method_call.location, function, arguments)
+                }
+                None => Ok(Completion::Value(Value::Void)),
+            }
+        }
+        ast::Expression::Term(ast::ExpressionTerm::If(if_term)) => {
+            match select_if_branch(if_term, context)? {
+                Some(branch) => evaluate_tail_block(branch, context),
+                None => Ok(Completion::Value(Value::Void)),
+            }
+        }
+        ast::Expression::Term(ast::ExpressionTerm::Expression(expression)) => {
+            evaluate_tail_expression(expression, context)
+        }
+        expression => Ok(Completion::Value(evaluate_expression(expression, context)?)),
+    }
+}
+
+fn tail_call(
+    callee: &ast::Expression,
+    call_site: &Location,
+    function: Value,
+    arguments: Arguments,
+) -> Result<Completion, RuntimeError> {
+    match function {
+        Value::Function(_) | Value::Intrinsic(_) => Ok(Completion::TailCall {
+            function,
+            arguments,
+            call_site: call_site.clone(),
+        }),
+        function => Err(RuntimeError::NotAFunction {
+            callee: callee.clone(),
+            value: function,
+        }),
+    }
+}
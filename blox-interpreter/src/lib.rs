@@ -1,18 +1,45 @@
+// lets `#[blox_intrinsic]` refer to this crate as `blox_interpreter` from
+// inside it too
+extern crate self as blox_interpreter;
+
+mod budget;
+mod bytecode;
+mod engine;
 mod error;
 mod expression;
+mod methods;
 mod module;
+mod native;
 mod program;
 mod repl;
 mod scope;
+mod stack;
+mod stack_trace;
 mod statement;
+mod stdlib;
 mod value;
 
 pub use self::{
+    budget::{Budget, Limit, Limits},
+    engine::{Engine, EngineBuilder, EngineError},
     error::RuntimeError,
-    module::{load_module_from_string, load_stdlib, EvaluationContext},
-    program::execute_program,
+    methods::RECEIVER,
+    module::{load_module_from_string, load_stdlib, Backend, Clock, EvaluationContext},
+    native::{NativeModule, NativeModuleRegistry},
+    program::{call_function_value, execute_program, execute_program_async},
     repl::{start_repl, BloxReplError},
     scope::Scope,
+    stack_trace::StackFrame,
+    value::serialization::{from_value, to_value, DURATION_TAG, SYMBOL_TAG, TIME_TAG},
     value::Value,
-    value::{Intrinsic, IntrinsicFn},
+    value::{
+        AsyncIntrinsicFn, Callback, ContextIntrinsicFn, Intrinsic, IntrinsicArguments, IntrinsicFn,
+        IntrinsicFunction, IntrinsicFuture, Regex,
+    },
 };
+
+pub use blox_language::location::Location;
+pub use blox_macros::blox_intrinsic;
+
+#[doc(hidden)]
+pub use self::value::conversion::{intrinsic_argument, optional_intrinsic_argument};
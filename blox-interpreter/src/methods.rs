@@ -0,0 +1,195 @@
+//! The methods values have without importing anything, like `'abc'.length()`
+//! or `[3, 1].sort()`.
+//!
+//! Where the standard library has a function for the type, the method is that
+//! function, so `text.trim()` is `trim(text: text)` from `stdlib/string`.
+//!
+//! A method call `base.name(...)` calls the function or intrinsic bound to
+//! `name` if there is one, so user code can always shadow these. Otherwise it
+//! calls the built-in method of that name for the type of `base`, if it has
+//! one.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use im::{OrdMap, Vector};
+use rust_decimal::Decimal;
+
+use blox_macros::blox_intrinsic;
+
+use crate::{
+    stdlib::{list, math, object, regex, string, time},
+    EvaluationContext, Intrinsic, RuntimeError, Value,
+};
+
+// intrinsics called as methods are passed the receiver as this argument,
+// unless they name another one; see `Intrinsic::with_receiver`
+pub const RECEIVER: &str = "this";
+
+// the built-in method called `name` on values of `value`'s type
+pub(crate) fn builtin_method(value: &Value, name: &str) -> Option<Intrinsic> {
+    static METHODS: OnceLock<HashMap<(&'static str, String), Intrinsic>> = OnceLock::new();
+
+    METHODS
+        .get_or_init(|| {
+            let tables = [
+                ("string", string_methods()),
+                ("number", number_methods()),
+                ("array", array_methods()),
+                ("object", object_methods()),
+                ("regex", regex_methods()),
+                ("time", time_methods()),
+                ("duration", duration_methods()),
+                ("symbol", symbol_methods()),
+            ];
+
+            tables
+                .into_iter()
+                .flat_map(|(type_name, methods)| {
+                    methods
+                        .into_iter()
+                        .map(move |method| ((type_name, method.name.clone()), method))
+                })
+                .collect()
+        })
+        .get(&(value.type_name(), name.to_string()))
+        .cloned()
+}
+
+// the functions of `stdlib/string` that take a string
+fn string_methods() -> Vec<Intrinsic> {
+    vec![
+        string::length_intrinsic(),
+        string::is_empty_intrinsic(),
+        string::split_intrinsic(),
+        string::trim_intrinsic(),
+        string::upper_intrinsic(),
+        string::lower_intrinsic(),
+        string::starts_with_intrinsic(),
+        string::ends_with_intrinsic(),
+        string::contains_intrinsic(),
+        string::replace_intrinsic(),
+        string::pad_start_intrinsic(),
+        string::pad_end_intrinsic(),
+        string::repeat_intrinsic(),
+        string::chars_intrinsic(),
+        string::slugify_intrinsic(),
+        string::truncate_intrinsic(),
+        string::to_number_intrinsic(),
+    ]
+}
+
+// the functions of `stdlib/math` that take a number
+fn number_methods() -> Vec<Intrinsic> {
+    vec![
+        math::abs_intrinsic(),
+        math::floor_intrinsic(),
+        math::ceil_intrinsic(),
+        math::round_intrinsic(),
+        math::trunc_intrinsic(),
+        math::min_intrinsic(),
+        math::max_intrinsic(),
+        math::clamp_intrinsic(),
+        math::pow_intrinsic(),
+        math::sqrt_intrinsic(),
+        math::sign_intrinsic(),
+        math::div_intrinsic(),
+        math::rem_intrinsic(),
+        string::to_string_intrinsic(),
+    ]
+}
+
+fn array_methods() -> Vec<Intrinsic> {
+    vec![
+        list::length_intrinsic(),
+        array_is_empty_intrinsic(),
+        first_intrinsic(),
+        last_intrinsic(),
+        list::reverse_intrinsic(),
+        list::sort_intrinsic(),
+        array_contains_intrinsic(),
+        list::join_intrinsic(),
+    ]
+}
+
+// the functions of `stdlib/object` that take an object
+fn object_methods() -> Vec<Intrinsic> {
+    vec![
+        object_length_intrinsic(),
+        object::keys_intrinsic(),
+        object::values_intrinsic(),
+        object::entries_intrinsic(),
+        object::has_intrinsic(),
+        object::get_intrinsic(),
+        object::merge_intrinsic(),
+        object::deep_merge_intrinsic(),
+        object::pick_intrinsic(),
+        object::omit_intrinsic(),
+        object::map_values_intrinsic(),
+        object::filter_intrinsic(),
+    ]
+}
+
+// the functions of `stdlib/regex` that take a regex
+fn regex_methods() -> Vec<Intrinsic> {
+    vec![
+        regex::is_match_intrinsic(),
+        regex::find_intrinsic(),
+        regex::find_all_intrinsic(),
+        regex::captures_intrinsic(),
+        regex::replace_intrinsic(),
+        regex::replace_all_intrinsic(),
+        regex::split_intrinsic(),
+    ]
+}
+
+// the functions of `stdlib/time` that take a time
+fn time_methods() -> Vec<Intrinsic> {
+    vec![
+        time::format_intrinsic(),
+        time::in_zone_intrinsic(),
+        string::to_string_intrinsic(),
+    ]
+}
+
+fn duration_methods() -> Vec<Intrinsic> {
+    vec![time::seconds_intrinsic(), string::to_string_intrinsic()]
+}
+
+fn symbol_methods() -> Vec<Intrinsic> {
+    vec![name_intrinsic()]
+}
+
+#[blox_intrinsic(name = "is_empty")]
+fn array_is_empty(this: Vector<Value>) -> bool {
+    this.is_empty()
+}
+
+// void when the array is empty
+#[blox_intrinsic]
+fn first(this: Vector<Value>) -> Option<Value> {
+    this.front().cloned()
+}
+
+#[blox_intrinsic]
+fn last(this: Vector<Value>) -> Option<Value> {
+    this.back().cloned()
+}
+
+#[blox_intrinsic(name = "contains")]
+fn array_contains(this: Vector<Value>, value: Value) -> bool {
+    this.contains(&value)
+}
+
+#[blox_intrinsic(name = "length")]
+fn object_length(this: OrdMap<String, Value>) -> Decimal {
+    this.len().into()
+}
+
+// a symbol's name, without the leading colon
+#[blox_intrinsic]
+fn name(this: Value, context: &mut EvaluationContext) -> Result<Option<String>, RuntimeError> {
+    match this {
+        Value::Symbol(name) => context.budget.charge(name).map(Some),
+        _ => Ok(None),
+    }
+}
@@ -1,25 +1,69 @@
 use std::{
     collections::BTreeMap,
-    sync::{Arc, RwLock},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
 };
 
 use blox_language::ast;
+use jiff::Timestamp;
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::runtime::Handle;
 
-use crate::{program::evaluate_block, RuntimeError, Scope, Value};
+use crate::{
+    budget::{Budget, Limits},
+    native::NativeModuleRegistry,
+    program::execute_program,
+    stdlib::NATIVE_STDLIB,
+    RuntimeError, Scope, Value,
+};
+
+// how programs are executed: by walking their syntax tree, or by compiling
+// them to bytecode first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Backend {
+    #[default]
+    TreeWalker,
+    Bytecode,
+}
+
+// where `now` from `stdlib/time` gets the current time
+pub type Clock = dyn Fn() -> Timestamp + Send + Sync;
 
 #[derive(Clone)]
 pub struct EvaluationContext {
     pub import_base_dir: String,
+    // more directories to look for modules in, in order, when they aren't
+    // found in `import_base_dir`
+    pub import_paths: Vec<String>,
     pub scope: Arc<Scope>,
     pub import_cache: Arc<RwLock<BTreeMap<String, Module>>>,
+    // modules written in rust, imported by path like any other
+    pub native_modules: NativeModuleRegistry,
+    pub backend: Backend,
+    pub budget: Arc<Budget>,
+    // the runtime async intrinsics are awaited on, when the program is
+    // running on one of its blocking threads; see `execute_program_async`
+    pub runtime: Option<Handle>,
+    // where `random` and `random_int` from `stdlib/math` get their numbers,
+    // shared with child contexts so a seeded sequence doesn't restart
+    pub random: Arc<Mutex<StdRng>>,
+    // the system clock, unless tests have frozen it
+    pub clock: Arc<Clock>,
 }
 
 impl Default for EvaluationContext {
     fn default() -> Self {
         Self {
             import_base_dir: ".".to_string(),
+            import_paths: Vec::new(),
             scope: Arc::new(Scope::default()),
             import_cache: Arc::new(RwLock::new(BTreeMap::new())),
+            native_modules: NativeModuleRegistry::default(),
+            backend: Backend::default(),
+            budget: Arc::new(Budget::default()),
+            runtime: None,
+            random: Arc::new(Mutex::new(StdRng::from_entropy())),
+            clock: Arc::new(Timestamp::now),
         }
     }
 }
@@ -32,87 +76,204 @@ impl EvaluationContext {
     ) -> Self {
         Self {
             import_base_dir: import_base_dir.to_string(),
+            import_paths: Vec::new(),
             scope,
             import_cache,
+            native_modules: NativeModuleRegistry::default(),
+            backend: Backend::default(),
+            budget: Arc::new(Budget::default()),
+            runtime: None,
+            random: Arc::new(Mutex::new(StdRng::from_entropy())),
+            clock: Arc::new(Timestamp::now),
         }
     }
 
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    // starts a fresh budget with these limits, for this context and the
+    // children created from it afterwards
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.budget = Arc::new(Budget::new(limits));
+        self
+    }
+
+    // makes random numbers repeatable, for this context and the children
+    // created from it afterwards
+    pub fn with_random_seed(mut self, seed: u64) -> Self {
+        self.random = Arc::new(Mutex::new(StdRng::seed_from_u64(seed)));
+        self
+    }
+
+    // reads the current time from `clock` instead of the system, for this
+    // context and the children created from it afterwards
+    pub fn with_clock(mut self, clock: Arc<Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     pub fn child(&self) -> Self {
         Self {
             import_base_dir: self.import_base_dir.clone(),
+            import_paths: self.import_paths.clone(),
             scope: self.scope.child(),
             import_cache: self.import_cache.clone(),
+            native_modules: self.native_modules.clone(),
+            backend: self.backend,
+            budget: self.budget.clone(),
+            runtime: self.runtime.clone(),
+            random: self.random.clone(),
+            clock: self.clock.clone(),
         }
     }
 
     pub fn child_with_scope(&self, call_scope: Arc<Scope>) -> Self {
         Self {
             import_base_dir: self.import_base_dir.clone(),
+            import_paths: self.import_paths.clone(),
             import_cache: self.import_cache.clone(),
+            native_modules: self.native_modules.clone(),
             scope: call_scope,
+            backend: self.backend,
+            budget: self.budget.clone(),
+            runtime: self.runtime.clone(),
+            random: self.random.clone(),
+            clock: self.clock.clone(),
         }
     }
 }
 
 // standard library modules statically included in the binary
-// so that they can be loaded without reading from the filesystem
-const STDLIB: [(&'static str, &'static str); 3] = [
-    ("stdlib/math", include_str!("../../stdlib/math.blox")),
-    ("stdlib/list", include_str!("../../stdlib/list.blox")),
-    (
-        "stdlib/database",
-        include_str!("../../stdlib/database.blox"),
-    ),
-];
-
-pub fn load_stdlib(context: &mut EvaluationContext) {
+// so that they can be loaded without reading from the filesystem; the rest
+// are written in rust, see `NATIVE_STDLIB`
+const STDLIB: [(&'static str, &'static str); 1] = [(
+    "stdlib/database",
+    include_str!("../../stdlib/database.blox"),
+)];
+
+pub fn load_stdlib(context: &mut EvaluationContext) -> Result<(), RuntimeError> {
     // load the standard library
-    for (path, source) in STDLIB.iter() {
-        let module = load_module_from_string(path, source, &context)
-            .expect("failed to load stdlib module {path}");
-
-        context
-            .import_cache
-            .write()
-            .unwrap()
-            .insert(path.to_string(), module);
+    for path in stdlib_modules() {
+        load_stdlib_module(path, context)?;
     }
+    Ok(())
+}
+
+// native modules come first, since the others import them
+pub(crate) fn stdlib_modules() -> impl Iterator<Item = &'static str> {
+    NATIVE_STDLIB
+        .iter()
+        .map(|(path, _)| *path)
+        .chain(STDLIB.iter().map(|(path, _)| *path))
+}
+
+// loads one module of the standard library, like `stdlib/math`, so that
+// programs can import it. the stdlib modules it imports itself, like
+// `stdlib/list` for `stdlib/database`, are loaded too.
+pub(crate) fn load_stdlib_module(
+    path: &str,
+    context: &mut EvaluationContext,
+) -> Result<(), RuntimeError> {
+    let module = if let Some((_, native)) = NATIVE_STDLIB.iter().find(|(name, _)| *name == path) {
+        native().into_module()
+    } else if let Some((path, source)) = STDLIB.iter().find(|(name, _)| *name == path) {
+        let program = blox_language::parser::Parser::new(path, source)?.parse()?;
+
+        for statement in &program.block.statements {
+            if let ast::Statement::Import(ast::Import(_, dependency)) = statement {
+                let loaded = context
+                    .import_cache
+                    .read()
+                    .map_err(|_| RuntimeError::PoisonedLock("import cache"))?
+                    .contains_key(dependency);
+                if !loaded && stdlib_modules().any(|module| module == dependency) {
+                    load_stdlib_module(dependency, context)?;
+                }
+            }
+        }
+
+        evalute_module(path, program, context)?
+    } else {
+        return Err(RuntimeError::ModuleNotFound(path.to_string()));
+    };
+
+    context
+        .import_cache
+        .write()
+        .map_err(|_| RuntimeError::PoisonedLock("import cache"))?
+        .insert(path.to_string(), module);
+    Ok(())
 }
 
 pub fn load_module(
     import_path: &str,
     context: &mut EvaluationContext,
 ) -> Result<Module, RuntimeError> {
-    let mut import_cache = context.import_cache.write().expect("import cache poisoned");
+    // the cache isn't kept locked while the module runs, since it may import
+    // other modules itself
+    let cached = context
+        .import_cache
+        .read()
+        .map_err(|_| RuntimeError::PoisonedLock("import cache"))?
+        .get(import_path)
+        .cloned();
 
-    if let Some(module) = import_cache.get(import_path) {
-        return Ok(module.clone());
+    if let Some(module) = cached {
+        return Ok(module);
     }
 
-    let filename = format!("{}/{}.blox", context.import_base_dir, import_path);
+    if let Some(module) = context.native_modules.get(import_path)? {
+        return Ok(module);
+    }
 
-    // resolve the filename
-    let filename = std::fs::canonicalize(&filename)
-        .map_err(|err| RuntimeError::ModuleNotFound(err.to_string()))?
-        .to_str()
-        .unwrap()
-        .to_string();
+    let filename = find_module(import_path, context)?;
+    let Some(filename) = filename.to_str().map(str::to_string) else {
+        return Err(RuntimeError::ModuleNotFound(
+            filename.to_string_lossy().to_string(),
+        ));
+    };
 
     let source = std::fs::read_to_string(&filename)
         .map_err(|_| RuntimeError::ModuleNotFound(filename.clone()))?;
     let module = load_module_from_string(import_path, &source, context)?;
 
-    import_cache.insert(import_path.to_string(), module.clone());
+    context
+        .import_cache
+        .write()
+        .map_err(|_| RuntimeError::PoisonedLock("import cache"))?
+        .insert(import_path.to_string(), module.clone());
 
     Ok(module)
 }
 
+// resolves an import to the first file that exists for it in the import
+// directories
+fn find_module(import_path: &str, context: &EvaluationContext) -> Result<PathBuf, RuntimeError> {
+    let mut not_found = None;
+
+    for directory in std::iter::once(&context.import_base_dir).chain(&context.import_paths) {
+        match std::fs::canonicalize(format!("{directory}/{import_path}.blox")) {
+            Ok(filename) => return Ok(filename),
+            Err(err) => {
+                not_found.get_or_insert(err);
+            }
+        }
+    }
+
+    Err(RuntimeError::ModuleNotFound(not_found.map_or_else(
+        || import_path.to_string(),
+        |err| err.to_string(),
+    )))
+}
+
 pub fn load_module_from_string(
     path: &str,
     source: &str,
     context: &EvaluationContext,
 ) -> Result<Module, RuntimeError> {
-    let parser = blox_language::parser::Parser::new(path, source);
+    let parser = blox_language::parser::Parser::new(path, source)?;
     let ast = parser.parse()?;
     let module = evalute_module(&path, ast, context)?;
     Ok(module)
@@ -124,12 +285,9 @@ pub fn evalute_module(
     context: &EvaluationContext,
 ) -> Result<Module, RuntimeError> {
     let mut context = context.child();
-    evaluate_block(&ast.block, &mut context)?;
+    execute_program(&ast, &mut context)?;
 
-    let module = Module::new(
-        path.to_string(),
-        context.scope.bindings.read().unwrap().clone(),
-    );
+    let module = Module::new(path.to_string(), context.scope.own_bindings()?);
     Ok(module)
 }
 
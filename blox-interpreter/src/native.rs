@@ -0,0 +1,174 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use blox_language::ast::Identifier;
+
+use crate::{
+    module::Module,
+    value::{Intrinsic, IntrinsicArguments, IntrinsicFuture},
+    RuntimeError, Value,
+};
+
+// rust functions and values grouped into a module that blox programs can
+// import, like `import { sha256 } from 'native/crypto'`
+#[derive(Debug, Clone)]
+pub struct NativeModule {
+    path: String,
+    exports: BTreeMap<Identifier, Value>,
+}
+
+impl NativeModule {
+    pub fn new(path: impl ToString) -> Self {
+        Self {
+            path: path.to_string(),
+            exports: BTreeMap::new(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn function(
+        self,
+        name: &str,
+        function: impl Fn(IntrinsicArguments) -> Result<Value, RuntimeError> + Send + Sync + 'static,
+    ) -> Self {
+        self.value(
+            name,
+            Value::Intrinsic(Intrinsic::new(name, Arc::new(function))),
+        )
+    }
+
+    pub fn async_function(
+        self,
+        name: &str,
+        function: impl Fn(IntrinsicArguments) -> IntrinsicFuture + Send + Sync + 'static,
+    ) -> Self {
+        self.value(
+            name,
+            Value::Intrinsic(Intrinsic::new_async(name, Arc::new(function))),
+        )
+    }
+
+    // adds an intrinsic under its own name, like one made by
+    // `#[blox_intrinsic]`
+    pub fn intrinsic(self, intrinsic: Intrinsic) -> Self {
+        let name = intrinsic.name.clone();
+        self.value(&name, Value::Intrinsic(intrinsic))
+    }
+
+    pub fn value(mut self, name: &str, value: Value) -> Self {
+        self.exports.insert(
+            Identifier {
+                name: name.to_string(),
+            },
+            value,
+        );
+        self
+    }
+
+    pub(crate) fn into_module(self) -> Module {
+        Module::new(self.path, self.exports)
+    }
+}
+
+// the native modules that can be imported, shared by an evaluation context
+// and all of its children
+#[derive(Debug, Clone, Default)]
+pub struct NativeModuleRegistry {
+    modules: Arc<RwLock<BTreeMap<String, Module>>>,
+}
+
+impl NativeModuleRegistry {
+    // makes the module importable, replacing any registered at the same path
+    pub fn register(&self, module: NativeModule) -> Result<(), RuntimeError> {
+        self.modules
+            .write()
+            .map_err(|_| RuntimeError::PoisonedLock("native module registry"))?
+            .insert(module.path.clone(), module.into_module());
+        Ok(())
+    }
+
+    pub fn paths(&self) -> Result<Vec<String>, RuntimeError> {
+        Ok(self
+            .modules
+            .read()
+            .map_err(|_| RuntimeError::PoisonedLock("native module registry"))?
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    pub(crate) fn get(&self, path: &str) -> Result<Option<Module>, RuntimeError> {
+        Ok(self
+            .modules
+            .read()
+            .map_err(|_| RuntimeError::PoisonedLock("native module registry"))?
+            .get(path)
+            .cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use blox_language::parser::Parser;
+
+    use super::*;
+    use crate::{execute_program, Backend, EvaluationContext};
+
+    fn text_module() -> NativeModule {
+        NativeModule::new("native/text")
+            .function("upper", |arguments| {
+                match arguments.get(&Identifier {
+                    name: "text".to_string(),
+                }) {
+                    Some(Value::String(text)) => Ok(Value::String(text.to_uppercase())),
+                    _ => Ok(Value::Void),
+                }
+            })
+            .value("separator", Value::String(", ".to_string()))
+    }
+
+    #[test]
+    fn test_import_native_module() {
+        let program = Parser::new(
+            "<test>",
+            "import { upper, separator } from 'native/text'\nupper(text: 'a') ++ separator",
+        )
+        .and_then(|parser| parser.parse())
+        .expect("parse error");
+
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            let context = EvaluationContext::default().with_backend(backend);
+            context
+                .native_modules
+                .register(text_module())
+                .expect("register module");
+
+            // the registry is shared with child contexts
+            let mut child = context.child();
+            assert_eq!(
+                execute_program(&program, &mut child),
+                Ok(Value::String("A, ".to_string())),
+                "{backend:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unregistered_native_module() {
+        let program = Parser::new("<test>", "import { upper } from 'native/text'")
+            .and_then(|parser| parser.parse())
+            .expect("parse error");
+
+        let mut context = EvaluationContext::default();
+        assert!(matches!(
+            execute_program(&program, &mut context),
+            Err(RuntimeError::ModuleNotFound(_))
+        ));
+        assert_eq!(context.native_modules.paths(), Ok(vec![]));
+    }
+}
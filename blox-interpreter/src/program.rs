@@ -1,13 +1,109 @@
-use blox_language::ast;
+use std::sync::Arc;
 
-use crate::{statement::execute_statement, EvaluationContext, RuntimeError, Value};
+use blox_language::{ast, location::Location};
+use tokio::runtime::Handle;
+
+use crate::{
+    bytecode,
+    expression::{call_function, evaluate_tail_expression, Arguments, Completion},
+    module::Backend,
+    statement::execute_statement,
+    EvaluationContext, RuntimeError, Value,
+};
 
 #[tracing::instrument(level = "debug", skip(program, context))]
 pub fn execute_program(
     program: &ast::Program,
     context: &mut EvaluationContext,
 ) -> Result<Value, RuntimeError> {
-    evaluate_block(&program.block, context)
+    match context.backend {
+        Backend::TreeWalker => evaluate_block(&program.block, context),
+        Backend::Bytecode => bytecode::run(Arc::new(bytecode::compile_program(program)?), context),
+    }
+}
+
+// calls a function or intrinsic value from rust with named arguments, like an
+// intrinsic calling a lambda it was passed. the call runs on the context's
+// backend, in a scope of its own.
+pub fn call_function_value<'a>(
+    function: &Value,
+    arguments: impl IntoIterator<Item = (&'a str, Value)>,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    call_function_value_at(
+        function.clone(),
+        arguments,
+        Location::synthetic("<native>"),
+        context,
+    )
+}
+
+// `call_function_value`, where stack traces show the call as made from
+// `call_site`
+pub(crate) fn call_function_value_at<'a>(
+    function: Value,
+    arguments: impl IntoIterator<Item = (&'a str, Value)>,
+    call_site: Location,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    let arguments: Arguments = arguments
+        .into_iter()
+        .map(|(name, value)| {
+            let name = ast::Identifier {
+                name: name.to_string(),
+            };
+            (name, value)
+        })
+        .collect();
+
+    match (context.backend, function) {
+        (Backend::Bytecode, Value::Function(function)) => {
+            let values = arguments.into_iter().map(|(_, value)| value).collect();
+            bytecode::call(function, values, call_site, context)
+        }
+        (_, function) => {
+            let callee = ast::Expression::Term(ast::ExpressionTerm::Identifier(
+                ast::Identifier {
+                    name: "<function>".to_string(),
+                },
+                ast::Address::Dynamic,
+            ));
+            call_function(&callee, &call_site, function, arguments, context)
+        }
+    }
+}
+
+// runs a program without blocking the async runtime it's awaited on. the
+// interpreter runs on one of the runtime's blocking threads, and waits there
+// for any async intrinsics the program calls, so other tasks keep running.
+pub async fn execute_program_async(
+    program: Arc<ast::Program>,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    let runtime =
+        Handle::try_current().map_err(|error| RuntimeError::AsyncRuntime(error.to_string()))?;
+
+    let mut blocking_context = context.clone();
+    blocking_context.runtime = Some(runtime.clone());
+
+    let task = runtime.spawn_blocking(move || {
+        let result = execute_program(&program, &mut blocking_context);
+        (result, blocking_context)
+    });
+
+    match task.await {
+        Ok((result, blocking_context)) => {
+            *context = EvaluationContext {
+                runtime: context.runtime.take(),
+                ..blocking_context
+            };
+            result
+        }
+        Err(error) if error.is_panic() => std::panic::resume_unwind(error.into_panic()),
+        Err(_) => Err(RuntimeError::AsyncRuntime(
+            "the program was cancelled before it finished".to_string(),
+        )),
+    }
 }
 
 pub fn evaluate_block(
@@ -23,14 +119,42 @@ pub fn evaluate_block(
     Ok(value)
 }
 
+// evaluates a function body, leaving a call in tail position for the caller
+// to make (see `call_function`)
+pub fn evaluate_tail_block(
+    block: &ast::Block,
+    context: &mut EvaluationContext,
+) -> Result<Completion, RuntimeError> {
+    let Some((last, statements)) = block.statements.split_last() else {
+        return Ok(Completion::Value(Value::Void));
+    };
+
+    for statement in statements {
+        execute_statement(statement, context)?;
+    }
+
+    match last {
+        ast::Statement::Expression(expression) => evaluate_tail_expression(expression, context),
+        statement => Ok(Completion::Value(execute_statement(statement, context)?)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::time::Duration;
+
     use blox_language::{error::ParseError, parser::Parser};
+    use proptest::prelude::*;
+    use tokio::sync::Barrier;
 
     use super::*;
+    use crate::{Intrinsic, Limit, Limits, RECEIVER};
+
+    // every test runs against both engines, which must behave identically
+    const BACKENDS: [Backend; 2] = [Backend::TreeWalker, Backend::Bytecode];
 
     fn parse(code: &str) -> Result<ast::Program, ParseError> {
-        let parser = Parser::new("<test>", code);
+        let parser = Parser::new("<test>", code)?;
         parser.parse()
     }
 
@@ -40,17 +164,19 @@ mod tests {
             Err(e) => panic!("Parsing error: {}", e),
         };
 
-        let mut context = EvaluationContext::default();
-        let result = execute_program(&program, &mut context);
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            let result = execute_program(&program, &mut context);
 
-        match &result {
-            Ok(value) => assert_eq!(
-                value, &expected,
-                "Expected: {expected}, got: {value}\nInput: {code}\nAST: {program:?}"
-            ),
-            Err(e) => panic!("Execution error: {}", e),
+            match &result {
+                Ok(value) => assert_eq!(
+                    value, &expected,
+                    "Expected: {expected}, got: {value} ({backend:?})\nInput: {code}\nAST: {program:?}"
+                ),
+                Err(e) => panic!("Execution error ({backend:?}): {}", e),
+            }
+            assert_eq!(result, Ok(expected.clone()));
         }
-        assert_eq!(result, Ok(expected));
     }
 
     #[test]
@@ -162,21 +288,27 @@ mod tests {
             "
             let x = [1, 2, 3]
             ",
-            Value::Array(vec![
-                Value::Number(1.into()),
-                Value::Number(2.into()),
-                Value::Number(3.into()),
-            ]),
+            Value::Array(
+                vec![
+                    Value::Number(1.into()),
+                    Value::Number(2.into()),
+                    Value::Number(3.into()),
+                ]
+                .into(),
+            ),
         );
         assert_result(
             "
             let x = [1 + 1, 2 + 2, 3 + 3]
             ",
-            Value::Array(vec![
-                Value::Number(2.into()),
-                Value::Number(4.into()),
-                Value::Number(6.into()),
-            ]),
+            Value::Array(
+                vec![
+                    Value::Number(2.into()),
+                    Value::Number(4.into()),
+                    Value::Number(6.into()),
+                ]
+                .into(),
+            ),
         );
         assert_result(
             "
@@ -207,6 +339,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_array_slices() {
+        assert_result(
+            "[1, 2, 3, 4][1..3]",
+            Value::Array(vec![Value::Number(2.into()), Value::Number(3.into())].into()),
+        );
+        assert_result(
+            "[1, 2, 3][1..]",
+            Value::Array(vec![Value::Number(2.into()), Value::Number(3.into())].into()),
+        );
+        assert_result(
+            "[1, 2][..5]",
+            Value::Array(vec![Value::Number(1.into()), Value::Number(2.into())].into()),
+        );
+        assert_result("[1, 2][3..]", Value::Array(vec![].into()));
+    }
+
     #[test]
     fn test_objects() {
         assert_result(
@@ -214,7 +363,7 @@ mod tests {
             let x = { a: 1, b: 2 }
             ",
             Value::Object(
-                [
+                vec![
                     ("a".to_string(), Value::Number(1.into())),
                     ("b".to_string(), Value::Number(2.into())),
                 ]
@@ -392,6 +541,387 @@ mod tests {
         assert_result("if 2 < 2 { 'error' }", Value::Void);
     }
 
+    #[test]
+    fn test_optional_chaining() {
+        assert_result(
+            "
+            let user = { address: { city: 'Ottawa' } }
+            user?.address?.city
+            ",
+            Value::String("Ottawa".to_string()),
+        );
+        assert_result(
+            "
+            let user = { name: 'Alice' }
+            user?.address?.city
+            ",
+            Value::Void,
+        );
+        assert_result(
+            "
+            let list = [1, 2, 3]
+            list?[5]
+            ",
+            Value::Void,
+        );
+        assert_result(
+            "
+            def missing() { if false { 1 } }
+            missing()?[0]
+            ",
+            Value::Void,
+        );
+        assert_result(
+            "
+            def double(x) { x * 2 }
+            let user = { name: 'Alice' }
+            user?.age?.double()
+            ",
+            Value::Void,
+        );
+        assert_result(
+            "
+            def double(x) { x * 2 }
+            let user = { age: 21 }
+            user?.age?.double()
+            ",
+            Value::Number(42.into()),
+        );
+
+        // a void optional link skips the rest of the chain
+        assert_result(
+            "
+            let user = { name: 'Alice' }
+            [user?.address.city, user?.address[0].upper(), user?.address.lines[1..]]
+            ",
+            Value::Array(vec![Value::Void, Value::Void, Value::Void].into()),
+        );
+        assert_result(
+            "
+            def nobody() { if false { 1 } }
+            nobody()?.address.city.upper()
+            ",
+            Value::Void,
+        );
+        assert_result(
+            "
+            let order = { customer: { name: 'Alice' } }
+            order?.customer.name.upper()
+            ",
+            Value::String("ALICE".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_optional_chaining_ends_at_parentheses() {
+        let program =
+            parse("let user = { name: 'Alice' }\n(user?.address).city").expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+
+            assert!(matches!(
+                execute_program(&program, &mut context),
+                Err(RuntimeError::NotAnObject { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_builtin_methods() {
+        assert_result("'héllo'.length()", Value::Number(5.into()));
+        assert_result("'  hi '.trim().upper()", Value::String("HI".to_string()));
+        assert_result(
+            "'a,b'.split(with: ',')",
+            Value::Array(vec!["a".into(), "b".into()].into()),
+        );
+        assert_result("let price = 2.5\nprice.round()", Value::Number(3.into()));
+        assert_result(
+            "[3, 1, 2].sort()",
+            Value::Array(vec![1.into(), 2.into(), 3.into()].into()),
+        );
+        assert_result(
+            "[1, 'a', :b].join(with: '-')",
+            Value::String("1-a-:b".to_string()),
+        );
+        assert_result("[].first()", Value::Void);
+        assert_result("{ b: 1, a: 2 }.keys()", vec!["a", "b"].into());
+        assert_result("{ a: 1 }.has(key: 'a')", Value::Boolean(true));
+        assert_result(
+            "let status = :paid\nstatus.name()",
+            Value::String("paid".to_string()),
+        );
+
+        // optional method calls skip built-in methods too
+        assert_result(
+            "
+            let user = { name: 'Alice' }
+            user?.nickname?.upper()
+            ",
+            Value::Void,
+        );
+
+        // functions shadow built-in methods, but other bindings don't
+        assert_result(
+            "
+            def length(list) { 0 }
+            [1, 2].length()
+            ",
+            Value::Number(0.into()),
+        );
+        assert_result(
+            "
+            let upper = 1
+            'abc'.upper()
+            ",
+            Value::String("ABC".to_string()),
+        );
+    }
+
+    #[test]
+    fn test_calling_functions_from_rust() {
+        let program = parse("|x| { x + 1 }").expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            let function = execute_program(&program, &mut context).expect("lambda");
+
+            for _ in 0..2 {
+                assert_eq!(
+                    call_function_value(&function, [("x", Value::Number(1.into()))], &mut context),
+                    Ok(Value::Number(2.into()))
+                );
+            }
+
+            // the bytecode machine compiles the body once, on the first call
+            let Value::Function(function) = function else {
+                panic!("expected a function, got {function}");
+            };
+            assert_eq!(
+                function.bytecode.get().is_some(),
+                backend == Backend::Bytecode
+            );
+        }
+    }
+
+    #[test]
+    fn test_intrinsic_methods() {
+        let program = parse("'hi'.shout(times: 2)").expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            let shout = Intrinsic::new(
+                "shout",
+                Arc::new(|arguments| {
+                    let argument = |name: &str| {
+                        arguments
+                            .get(&ast::Identifier {
+                                name: name.to_string(),
+                            })
+                            .cloned()
+                    };
+                    match (argument(RECEIVER), argument("times")) {
+                        (Some(Value::String(text)), Some(Value::Number(times))) => {
+                            Ok(Value::String(text.to_uppercase().repeat(times.try_into()?)))
+                        }
+                        _ => Ok(Value::Void),
+                    }
+                }),
+            );
+            context
+                .scope
+                .insert_binding(
+                    &ast::Identifier {
+                        name: "shout".to_string(),
+                    },
+                    Value::Intrinsic(shout),
+                )
+                .expect("insert binding");
+
+            assert_eq!(
+                execute_program(&program, &mut context),
+                Ok(Value::String("HIHI".to_string())),
+                "{backend:?}"
+            );
+        }
+
+        let program = parse("'abc'.missing()").expect("parse error");
+        let mut context = EvaluationContext::default();
+        assert_eq!(
+            execute_program(&program, &mut context)
+                .expect_err("strings have no method called missing")
+                .to_string(),
+            "missing is not defined, and a string has no method named missing"
+        );
+    }
+
+    #[test]
+    fn test_coalesce() {
+        assert_result(
+            "
+            let user = { name: 'Alice' }
+            user?.nickname ?? user.name
+            ",
+            Value::String("Alice".to_string()),
+        );
+        assert_result(
+            "
+            let user = { nickname: 'Al' }
+            user?.nickname ?? 'anonymous'
+            ",
+            Value::String("Al".to_string()),
+        );
+        assert_result(
+            "
+            let x = 0
+            x ?? 1
+            ",
+            Value::Number(0.into()),
+        );
+    }
+
+    #[test]
+    fn test_comparisons() {
+        assert_result("{ a: 1 } == { a: 1 }", Value::Boolean(true));
+        assert_result("{ a: 1 } == { a: 2 }", Value::Boolean(false));
+        assert_result("[{ a: 1 }] != [{ a: 1 }]", Value::Boolean(false));
+        assert_result("1 != 'one'", Value::Boolean(true));
+        assert_result("'apple' < 'banana'", Value::Boolean(true));
+        assert_result("[1, 2] < [1, 3]", Value::Boolean(true));
+        assert_result("true > false", Value::Boolean(true));
+        assert_result(
+            "
+            def f() { 1 }
+            f == f
+            ",
+            Value::Boolean(true),
+        );
+        assert_result(
+            "
+            def f() { 1 }
+            def g() { 1 }
+            f == g
+            ",
+            Value::Boolean(false),
+        );
+    }
+
+    #[test]
+    fn test_mismatched_comparison() {
+        for code in [
+            "1 < 'one'",
+            "[1] < ['a']",
+            "{ a: 1 } < { a: true }",
+            "let f = |x| { x }\n[f] < [f]",
+        ] {
+            let program = parse(code).expect("parse error");
+
+            for backend in BACKENDS {
+                let mut context = EvaluationContext::default().with_backend(backend);
+
+                assert!(
+                    matches!(
+                        execute_program(&program, &mut context),
+                        Err(RuntimeError::InvalidOperands { .. })
+                    ),
+                    "{code}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_function_comparison() {
+        assert_result(
+            "
+            def make() { |x| { x } }
+            let f = make()
+            let g = f
+            [f == g, make() == make()]
+            ",
+            Value::Array(vec![Value::Boolean(true), Value::Boolean(false)].into()),
+        );
+
+        let program = parse("let f = |x| { x }\nf < f").expect("parse error");
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+
+            assert!(matches!(
+                execute_program(&program, &mut context),
+                Err(RuntimeError::InvalidOperands { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_mutable_bindings() {
+        assert_result(
+            "
+            var x = 1
+            x = x + 1
+            x
+            ",
+            Value::Number(2.into()),
+        );
+        assert_result(
+            "
+            var list = [1]
+            list << 2
+            list
+            ",
+            Value::Array(vec![Value::Number(1.into()), Value::Number(2.into())].into()),
+        );
+        assert_result(
+            "
+            var user = { name: 'Alice' }
+            user.name = 'Bob'
+            user.name
+            ",
+            Value::String("Bob".to_string()),
+        );
+        assert_result(
+            "
+            var count = 0
+            def increment() { count = count + 1 }
+            increment()
+            increment()
+            count
+            ",
+            Value::Number(2.into()),
+        );
+        assert_result(
+            "
+            def numbers() { [1] }
+            numbers() << 2
+            ",
+            Value::Array(vec![Value::Number(1.into()), Value::Number(2.into())].into()),
+        );
+    }
+
+    #[test]
+    fn test_immutable_bindings() {
+        for (code, name) in [
+            ("let x = 1\nx = 2", "x"),
+            ("let list = [1]\nlist << 2", "list"),
+            ("let user = { name: 'Alice' }\nuser.name = 'Bob'", "user"),
+            ("def f(list) { list << 2 }\nf(list: [1])", "list"),
+            ("let x = 1\ndef f() { x = 2 }\nf()", "x"),
+        ] {
+            let program = parse(code).expect("parse error");
+
+            for backend in BACKENDS {
+                let mut context = EvaluationContext::default().with_backend(backend);
+
+                let error = execute_program(&program, &mut context).expect_err(code);
+                assert_eq!(
+                    error.root_cause(),
+                    &RuntimeError::ImmutableBinding(name.to_string()),
+                    "{code} ({backend:?})"
+                );
+            }
+        }
+    }
+
     #[test]
     pub fn test_fib() {
         assert_result(
@@ -410,4 +940,484 @@ mod tests {
             Value::Number(55.into()),
         );
     }
+
+    #[test]
+    fn test_tail_calls() {
+        assert_result(
+            "
+            def count(n, total) {
+              if n == 0 {
+                total
+              } else {
+                count(n: n - 1, total: total + 1)
+              }
+            }
+            count(n: 100000, total: 0)
+            ",
+            Value::Number(100000.into()),
+        );
+        assert_result(
+            "
+            def is_even(n) { if n == 0 { true } else { is_odd(n: n - 1) } }
+            def is_odd(n) { if n == 0 { false } else { is_even(n: n - 1) } }
+            is_even(n: 100001)
+            ",
+            Value::Boolean(false),
+        );
+    }
+
+    #[test]
+    fn test_lexical_scoping() {
+        assert_result(
+            "
+            def make_counter() {
+              var count = 0
+              || { count = count + 1 }
+            }
+            let counter = make_counter()
+            counter()
+            counter()
+            ",
+            Value::Number(2.into()),
+        );
+        assert_result(
+            "
+            def add(a) { |b| { a + b } }
+            let add_two = add(a: 2)
+            add_two(b: 3)
+            ",
+            Value::Number(5.into()),
+        );
+        assert_result(
+            "
+            let x = 'outer'
+            def f(flag) {
+              if flag { let x = 'inner' }
+              x
+            }
+            [f(flag: true), f(flag: false)]
+            ",
+            Value::Array(
+                vec![
+                    Value::String("inner".to_string()),
+                    Value::String("outer".to_string()),
+                ]
+                .into(),
+            ),
+        );
+        assert_result(
+            "
+            let x = 1
+            def f() {
+              let y = x
+              let x = 2
+              y + x
+            }
+            f()
+            ",
+            Value::Number(3.into()),
+        );
+    }
+
+    #[test]
+    fn test_top_level_redefinition() {
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+
+            for code in ["def f() { 1 }\ndef g() { f() }", "def f() { 2 }"] {
+                let program = parse(code).expect("parse error");
+                execute_program(&program, &mut context).expect("execution error");
+            }
+
+            let program = parse("g()").expect("parse error");
+            assert_eq!(
+                execute_program(&program, &mut context),
+                Ok(Value::Number(2.into())),
+                "{backend:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_backends_report_the_same_errors() {
+        for code in [
+            "undefined_name",
+            "1 + 'one'",
+            "if 'yes' { 1 }",
+            "[1, 2][5]",
+            "[1, 2]['a']",
+            "{ a: 1 }.b",
+            "1.a",
+            "let x = 1\nx()",
+            "def f() { 1 }\n2.f()",
+            "[1, 2][..'a']",
+            "1 = 2",
+            "let list = [[1]]\nlist[0][0] = 2",
+            "def f(n) { if n == 0 { n.missing } else { f(n: n - 1) } }\nf(n: 3)",
+            "79228162514264337593543950335 + 1",
+            "[1, 2][..0 - 1]",
+            "'abc'.missing()",
+            "'abc'.split(with: 1)",
+            "let missing = 1\n'abc'.missing()",
+        ] {
+            let program = parse(code).expect("parse error");
+
+            let results: Vec<_> = BACKENDS
+                .map(|backend| {
+                    let mut context = EvaluationContext::default().with_backend(backend);
+                    execute_program(&program, &mut context)
+                })
+                .into_iter()
+                .collect();
+
+            assert!(results[0].is_err(), "{code}");
+            assert_eq!(results[0], results[1], "{code}");
+        }
+    }
+
+    #[test]
+    fn test_arithmetic_errors() {
+        for code in [
+            "79228162514264337593543950335 + 1",
+            "0 - 79228162514264337593543950335 - 1",
+            "79228162514264337593543950335 * 2",
+        ] {
+            let program = parse(code).expect("parse error");
+
+            for backend in BACKENDS {
+                let mut context = EvaluationContext::default().with_backend(backend);
+
+                assert!(
+                    matches!(
+                        execute_program(&program, &mut context),
+                        Err(RuntimeError::ArithmeticOverflow { .. })
+                    ),
+                    "{code} ({backend:?})"
+                );
+            }
+        }
+
+        let program = parse("[1, 2, 3][..0 - 1]").expect("parse error");
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+
+            assert!(
+                matches!(
+                    execute_program(&program, &mut context),
+                    Err(RuntimeError::InvalidSliceBound { .. })
+                ),
+                "{backend:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_execution_limits() {
+        let cases = [
+            (
+                "def spin(n) { spin(n: n + 1) }\nspin(n: 0)",
+                Limits {
+                    max_steps: Some(10_000),
+                    ..Limits::default()
+                },
+                Limit::Steps(10_000),
+            ),
+            (
+                "def deep(n) { 1 + deep(n: n + 1) }\ndeep(n: 0)",
+                Limits {
+                    max_call_depth: Some(100),
+                    ..Limits::default()
+                },
+                Limit::CallDepth(100),
+            ),
+            (
+                "def grow(list) { grow(list: list ++ ['padding']) }\ngrow(list: [])",
+                Limits {
+                    max_bytes: Some(10_000),
+                    ..Limits::default()
+                },
+                Limit::Memory(10_000),
+            ),
+            // strings built by intrinsics are charged too
+            (
+                "def shout(text) { shout(text: text.upper()) }\nshout(text: 'abcdefghij'.repeat(count: 100))",
+                Limits {
+                    max_steps: Some(1_000_000),
+                    max_bytes: Some(10_000),
+                    ..Limits::default()
+                },
+                Limit::Memory(10_000),
+            ),
+            // an intrinsic calling back into blox is a call of its own
+            (
+                "{ a: 1 }.map_values(fn: |value, key| { value })",
+                Limits {
+                    max_call_depth: Some(1),
+                    ..Limits::default()
+                },
+                Limit::CallDepth(1),
+            ),
+            (
+                "def spin(n) { spin(n: n + 1) }\nspin(n: 0)",
+                Limits {
+                    timeout: Some(Duration::from_millis(20)),
+                    ..Limits::default()
+                },
+                Limit::Timeout(Duration::from_millis(20)),
+            ),
+        ];
+
+        for (code, limits, limit) in cases {
+            let program = parse(code).expect("parse error");
+
+            for backend in BACKENDS {
+                let mut context = EvaluationContext::default()
+                    .with_backend(backend)
+                    .with_limits(limits.clone());
+
+                let error = execute_program(&program, &mut context).expect_err(code);
+                assert_eq!(
+                    error.root_cause(),
+                    &RuntimeError::LimitExceeded(limit.clone()),
+                    "{code} ({backend:?})"
+                );
+                assert_eq!(context.budget.depth(), 0, "{code} ({backend:?})");
+            }
+        }
+    }
+
+    #[test]
+    fn test_programs_within_limits() {
+        let program = parse(
+            "
+            def count(n, list) { if n == 0 { list } else { count(n: n - 1, list: list ++ [n]) } }
+            count(n: 100, list: [])[99]
+            ",
+        )
+        .expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default()
+                .with_backend(backend)
+                .with_limits(Limits {
+                    max_steps: Some(100_000),
+                    max_call_depth: Some(10),
+                    max_bytes: Some(100_000),
+                    timeout: Some(Duration::from_secs(10)),
+                });
+
+            assert_eq!(
+                execute_program(&program, &mut context),
+                Ok(Value::Number(1.into())),
+                "{backend:?}"
+            );
+            assert!(context.budget.steps() > 0);
+            assert!(context.budget.bytes() > 0);
+        }
+    }
+
+    #[test]
+    fn test_stack_traces() {
+        let program = parse(
+            "def inner(x) { x + 'one' }
+def outer(x) { 1 + inner(x: x) }
+let twice = |x| { outer(x: x) * 2 }
+twice(x: 1)",
+        )
+        .expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            let error = execute_program(&program, &mut context).expect_err("inner fails");
+
+            assert!(matches!(
+                error.root_cause(),
+                RuntimeError::InvalidOperands { .. }
+            ));
+
+            let trace: Vec<_> = error
+                .stack_trace()
+                .iter()
+                .map(|frame| {
+                    (
+                        frame.function.as_str(),
+                        frame.module.as_str(),
+                        frame.call_site.range.start_point.row + 1,
+                    )
+                })
+                .collect();
+            assert_eq!(
+                trace,
+                vec![
+                    ("inner", "<test>", 2),
+                    ("outer", "<test>", 3),
+                    ("<lambda>", "<test>", 4),
+                ],
+                "{backend:?}"
+            );
+
+            let message = error.to_string();
+            assert!(
+                message.contains("\n    at inner in <test>, called from <test>:2:"),
+                "{message}"
+            );
+        }
+    }
+
+    // a context with an async intrinsic, `rendezvous`, which only returns once
+    // it's been called by as many programs as the barrier was made for
+    fn rendezvous_context(backend: Backend, barrier: Arc<Barrier>) -> EvaluationContext {
+        let context = EvaluationContext::default().with_backend(backend);
+        let rendezvous = Intrinsic::new_async(
+            "rendezvous",
+            Arc::new(move |_arguments| {
+                let barrier = barrier.clone();
+                Box::pin(async move {
+                    barrier.wait().await;
+                    Ok(Value::Number(1.into()))
+                })
+            }),
+        );
+
+        context
+            .scope
+            .insert_binding(
+                &ast::Identifier {
+                    name: "rendezvous".to_string(),
+                },
+                Value::Intrinsic(rendezvous),
+            )
+            .expect("insert binding");
+        context
+    }
+
+    #[tokio::test]
+    async fn test_async_intrinsics() {
+        let program = Arc::new(parse("rendezvous() + 1").expect("parse error"));
+
+        for backend in BACKENDS {
+            // neither program can finish until both are waiting, so this only
+            // completes if they run concurrently on a single-threaded runtime
+            let barrier = Arc::new(Barrier::new(2));
+            let mut first = rendezvous_context(backend, barrier.clone());
+            let mut second = rendezvous_context(backend, barrier);
+
+            let (first, second) = tokio::join!(
+                execute_program_async(program.clone(), &mut first),
+                execute_program_async(program.clone(), &mut second),
+            );
+
+            assert_eq!(first, Ok(Value::Number(2.into())), "{backend:?}");
+            assert_eq!(second, Ok(Value::Number(2.into())), "{backend:?}");
+        }
+    }
+
+    #[test]
+    fn test_async_intrinsics_outside_a_runtime() {
+        let program = parse("rendezvous() + 1").expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = rendezvous_context(backend, Arc::new(Barrier::new(1)));
+
+            assert_eq!(
+                execute_program(&program, &mut context),
+                Ok(Value::Number(2.into())),
+                "{backend:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_intrinsics_do_not_block_the_runtime() {
+        let program = parse("rendezvous()").expect("parse error");
+
+        for backend in BACKENDS {
+            let mut context = rendezvous_context(backend, Arc::new(Barrier::new(1)));
+
+            let error = execute_program(&program, &mut context).expect_err("would block");
+            assert_eq!(
+                error.root_cause(),
+                &RuntimeError::BlockingAsyncCall("rendezvous".to_string()),
+                "{backend:?}"
+            );
+        }
+    }
+
+    // fragments of blox that are likely to fit together in interesting ways
+    const TOKENS: &[&str] = &[
+        "let",
+        "var",
+        "def",
+        "if",
+        "else",
+        "x",
+        "f",
+        "n",
+        "=",
+        "(",
+        ")",
+        "{",
+        "}",
+        "[",
+        "]",
+        ",",
+        ":",
+        "..",
+        "+",
+        "-",
+        "*",
+        "/",
+        "++",
+        "<<",
+        "|>",
+        "??",
+        "==",
+        "<",
+        "|",
+        ".",
+        "?.",
+        "?[",
+        "0",
+        "1",
+        "79228162514264337593543950335",
+        "'a'",
+        ":a",
+        "true",
+        "\n",
+    ];
+
+    // whatever the program, parsing and running it either succeeds or reports
+    // an error; it never panics
+    fn check_program(code: &str) {
+        let Ok(program) = parse(code) else {
+            return;
+        };
+
+        for backend in BACKENDS {
+            let mut context = EvaluationContext::default()
+                .with_backend(backend)
+                .with_limits(Limits {
+                    max_steps: Some(10_000),
+                    max_call_depth: Some(50),
+                    max_bytes: Some(1_000_000),
+                    timeout: Some(Duration::from_secs(1)),
+                });
+
+            let _ = execute_program(&program, &mut context);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_arbitrary_source_does_not_panic(code in "\\PC{0,64}") {
+            check_program(&code);
+        }
+
+        #[test]
+        fn test_arbitrary_tokens_do_not_panic(
+            tokens in prop::collection::vec(prop::sample::select(TOKENS), 0..40)
+        ) {
+            check_program(&tokens.join(" "));
+        }
+    }
 }
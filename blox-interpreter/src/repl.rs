@@ -35,9 +35,7 @@ pub fn start_repl(mut context: EvaluationContext) -> Result<(), BloxReplError> {
             Ok(line) => {
                 editor.add_history_entry(&line)?;
 
-                let parser = Parser::new("<repl>", &line);
-
-                match parser.parse() {
+                match Parser::new("<repl>", &line).and_then(|parser| parser.parse()) {
                     Ok(ast) => {
                         let value = execute_program(&ast, &mut context);
                         match value {
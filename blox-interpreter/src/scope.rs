@@ -1,6 +1,6 @@
 use std::{
-    collections::BTreeMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, BTreeSet},
+    sync::{Arc, LockResult, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
 };
 
 use blox_language::ast;
@@ -11,35 +11,51 @@ use crate::{value::Value, RuntimeError};
 pub struct Scope {
     pub parent: Option<Arc<Scope>>,
     pub bindings: RwLock<BTreeMap<ast::Identifier, Value>>,
+    // names in `bindings` that were declared with `var` and may be reassigned
+    pub mutable_bindings: RwLock<BTreeSet<ast::Identifier>>,
+    // the locals of a function call, indexed by the slots the resolver
+    // assigned them. a slot is empty until its declaration runs.
+    pub locals: RwLock<Vec<Option<Local>>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Local {
+    pub value: Value,
+    pub mutable: bool,
 }
 
 impl Clone for Scope {
     fn clone(&self) -> Self {
-        let bindings = self.bindings.read().expect("bindings poisoned in clone");
-
         Scope {
             parent: self.parent.clone(),
-            bindings: RwLock::new(bindings.clone()),
+            bindings: RwLock::new(recover(self.bindings.read()).clone()),
+            mutable_bindings: RwLock::new(recover(self.mutable_bindings.read()).clone()),
+            locals: RwLock::new(recover(self.locals.read()).clone()),
         }
     }
 }
 
 impl PartialEq for Scope {
     fn eq(&self, other: &Self) -> bool {
-        if self.parent != other.parent {
-            return false;
-        }
+        self.parent == other.parent
+            && *recover(self.bindings.read()) == *recover(other.bindings.read())
+            && *recover(self.locals.read()) == *recover(other.locals.read())
+    }
+}
 
-        let Ok(lhs) = self.bindings.read() else {
-            panic!("binding is poisoned in eq")
-        };
+// cloning and comparing can't fail, so they use the contents of poisoned
+// locks as they are; everything else reports them as errors
+fn recover<Guard>(result: LockResult<Guard>) -> Guard {
+    result.unwrap_or_else(PoisonError::into_inner)
+}
 
-        let Ok(rhs) = other.bindings.read() else {
-            panic!("binding is poisoned in eq")
-        };
+fn read<T>(lock: &RwLock<T>) -> Result<RwLockReadGuard<'_, T>, RuntimeError> {
+    lock.read().map_err(|_| RuntimeError::PoisonedLock("scope"))
+}
 
-        &*lhs == &*rhs
-    }
+fn write<T>(lock: &RwLock<T>) -> Result<RwLockWriteGuard<'_, T>, RuntimeError> {
+    lock.write()
+        .map_err(|_| RuntimeError::PoisonedLock("scope"))
 }
 
 impl Scope {
@@ -47,19 +63,150 @@ impl Scope {
         Arc::new(Scope {
             parent: Some(self.clone()),
             bindings: RwLock::new(BTreeMap::new()),
+            mutable_bindings: RwLock::new(BTreeSet::new()),
+            locals: RwLock::new(Vec::new()),
+        })
+    }
+
+    // the scope of a call to a function closing over this one
+    pub fn child_with_locals(self: &Arc<Scope>, count: usize) -> Arc<Self> {
+        Arc::new(Scope {
+            parent: Some(self.clone()),
+            bindings: RwLock::new(BTreeMap::new()),
+            mutable_bindings: RwLock::new(BTreeSet::new()),
+            locals: RwLock::new(vec![None; count]),
         })
     }
 
-    pub fn insert_binding(&self, name: &ast::Identifier, value: Value) {
-        let mut bindings = self.bindings.write().unwrap();
+    fn ancestor(&self, depth: usize) -> Option<&Scope> {
+        let mut scope = self;
+        for _ in 0..depth {
+            scope = scope.parent.as_deref()?;
+        }
+        Some(scope)
+    }
+
+    // binds a name declared by `let`, `var`, `def`, `import` or a parameter
+    pub fn declare(
+        &self,
+        name: &ast::Identifier,
+        address: &ast::Address,
+        value: Value,
+        mutable: bool,
+    ) -> Result<(), RuntimeError> {
+        if let ast::Address::Local(slots) = address {
+            if let Some((_, slot)) = slots.first() {
+                if let Some(local) = write(&self.locals)?.get_mut(*slot) {
+                    *local = Some(Local { value, mutable });
+                    return Ok(());
+                }
+            }
+        }
+
+        if mutable {
+            self.insert_mutable_binding(name, value)
+        } else {
+            self.insert_binding(name, value)
+        }
+    }
+
+    // reads a variable from the first initialized slot of its address, or by
+    // name if it has none; see `ast::Address`
+    pub fn lookup(
+        &self,
+        name: &ast::Identifier,
+        address: &ast::Address,
+    ) -> Result<Value, RuntimeError> {
+        if let ast::Address::Local(slots) = address {
+            for (depth, slot) in slots {
+                let Some(scope) = self.ancestor(*depth) else {
+                    continue;
+                };
+
+                if let Some(Some(local)) = read(&scope.locals)?.get(*slot) {
+                    return Ok(local.value.clone());
+                }
+            }
+        }
+
+        self.get_binding(name)
+    }
+
+    pub fn assign(
+        &self,
+        name: &ast::Identifier,
+        address: &ast::Address,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        if let ast::Address::Local(slots) = address {
+            for (depth, slot) in slots {
+                let Some(scope) = self.ancestor(*depth) else {
+                    continue;
+                };
+
+                if let Some(Some(local)) = write(&scope.locals)?.get_mut(*slot) {
+                    if !local.mutable {
+                        return Err(RuntimeError::ImmutableBinding(name.to_string()));
+                    }
+
+                    local.value = value;
+                    return Ok(());
+                }
+            }
+        }
+
+        self.assign_binding(name, value)
+    }
+
+    pub fn insert_binding(&self, name: &ast::Identifier, value: Value) -> Result<(), RuntimeError> {
+        let mut bindings = write(&self.bindings)?;
+        let mut mutable_bindings = write(&self.mutable_bindings)?;
+
+        bindings.insert(name.clone(), value);
+        mutable_bindings.remove(name);
+        Ok(())
+    }
+
+    pub fn insert_mutable_binding(
+        &self,
+        name: &ast::Identifier,
+        value: Value,
+    ) -> Result<(), RuntimeError> {
+        let mut bindings = write(&self.bindings)?;
+        let mut mutable_bindings = write(&self.mutable_bindings)?;
 
         bindings.insert(name.clone(), value);
+        mutable_bindings.insert(name.clone());
+        Ok(())
+    }
+
+    // updates an existing binding in whichever scope declared it, which must
+    // have been declared mutable
+    pub fn assign_binding(&self, name: &ast::Identifier, value: Value) -> Result<(), RuntimeError> {
+        let mut scope = self;
+        loop {
+            let mut bindings = write(&scope.bindings)?;
+            match bindings.get_mut(name) {
+                Some(binding) => {
+                    if !read(&scope.mutable_bindings)?.contains(name) {
+                        return Err(RuntimeError::ImmutableBinding(name.to_string()));
+                    }
+
+                    *binding = value;
+                    return Ok(());
+                }
+                None => match &scope.parent {
+                    Some(parent) => scope = parent,
+                    None => return Err(RuntimeError::UndefinedVariable(name.to_string())),
+                },
+            }
+        }
     }
 
     pub fn get_binding(&self, name: &ast::Identifier) -> Result<Value, RuntimeError> {
         let mut scope = self;
         loop {
-            let bindings = scope.bindings.read().unwrap();
+            let bindings = read(&scope.bindings)?;
             match bindings.get(name) {
                 Some(value) => return Ok(value.clone()),
                 None => match &scope.parent {
@@ -69,4 +216,9 @@ impl Scope {
             }
         }
     }
+
+    // a copy of the names bound directly in this scope, not its parents
+    pub fn own_bindings(&self) -> Result<BTreeMap<ast::Identifier, Value>, RuntimeError> {
+        Ok(read(&self.bindings)?.clone())
+    }
 }
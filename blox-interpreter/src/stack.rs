@@ -0,0 +1,10 @@
+//! Growing the stack for code that recurses as deeply as the program or value
+//! it works on is nested, like evaluating an expression or writing an array.
+
+const RED_ZONE: usize = 128 * 1024; // grow when there's less than this amount remaining
+const BLOCK_SIZE: usize = 1024 * 1024; // allocate this much at a time when growing
+
+// runs `f`, on a new stack segment if this one is close to running out
+pub(crate) fn maybe_grow<R>(f: impl FnOnce() -> R) -> R {
+    stacker::maybe_grow(RED_ZONE, BLOCK_SIZE, f)
+}
@@ -0,0 +1,66 @@
+use blox_language::location::Location;
+
+use crate::{value::Function, Intrinsic};
+
+// only this many frames are printed, innermost first, so that runaway
+// recursion doesn't produce pages of identical lines
+const PRINTED_FRAMES: usize = 20;
+
+// a call that was in progress when an error happened
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    // the function's name, or `<lambda>`
+    pub function: String,
+    // the module the function was defined in, or `<intrinsic>`
+    pub module: String,
+    // where the function was called from
+    pub call_site: Location,
+}
+
+impl StackFrame {
+    pub(crate) fn function(function: &Function, call_site: &Location) -> Self {
+        let definition = &function.definition;
+
+        Self {
+            function: match &definition.name {
+                Some(name) => name.name.clone(),
+                None => "<lambda>".to_string(),
+            },
+            module: definition.body.location.file.to_string(),
+            call_site: call_site.clone(),
+        }
+    }
+
+    pub(crate) fn intrinsic(intrinsic: &Intrinsic, call_site: &Location) -> Self {
+        Self {
+            function: intrinsic.name.clone(),
+            module: "<intrinsic>".to_string(),
+            call_site: call_site.clone(),
+        }
+    }
+}
+
+impl std::fmt::Display for StackFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} in {}, called from {}",
+            self.function, self.module, self.call_site
+        )
+    }
+}
+
+pub(crate) fn write_stack_trace(
+    f: &mut std::fmt::Formatter<'_>,
+    stack: &[StackFrame],
+) -> std::fmt::Result {
+    for frame in stack.iter().take(PRINTED_FRAMES) {
+        write!(f, "\n    at {frame}")?;
+    }
+
+    if stack.len() > PRINTED_FRAMES {
+        write!(f, "\n    ... {} more", stack.len() - PRINTED_FRAMES)?;
+    }
+
+    Ok(())
+}
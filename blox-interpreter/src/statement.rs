@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use blox_language::ast;
 
 use crate::{
@@ -16,40 +18,48 @@ pub fn execute_statement(
             let value = evaluate_expression(expression, context)?;
             Ok(value)
         }
-        ast::Statement::Binding(lhs, rhs) => {
+        ast::Statement::Binding(lhs, address, rhs) => {
+            let value = evaluate_expression(rhs, context)?;
+            context.scope.declare(lhs, address, value.clone(), false)?;
+            Ok(value)
+        }
+        ast::Statement::MutableBinding(lhs, address, rhs) => {
             let value = evaluate_expression(rhs, context)?;
-            context.scope.insert_binding(lhs, value.clone());
+            context.scope.declare(lhs, address, value.clone(), true)?;
             Ok(value)
         }
         ast::Statement::Definition(definition) => {
-            let closure = context.scope.child();
-
-            let function = Value::Function(Function {
-                definition: definition.clone(),
-                closure: closure.clone(),
-            });
+            let function = Value::Function(Function::new(
+                Arc::new(definition.clone()),
+                context.scope.clone(),
+            ));
 
             if let Some(name) = &definition.name {
-                context.scope.insert_binding(name, function.clone());
+                context
+                    .scope
+                    .declare(name, &definition.address, function.clone(), false)?;
             }
 
             Ok(function)
         }
-        ast::Statement::Import(import) => {
-            let module = load_module(&import.1, context)?;
-
-            for symbol in &import.0 {
-                let value = module.export(&symbol.0)?;
-                let name = if symbol.1 == None {
-                    symbol.0.clone()
-                } else {
-                    symbol.1.clone().unwrap()
-                };
-
-                context.scope.insert_binding(&name, value.clone());
-            }
+        ast::Statement::Import(import) => execute_import(import, context),
+    }
+}
 
-            Ok(Value::Module(module))
-        }
+pub fn execute_import(
+    import: &ast::Import,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    let module = load_module(&import.1, context)?;
+
+    for symbol in &import.0 {
+        let value = module.export(&symbol.0)?;
+        let name = symbol.1.as_ref().unwrap_or(&symbol.0);
+
+        context
+            .scope
+            .declare(name, &symbol.2, value.clone(), false)?;
     }
+
+    Ok(Value::Module(module))
 }
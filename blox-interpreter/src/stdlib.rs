@@ -0,0 +1,90 @@
+//! The parts of the standard library written in rust. They're imported like
+//! any other module, e.g. `import { map } from 'stdlib/list'`.
+
+use crate::{
+    expression::truthiness, native::NativeModule, value::Callback, EvaluationContext, RuntimeError,
+    Value,
+};
+
+pub(crate) mod json;
+pub(crate) mod list;
+pub(crate) mod log;
+pub(crate) mod math;
+pub(crate) mod object;
+pub(crate) mod regex;
+pub(crate) mod string;
+pub(crate) mod test;
+pub(crate) mod time;
+
+pub(crate) const NATIVE_STDLIB: [(&str, fn() -> NativeModule); 9] = [
+    ("stdlib/list", list::module),
+    ("stdlib/string", string::module),
+    ("stdlib/object", object::module),
+    ("stdlib/regex", regex::module),
+    ("stdlib/json", json::module),
+    ("stdlib/math", math::module),
+    ("stdlib/time", time::module),
+    ("stdlib/log", log::module),
+    ("stdlib/test", test::module),
+];
+
+// calls a condition passed to `function` as `fn`, which must return something
+// that can be used as a condition
+fn call_predicate<'a>(
+    function: &str,
+    condition: &Callback,
+    arguments: impl IntoIterator<Item = (&'a str, Value)>,
+    context: &mut EvaluationContext,
+) -> Result<bool, RuntimeError> {
+    let result = condition.call(arguments, context)?;
+
+    truthiness(&result).ok_or_else(|| RuntimeError::InvalidCallbackResult {
+        function: function.to_string(),
+        argument: "fn".to_string(),
+        expected: "a boolean".to_string(),
+        value: result,
+    })
+}
+
+// runs `code` with every export of the stdlib module at `path` imported, on
+// both backends, which must agree. errors lose their stack traces, and the
+// clock is stopped at 2024-05-01T12:00:00Z.
+#[cfg(test)]
+fn run_with_module(path: &str, code: &str) -> Result<Value, RuntimeError> {
+    use std::sync::Arc;
+
+    use blox_language::parser::Parser;
+
+    use crate::{execute_program, load_stdlib, Backend, Limits};
+
+    let Some((_, module)) = NATIVE_STDLIB.iter().find(|(name, _)| *name == path) else {
+        panic!("no stdlib module {path}");
+    };
+    let names: Vec<_> = module()
+        .into_module()
+        .exports
+        .into_keys()
+        .map(|name| name.name)
+        .collect();
+    let code = format!("import {{ {} }} from '{path}'\n{code}", names.join(", "));
+    let program = Parser::new("<test>", &code)
+        .and_then(|parser| parser.parse())
+        .expect("parse error");
+
+    let mut results = [Backend::TreeWalker, Backend::Bytecode].map(|backend| {
+        let mut context = EvaluationContext::default()
+            .with_backend(backend)
+            .with_limits(Limits {
+                max_bytes: Some(1_000_000),
+                ..Limits::default()
+            })
+            .with_clock(Arc::new(|| {
+                jiff::Timestamp::from_second(1_714_564_800).expect("valid timestamp")
+            }));
+        load_stdlib(&mut context).expect("the stdlib loads");
+        execute_program(&program, &mut context).map_err(RuntimeError::into_root_cause)
+    });
+
+    assert_eq!(results[0], results[1], "{code}");
+    std::mem::replace(&mut results[0], Ok(Value::Void))
+}
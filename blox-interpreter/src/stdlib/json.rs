@@ -0,0 +1,253 @@
+//! `stdlib/json`: reading and writing JSON, for API endpoints, webhook
+//! payloads and config files.
+//!
+//! Values are read and written through their serde mapping, described in
+//! `value::serialization`, so `parse` reads back exactly what `stringify`
+//! wrote:
+//!
+//! - `null` is void
+//! - booleans, strings, arrays and objects are themselves. objects are
+//!   written with their keys in sorted order, and when a key appears more
+//!   than once the last one wins.
+//! - numbers are kept exact both ways: `0.1` is read and written as `0.1`,
+//!   not the binary fraction nearest to it, and `1.50` keeps its zero.
+//!   numbers with more significant digits than a number can hold are
+//!   rounded, and ones too large for it can't be read.
+//! - symbols, times and durations are written as single-field objects, like
+//!   `{"$symbol": "draft"}`, `{"$time": "2024-05-01T09:30:00+02:00[Europe/Paris]"}`
+//!   and `{"$duration": "PT1H30M"}`, and read back as themselves
+//! - functions, intrinsics, modules and regexes can't be written
+//! - arrays and objects can be nested 128 deep when they're read
+
+use std::io;
+
+use blox_macros::blox_intrinsic;
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, PrettyFormatter};
+
+use crate::{budget::Budget, native::NativeModule, EvaluationContext, RuntimeError, Value};
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/json")
+        .intrinsic(parse_intrinsic())
+        .intrinsic(stringify_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn parse(text: String, context: &mut EvaluationContext) -> Result<Value, RuntimeError> {
+    let value: Value = serde_json::from_str(&text).map_err(|error| {
+        let (line, column) = (error.line(), error.column());
+        let message = error.to_string();
+        let message = message
+            .strip_suffix(&format!(" at line {line} column {column}"))
+            .unwrap_or(&message);
+
+        RuntimeError::InvalidJson {
+            message: message.to_string(),
+            line,
+            column,
+        }
+    })?;
+
+    context.budget.charge(value)
+}
+
+// writes the value on one line, unless `pretty` asks for it to be indented
+// over several
+#[blox_intrinsic]
+pub(crate) fn stringify(
+    value: Value,
+    pretty: Option<bool>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let mut writer = Writer {
+        json: Vec::new(),
+        budget: &context.budget,
+        error: None,
+    };
+
+    let written = match pretty.unwrap_or(false) {
+        true => value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut writer,
+            PrettyFormatter::new(),
+        )),
+        false => value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut writer,
+            CompactFormatter,
+        )),
+    };
+    if let Some(error) = writer.error {
+        return Err(error);
+    }
+    written.map_err(|error| RuntimeError::SerializationError(error.to_string()))?;
+
+    String::from_utf8(writer.json).map_err(|error| RuntimeError::Internal(error.to_string()))
+}
+
+// values can share their members, so the JSON for one can be far larger than
+// the value itself. it's charged to the budget as it's written, and writing
+// stops as soon as the budget runs out.
+struct Writer<'a> {
+    json: Vec<u8>,
+    budget: &'a Budget,
+    // why writing stopped, when it was the budget
+    error: Option<RuntimeError>,
+}
+
+impl io::Write for Writer<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if let Err(error) = self.budget.allocate(buf.len()) {
+            let message = error.to_string();
+            self.error = Some(error);
+            return Err(io::Error::other(message));
+        }
+
+        self.json.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/json", code)
+    }
+
+    fn string(text: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::String(text.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            run(r#"parse(text: '{"name": "Alice", "tags": ["a", null], "admin": false}')"#),
+            Ok(Value::Object(
+                [
+                    ("admin".to_string(), Value::Boolean(false)),
+                    ("name".to_string(), "Alice".into()),
+                    (
+                        "tags".to_string(),
+                        Value::Array(vec!["a".into(), Value::Void].into())
+                    ),
+                ]
+                .into_iter()
+                .collect()
+            ))
+        );
+        assert_eq!(
+            run("parse(text: '[0.1, -2.50, 1e3, 12345678901234567890.123456789]')"),
+            Ok(Value::Array(
+                vec![
+                    Value::Number(Decimal::new(1, 1)),
+                    Value::Number(Decimal::new(-250, 2)),
+                    Value::Number(1000.into()),
+                    Value::Number("12345678901234567890.123456789".parse().unwrap()),
+                ]
+                .into()
+            ))
+        );
+        assert_eq!(
+            run(r#"parse(text: '"tab\t, é, 👍"')"#),
+            string("tab\t, é, 👍")
+        );
+        assert_eq!(
+            run("[parse(text: '-1.5E+3'), parse(text: '1e-40'), parse(text: '0.00000000000000000000000000012')]"),
+            Ok(Value::Array(
+                vec![
+                    Value::Number((-1500).into()),
+                    Value::Number(0.into()),
+                    Value::Number(Decimal::new(1, 28)),
+                ]
+                .into()
+            ))
+        );
+        assert_eq!(run(r#"parse(text: ' {"a": 1, "a": 2} ').a"#), Ok(2.into()));
+    }
+
+    #[test]
+    fn test_stringify() {
+        assert_eq!(
+            run("stringify(value: { b: [1.50, 0.1], a: 'say \"hi\"', c: :draft })"),
+            string(r#"{"a":"say \"hi\"","b":[1.50,0.1],"c":{"$symbol":"draft"}}"#)
+        );
+        assert_eq!(
+            run("stringify(value: { a: [1, {}], b: [] }, pretty: true)"),
+            string("{\n  \"a\": [\n    1,\n    {}\n  ],\n  \"b\": []\n}")
+        );
+        assert_eq!(run("stringify(value: if false { 1 })"), string("null"));
+        assert_eq!(
+            run("stringify(value: [if false { 1 }, 'line\n'])"),
+            string(r#"[null,"line\n"]"#)
+        );
+        assert_eq!(
+            run("let value = { a: [1, 'é'], b: 0.30, c: :draft, d: 0.3333333333333333333333333333 }\nparse(text: stringify(value: value)) == value"),
+            Ok(Value::Boolean(true))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |code| run(code).map_err(|error| error.to_string());
+
+        assert_eq!(
+            parse(
+                "{\n  \"é\": ?\n}".to_string(),
+                &mut EvaluationContext::default()
+            )
+            .map_err(|error| error.to_string()),
+            Err("invalid JSON at line 2, column 9: expected value".to_string())
+        );
+        assert_eq!(
+            error("parse(text: '[1, 2')"),
+            Err("invalid JSON at line 1, column 5: EOF while parsing a list".to_string())
+        );
+        assert_eq!(
+            error(r#"parse(text: '{"a": 01}')"#),
+            Err("invalid JSON at line 1, column 8: invalid number".to_string())
+        );
+        assert_eq!(
+            error(r#"parse(text: '"\x"')"#),
+            Err("invalid JSON at line 1, column 3: invalid escape".to_string())
+        );
+        assert_eq!(
+            error("parse(text: '1e999')"),
+            Err("invalid JSON at line 1, column 5: 1e+999 is too large for a number".to_string())
+        );
+        assert_eq!(
+            error("parse(text: '[] []')"),
+            Err("invalid JSON at line 1, column 4: trailing characters".to_string())
+        );
+        assert_eq!(
+            parse("[".repeat(200), &mut EvaluationContext::default())
+                .map_err(|error| error.to_string()),
+            Err("invalid JSON at line 1, column 128: recursion limit exceeded".to_string())
+        );
+        assert_eq!(
+            stringify(
+                Value::from(vec![Value::Intrinsic(parse_intrinsic())]),
+                None,
+                &mut EvaluationContext::default()
+            )
+            .map_err(|error| error.to_string()),
+            Err("could not convert value: an intrinsic can't be serialized".to_string())
+        );
+
+        // doubling an array a few dozen times takes little memory, since the
+        // halves are shared, but its JSON would be enormous
+        assert!(matches!(
+            run("def double(value, times) {
+                if times == 0 { value } else { double(value: [value, value], times: times - 1) }
+            }
+            stringify(value: double(value: [1], times: 40))"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,501 @@
+//! `stdlib/list`: functions over arrays. Every function takes the array as
+//! `list`, so they can also be called as methods, like `names.join(with: ', ')`.
+//!
+//! Functions passed as `fn` are called with the member as `value`; `reduce`
+//! also passes the result so far as `accumulator`.
+
+use std::collections::BTreeSet;
+
+use im::{OrdMap, Vector};
+use rust_decimal::Decimal;
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, value::Callback, EvaluationContext, RuntimeError, Value};
+
+use super::call_predicate;
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/list")
+        .intrinsic(length_intrinsic())
+        .intrinsic(join_intrinsic())
+        .intrinsic(map_intrinsic())
+        .intrinsic(filter_intrinsic())
+        .intrinsic(reduce_intrinsic())
+        .intrinsic(find_intrinsic())
+        .intrinsic(any_intrinsic())
+        .intrinsic(all_intrinsic())
+        .intrinsic(sort_intrinsic())
+        .intrinsic(sort_by_intrinsic())
+        .intrinsic(reverse_intrinsic())
+        .intrinsic(uniq_intrinsic())
+        .intrinsic(zip_intrinsic())
+        .intrinsic(flat_map_intrinsic())
+        .intrinsic(group_by_intrinsic())
+        .intrinsic(chunk_intrinsic())
+        .intrinsic(take_intrinsic())
+        .intrinsic(drop_intrinsic())
+        .intrinsic(index_of_intrinsic())
+        .intrinsic(sum_intrinsic())
+        .intrinsic(min_intrinsic())
+        .intrinsic(max_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn length(list: Vector<Value>) -> Decimal {
+    list.len().into()
+}
+
+// strings are joined as they are, and other values as they're displayed
+#[blox_intrinsic]
+pub(crate) fn join(
+    list: Vector<Value>,
+    with: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let joined = list
+        .iter()
+        .map(Value::to_display_string)
+        .collect::<Vec<_>>()
+        .join(&with.unwrap_or_default());
+    context.budget.charge(joined)
+}
+
+#[blox_intrinsic]
+fn map(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mapped = list
+        .into_iter()
+        .map(|value| r#fn.call([("value", value)], context))
+        .collect::<Result<_, RuntimeError>>()?;
+    context.budget.charge(mapped)
+}
+
+#[blox_intrinsic]
+fn filter(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut members = Vector::new();
+    for value in list {
+        if call_predicate("filter", &r#fn, [("value", value.clone())], context)? {
+            members.push_back(value);
+        }
+    }
+    context.budget.charge(members)
+}
+
+#[blox_intrinsic]
+fn reduce(
+    list: Vector<Value>,
+    initial: Value,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Value, RuntimeError> {
+    list.into_iter().try_fold(initial, |accumulator, value| {
+        r#fn.call([("accumulator", accumulator), ("value", value)], context)
+    })
+}
+
+// the first member `fn` is true for, or void
+#[blox_intrinsic]
+fn find(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Option<Value>, RuntimeError> {
+    for value in list {
+        if call_predicate("find", &r#fn, [("value", value.clone())], context)? {
+            return Ok(Some(value));
+        }
+    }
+    Ok(None)
+}
+
+#[blox_intrinsic]
+fn any(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<bool, RuntimeError> {
+    for value in list {
+        if call_predicate("any", &r#fn, [("value", value.clone())], context)? {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[blox_intrinsic]
+fn all(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<bool, RuntimeError> {
+    for value in list {
+        if !call_predicate("all", &r#fn, [("value", value.clone())], context)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// sorts by the total order of values; see `Value::cmp`
+#[blox_intrinsic]
+pub(crate) fn sort(
+    list: Vector<Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut list = list;
+    list.sort();
+    context.budget.charge(list)
+}
+
+// sorts by the key `fn` returns for each member, keeping members with equal
+// keys in their original order
+#[blox_intrinsic]
+fn sort_by(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut keyed = list
+        .into_iter()
+        .map(|value| Ok((r#fn.call([("value", value.clone())], context)?, value)))
+        .collect::<Result<Vec<_>, RuntimeError>>()?;
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let sorted: Vector<Value> = keyed.into_iter().map(|(_, value)| value).collect();
+    context.budget.charge(sorted)
+}
+
+#[blox_intrinsic]
+pub(crate) fn reverse(
+    list: Vector<Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let reversed: Vector<Value> = list.into_iter().rev().collect();
+    context.budget.charge(reversed)
+}
+
+// drops repeated members, keeping the first of each
+#[blox_intrinsic]
+fn uniq(
+    list: Vector<Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut seen = BTreeSet::new();
+    let unique: Vector<Value> = list
+        .into_iter()
+        .filter(|value| seen.insert(value.clone()))
+        .collect();
+    context.budget.charge(unique)
+}
+
+// pairs up the members of two arrays, as long as the shorter one
+#[blox_intrinsic]
+fn zip(
+    list: Vector<Value>,
+    other: Vector<Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut pairs = Vector::new();
+    for (a, b) in list.into_iter().zip(other) {
+        let pair = Value::Array(Vector::from(vec![a, b]));
+        pairs.push_back(context.budget.charge(pair)?);
+    }
+    context.budget.charge(pairs)
+}
+
+// maps each member to an array and joins them; results that aren't arrays are
+// kept as they are
+#[blox_intrinsic]
+fn flat_map(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    let mut members = Vector::new();
+    for value in list {
+        match r#fn.call([("value", value)], context)? {
+            Value::Array(values) => members.append(values),
+            value => members.push_back(value),
+        }
+    }
+    context.budget.charge(members)
+}
+
+// an object of arrays, keyed by what `fn` returns for their members. keys that
+// aren't strings are used as they're displayed.
+#[blox_intrinsic]
+fn group_by(
+    list: Vector<Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let mut groups: OrdMap<String, Vector<Value>> = OrdMap::new();
+    for value in list {
+        let key = r#fn.call([("value", value.clone())], context)?;
+        groups
+            .entry(key.to_display_string())
+            .or_default()
+            .push_back(value);
+    }
+
+    let mut grouped = OrdMap::new();
+    for (key, members) in groups {
+        grouped.insert(key, context.budget.charge(Value::Array(members))?);
+    }
+    context.budget.charge(grouped)
+}
+
+// splits the array into arrays of `size` members; the last may be shorter
+#[blox_intrinsic]
+fn chunk(
+    list: Vector<Value>,
+    size: usize,
+    context: &mut EvaluationContext,
+) -> Result<Vector<Value>, RuntimeError> {
+    if size == 0 {
+        return Err(RuntimeError::InvalidArgument {
+            function: "chunk".to_string(),
+            argument: "size".to_string(),
+            expected: "a positive whole number".to_string(),
+            value: Value::Number(size.into()),
+        });
+    }
+
+    let members: Vec<Value> = list.into_iter().collect();
+    let mut chunks = Vector::new();
+    for chunk in members.chunks(size) {
+        let chunk = Value::Array(chunk.iter().cloned().collect());
+        chunks.push_back(context.budget.charge(chunk)?);
+    }
+    context.budget.charge(chunks)
+}
+
+#[blox_intrinsic]
+fn take(list: Vector<Value>, count: usize) -> Vector<Value> {
+    list.take(count.min(list.len()))
+}
+
+#[blox_intrinsic]
+fn drop(list: Vector<Value>, count: usize) -> Vector<Value> {
+    list.skip(count.min(list.len()))
+}
+
+// the position of the first member equal to `value`, or void
+#[blox_intrinsic]
+fn index_of(list: Vector<Value>, value: Value) -> Option<Decimal> {
+    list.index_of(&value).map(Decimal::from)
+}
+
+#[blox_intrinsic]
+fn sum(list: Vector<Value>) -> Result<Decimal, RuntimeError> {
+    let mut total = Decimal::ZERO;
+    for value in &list {
+        let Value::Number(number) = value else {
+            return Err(RuntimeError::InvalidArgument {
+                function: "sum".to_string(),
+                argument: "list".to_string(),
+                expected: "an array of numbers".to_string(),
+                value: Value::Array(list.clone()),
+            });
+        };
+
+        total = total
+            .checked_add(*number)
+            .ok_or_else(|| RuntimeError::NumberOverflow {
+                function: "sum".to_string(),
+            })?;
+    }
+    Ok(total)
+}
+
+// the smallest member by the total order of values, or void
+#[blox_intrinsic]
+fn min(list: Vector<Value>) -> Option<Value> {
+    list.into_iter().min()
+}
+
+#[blox_intrinsic]
+fn max(list: Vector<Value>) -> Option<Value> {
+    list.into_iter().max()
+}
+
+#[cfg(test)]
+mod tests {
+    use blox_language::parser::Parser;
+
+    use super::*;
+    use crate::{execute_program, load_stdlib, Backend};
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        let program = Parser::new("<test>", code)
+            .and_then(|parser| parser.parse())
+            .expect("parse error");
+
+        let mut results = [Backend::TreeWalker, Backend::Bytecode].map(|backend| {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            load_stdlib(&mut context).expect("the stdlib loads");
+            execute_program(&program, &mut context)
+        });
+
+        assert_eq!(results[0], results[1], "{code}");
+        std::mem::replace(&mut results[0], Ok(Value::Void))
+    }
+
+    fn numbers(numbers: &[i64]) -> Value {
+        Value::Array(numbers.iter().map(|&number| number.into()).collect())
+    }
+
+    #[test]
+    fn test_higher_order_functions() {
+        let prelude = "import { map, filter, reduce, find, any, all, sort_by, flat_map, group_by } from 'stdlib/list'\n";
+        let run = |code: &str| run(&format!("{prelude}{code}"));
+
+        assert_eq!(
+            run("map(list: [1, 2, 3], fn: |value| { value * 2 })"),
+            Ok(numbers(&[2, 4, 6]))
+        );
+        assert_eq!(
+            run("[1, 2, 3, 4].filter(fn: |value| { value > 2 })"),
+            Ok(numbers(&[3, 4]))
+        );
+        assert_eq!(
+            run("reduce(list: [1, 2, 3], initial: 10, fn: |accumulator, value| { accumulator + value })"),
+            Ok(Value::Number(16.into()))
+        );
+        assert_eq!(
+            run("find(list: [1, 2, 3], fn: |value| { value > 1 })"),
+            Ok(Value::Number(2.into()))
+        );
+        assert_eq!(
+            run("find(list: [1], fn: |value| { value > 1 })"),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            run("[any(list: [1, 2], fn: |value| { value > 1 }), all(list: [1, 2], fn: |value| { value > 1 })]"),
+            Ok(Value::Array(vec![true.into(), false.into()].into()))
+        );
+        assert_eq!(
+            run("sort_by(list: ['ccc', 'a', 'bb'], fn: |value| { value.length() })"),
+            Ok(Value::from(vec!["a", "bb", "ccc"]))
+        );
+        assert_eq!(
+            run("flat_map(list: [1, 2], fn: |value| { [value, value] })"),
+            Ok(numbers(&[1, 1, 2, 2]))
+        );
+        assert_eq!(
+            run("group_by(list: [1, 2, 3], fn: |value| { value > 1 })"),
+            Ok(Value::Object(OrdMap::from(vec![
+                ("false".to_string(), numbers(&[1])),
+                ("true".to_string(), numbers(&[2, 3])),
+            ])))
+        );
+
+        let error = run("filter(list: [1], fn: |value| { 'yes' })").expect_err("filter fails");
+        assert_eq!(
+            error.root_cause(),
+            &RuntimeError::InvalidCallbackResult {
+                function: "filter".to_string(),
+                argument: "fn".to_string(),
+                expected: "a boolean".to_string(),
+                value: Value::String("yes".to_string()),
+            }
+        );
+        assert!(matches!(
+            run("map(list: [1], fn: 2)").map_err(|error| error.root_cause().to_string()),
+            Err(message) if message == "argument fn of map must be a function, not 2 (a number)"
+        ));
+    }
+
+    #[test]
+    fn test_list_functions() {
+        let prelude = "import { join, length, sort, reverse, uniq, zip, chunk, take, drop, index_of, sum, min, max } from 'stdlib/list'\n";
+        let run = |code: &str| run(&format!("{prelude}{code}"));
+
+        assert_eq!(
+            run("['a', 'b'].join(with: ', ')"),
+            Ok(Value::String("a, b".to_string()))
+        );
+        assert_eq!(run("length(list: [1, 2])"), Ok(Value::Number(2.into())));
+        assert_eq!(run("sort(list: [3, 1, 2])"), Ok(numbers(&[1, 2, 3])));
+        assert_eq!(run("reverse(list: [1, 2])"), Ok(numbers(&[2, 1])));
+        assert_eq!(run("uniq(list: [1, 2, 1, 3, 2])"), Ok(numbers(&[1, 2, 3])));
+        assert_eq!(
+            run("zip(list: [1, 2, 3], other: ['a', 'b'])"),
+            Ok(Value::Array(
+                vec![
+                    Value::Array(vec![1.into(), "a".into()].into()),
+                    Value::Array(vec![2.into(), "b".into()].into()),
+                ]
+                .into()
+            ))
+        );
+        assert_eq!(
+            run("chunk(list: [1, 2, 3], size: 2)"),
+            Ok(Value::Array(vec![numbers(&[1, 2]), numbers(&[3])].into()))
+        );
+        assert!(run("chunk(list: [1], size: 0)").is_err());
+        assert_eq!(
+            run("take(list: [1, 2, 3], count: 5)"),
+            Ok(numbers(&[1, 2, 3]))
+        );
+        assert_eq!(run("drop(list: [1, 2, 3], count: 2)"), Ok(numbers(&[3])));
+        assert_eq!(
+            run("index_of(list: [1, 2], value: 2)"),
+            Ok(Value::Number(1.into()))
+        );
+        assert_eq!(run("index_of(list: [1, 2], value: 3)"), Ok(Value::Void));
+        assert_eq!(
+            run("sum(list: [1, 2.5])"),
+            Ok(Value::Number(Decimal::new(35, 1)))
+        );
+        assert_eq!(
+            run("sum(list: [79228162514264337593543950335, 1])")
+                .map_err(|error| error.root_cause().to_string()),
+            Err("the result of sum is too large for a number".to_string())
+        );
+        assert_eq!(
+            run("[min(list: [2, 1]), max(list: [])]"),
+            Ok(Value::Array(vec![1.into(), Value::Void].into()))
+        );
+    }
+
+    #[test]
+    fn test_long_lists() {
+        let program = Parser::new(
+            "<test>",
+            "import { map, sum } from 'stdlib/list'\nsum(list: map(list: list, fn: |value| { value * 2 }))",
+        )
+        .and_then(|parser| parser.parse())
+        .expect("parse error");
+
+        // the functions loop rather than recursing, so they handle lists
+        // that would exhaust the stack
+        for backend in [Backend::TreeWalker, Backend::Bytecode] {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            load_stdlib(&mut context).expect("the stdlib loads");
+            context
+                .scope
+                .insert_binding(
+                    &blox_language::ast::Identifier {
+                        name: "list".to_string(),
+                    },
+                    Value::Array((0..100_000).map(Value::from).collect()),
+                )
+                .expect("insert binding");
+
+            assert_eq!(
+                execute_program(&program, &mut context),
+                Ok(Value::Number(9_999_900_000i64.into())),
+                "{backend:?}"
+            );
+        }
+    }
+}
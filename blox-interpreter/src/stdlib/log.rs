@@ -0,0 +1,142 @@
+//! `stdlib/log`: structured logging, like
+//! `info(message: 'order paid', fields: { order: order.id, total: total })`.
+//! `debug`, `info`, `warn` and `error` emit `tracing` events with the `blox`
+//! target, so they end up wherever the host sends the rest of its logs.
+//!
+//! Each event records the module it was logged from and the line and column
+//! of the call, along with the message and any fields.
+
+use blox_language::location::Location;
+use im::OrdMap;
+use tracing::Level;
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, Value};
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/log")
+        .intrinsic(debug_intrinsic())
+        .intrinsic(info_intrinsic())
+        .intrinsic(warn_intrinsic())
+        .intrinsic(error_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn debug(message: Value, fields: Option<OrdMap<String, Value>>, call_site: &Location) {
+    log(Level::DEBUG, message, fields, call_site);
+}
+
+#[blox_intrinsic]
+pub(crate) fn info(message: Value, fields: Option<OrdMap<String, Value>>, call_site: &Location) {
+    log(Level::INFO, message, fields, call_site);
+}
+
+#[blox_intrinsic]
+pub(crate) fn warn(message: Value, fields: Option<OrdMap<String, Value>>, call_site: &Location) {
+    log(Level::WARN, message, fields, call_site);
+}
+
+#[blox_intrinsic]
+pub(crate) fn error(message: Value, fields: Option<OrdMap<String, Value>>, call_site: &Location) {
+    log(Level::ERROR, message, fields, call_site);
+}
+
+fn log(level: Level, message: Value, fields: Option<OrdMap<String, Value>>, call_site: &Location) {
+    let module = call_site.file.as_ref();
+    let line = call_site.range.start_point.row + 1;
+    let column = call_site.range.start_point.column + 1;
+    // tracing needs field names up front, so the fields are recorded as one
+    let fields = fields.map(|fields| Value::Object(fields).to_string());
+
+    // the level of an event has to be known where it's emitted
+    macro_rules! emit {
+        ($level:expr) => {
+            tracing::event!(
+                target: "blox",
+                $level,
+                module,
+                line,
+                column,
+                fields = fields.as_deref(),
+                "{message}"
+            )
+        };
+    }
+
+    match level {
+        Level::DEBUG => emit!(Level::DEBUG),
+        Level::INFO => emit!(Level::INFO),
+        Level::WARN => emit!(Level::WARN),
+        Level::ERROR => emit!(Level::ERROR),
+        _ => emit!(Level::TRACE),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+
+    use super::*;
+    use crate::RuntimeError;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/log", code)
+    }
+
+    // collects what a subscriber writes, so tests can read it back
+    #[derive(Clone, Default)]
+    struct Capture(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Capture {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().expect("capture lock").extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_events() {
+        let capture = Capture::default();
+        let writer = capture.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(Level::INFO)
+            .with_ansi(false)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+
+        let result = tracing::subscriber::with_default(subscriber, || {
+            run("debug(message: 'hidden')\ninfo(message: 'order paid', fields: { order: 42, status: :paid })\n  warn(message: 'slow')")
+        });
+        assert_eq!(result, Ok(Value::Void));
+
+        let output = String::from_utf8(capture.0.lock().expect("capture lock").clone())
+            .expect("utf-8 output");
+        // the level, and the event without the spans it happened in. once
+        // for each backend.
+        let events: Vec<_> = output
+            .lines()
+            .filter_map(|line| {
+                let (prefix, event) = line.split_once(" blox: ")?;
+                let level = prefix.split_whitespace().next()?;
+                Some(format!("{level} {event}"))
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                r#"INFO order paid module="<test>" line=3 column=5 fields="{order: 42, status: :paid}""#,
+                r#"WARN slow module="<test>" line=4 column=7"#,
+            ]
+            .repeat(2)
+        );
+    }
+}
@@ -0,0 +1,419 @@
+//! `stdlib/math`: arithmetic on exact decimal numbers. Every function that
+//! takes a number takes it as `number`, so they can also be called as
+//! methods, like `price.round(places: 2)`.
+//!
+//! Results that are too large for a number, and arguments a function isn't
+//! defined for, like the square root of a negative number, are errors rather
+//! than infinities or NaNs, which numbers don't have.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rust_decimal::{prelude::Signed, Decimal, MathematicalOps, RoundingStrategy};
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, EvaluationContext, RuntimeError, Value};
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/math")
+        .value("pi", Value::Number(Decimal::PI))
+        .intrinsic(circle_area_intrinsic())
+        .intrinsic(abs_intrinsic())
+        .intrinsic(floor_intrinsic())
+        .intrinsic(ceil_intrinsic())
+        .intrinsic(round_intrinsic())
+        .intrinsic(trunc_intrinsic())
+        .intrinsic(min_intrinsic())
+        .intrinsic(max_intrinsic())
+        .intrinsic(clamp_intrinsic())
+        .intrinsic(pow_intrinsic())
+        .intrinsic(sqrt_intrinsic())
+        .intrinsic(sign_intrinsic())
+        .intrinsic(div_intrinsic())
+        .intrinsic(rem_intrinsic())
+        .intrinsic(random_intrinsic())
+        .intrinsic(random_int_intrinsic())
+        .intrinsic(seed_intrinsic())
+}
+
+// A = 𝜋𝘳²
+#[blox_intrinsic]
+pub(crate) fn circle_area(r: Decimal) -> Result<Decimal, RuntimeError> {
+    Decimal::PI
+        .checked_mul(r)
+        .and_then(|area| area.checked_mul(r))
+        .ok_or_else(|| overflow("circle_area"))
+}
+
+#[blox_intrinsic]
+pub(crate) fn abs(number: Decimal) -> Decimal {
+    number.abs()
+}
+
+#[blox_intrinsic]
+pub(crate) fn floor(number: Decimal) -> Decimal {
+    number.floor()
+}
+
+#[blox_intrinsic]
+pub(crate) fn ceil(number: Decimal) -> Decimal {
+    number.ceil()
+}
+
+// rounds to `places` decimal places, none unless given. `mode` says which way
+// to round:
+//
+// - `:half_up`, the default, rounds halves away from zero, like they're
+//   taught at school
+// - `:half_even` rounds halves to the nearest even digit, as banks do
+// - `:half_down` rounds halves towards zero
+// - `:up` and `:down` round anything left over away from and towards zero
+// - `:ceiling` and `:floor` round it up and down
+#[blox_intrinsic]
+pub(crate) fn round(
+    number: Decimal,
+    places: Option<u32>,
+    mode: Option<Value>,
+) -> Result<Decimal, RuntimeError> {
+    let strategy = match mode {
+        None => RoundingStrategy::MidpointAwayFromZero,
+        Some(mode) => rounding_strategy(&mode).ok_or_else(|| invalid_rounding_mode(mode))?,
+    };
+
+    Ok(number.round_dp_with_strategy(places.unwrap_or(0), strategy))
+}
+
+// drops the fractional part, rounding towards zero
+#[blox_intrinsic]
+pub(crate) fn trunc(number: Decimal) -> Decimal {
+    number.trunc()
+}
+
+#[blox_intrinsic]
+pub(crate) fn min(number: Decimal, other: Decimal) -> Decimal {
+    number.min(other)
+}
+
+#[blox_intrinsic]
+pub(crate) fn max(number: Decimal, other: Decimal) -> Decimal {
+    number.max(other)
+}
+
+// the number if it's between `min` and `max`, or whichever it's beyond
+#[blox_intrinsic]
+pub(crate) fn clamp(number: Decimal, min: Decimal, max: Decimal) -> Result<Decimal, RuntimeError> {
+    if min > max {
+        return Err(invalid_argument(
+            "clamp",
+            "max",
+            "a number no less than min",
+            max,
+        ));
+    }
+
+    Ok(number.clamp(min, max))
+}
+
+// whole exponents give exact results. fractional ones are approximated, and
+// only defined for numbers that aren't negative.
+#[blox_intrinsic]
+pub(crate) fn pow(number: Decimal, exponent: Decimal) -> Result<Decimal, RuntimeError> {
+    if number.is_zero() && exponent.is_sign_negative() {
+        return Err(invalid_argument(
+            "pow",
+            "number",
+            "a number other than zero, for a negative exponent",
+            number,
+        ));
+    }
+    if number.is_sign_negative() && !exponent.fract().is_zero() {
+        return Err(invalid_argument(
+            "pow",
+            "number",
+            "a number that isn't negative, for a fractional exponent",
+            number,
+        ));
+    }
+
+    number.checked_powd(exponent).ok_or_else(|| overflow("pow"))
+}
+
+#[blox_intrinsic]
+pub(crate) fn sqrt(number: Decimal) -> Result<Decimal, RuntimeError> {
+    number
+        .sqrt()
+        .ok_or_else(|| invalid_argument("sqrt", "number", "a number that isn't negative", number))
+}
+
+// -1, 0 or 1, as the number is negative, zero or positive
+#[blox_intrinsic]
+pub(crate) fn sign(number: Decimal) -> Decimal {
+    number.signum()
+}
+
+// how many whole times `by` goes into the number, rounded down, so that
+// `number == div(number, by) * by + rem(number, by)`
+#[blox_intrinsic]
+pub(crate) fn div(number: Decimal, by: Decimal) -> Result<Decimal, RuntimeError> {
+    let remainder = remainder("div", number, by)?;
+
+    // the remainder makes the division exact
+    number
+        .checked_sub(remainder)
+        .and_then(|multiple| multiple.checked_div(by))
+        .ok_or_else(|| overflow("div"))
+}
+
+// what's left over from `div`, which has the same sign as `by`
+#[blox_intrinsic]
+pub(crate) fn rem(number: Decimal, by: Decimal) -> Result<Decimal, RuntimeError> {
+    remainder("rem", number, by)
+}
+
+// a number from 0 up to, but not including, 1
+#[blox_intrinsic]
+pub(crate) fn random(context: &mut EvaluationContext) -> Result<Decimal, RuntimeError> {
+    const SCALE: u32 = 28;
+
+    let mut random = context
+        .random
+        .lock()
+        .map_err(|_| RuntimeError::PoisonedLock("random number generator"))?;
+    let digits = random.gen_range(0..10i128.pow(SCALE));
+
+    Ok(Decimal::from_i128_with_scale(digits, SCALE))
+}
+
+// a whole number from `min` up to and including `max`
+#[blox_intrinsic]
+pub(crate) fn random_int(
+    min: i64,
+    max: i64,
+    context: &mut EvaluationContext,
+) -> Result<Decimal, RuntimeError> {
+    if min > max {
+        return Err(invalid_argument(
+            "random_int",
+            "max",
+            "a number no less than min",
+            max.into(),
+        ));
+    }
+
+    let mut random = context
+        .random
+        .lock()
+        .map_err(|_| RuntimeError::PoisonedLock("random number generator"))?;
+
+    Ok(random.gen_range(min..=max).into())
+}
+
+// makes the random numbers that follow repeatable; the same seed always
+// gives the same ones
+#[blox_intrinsic]
+pub(crate) fn seed(value: u64, context: &mut EvaluationContext) -> Result<(), RuntimeError> {
+    let mut random = context
+        .random
+        .lock()
+        .map_err(|_| RuntimeError::PoisonedLock("random number generator"))?;
+    *random = StdRng::seed_from_u64(value);
+
+    Ok(())
+}
+
+// the remainder of dividing `number` by `by`, with the same sign as `by`
+fn remainder(function: &str, number: Decimal, by: Decimal) -> Result<Decimal, RuntimeError> {
+    if by.is_zero() {
+        return Err(invalid_argument(
+            function,
+            "by",
+            "a number other than zero",
+            by,
+        ));
+    }
+
+    // rust's remainder has the sign of `number` instead
+    let remainder = number.checked_rem(by).ok_or_else(|| overflow(function))?;
+    if !remainder.is_zero() && remainder.is_sign_negative() != by.is_sign_negative() {
+        return Ok(remainder + by);
+    }
+
+    Ok(remainder)
+}
+
+fn rounding_strategy(mode: &Value) -> Option<RoundingStrategy> {
+    let Value::Symbol(mode) = mode else {
+        return None;
+    };
+
+    match mode.as_str() {
+        "half_up" => Some(RoundingStrategy::MidpointAwayFromZero),
+        "half_even" => Some(RoundingStrategy::MidpointNearestEven),
+        "half_down" => Some(RoundingStrategy::MidpointTowardZero),
+        "up" => Some(RoundingStrategy::AwayFromZero),
+        "down" => Some(RoundingStrategy::ToZero),
+        "ceiling" => Some(RoundingStrategy::ToPositiveInfinity),
+        "floor" => Some(RoundingStrategy::ToNegativeInfinity),
+        _ => None,
+    }
+}
+
+fn invalid_rounding_mode(mode: Value) -> RuntimeError {
+    RuntimeError::InvalidArgument {
+        function: "round".to_string(),
+        argument: "mode".to_string(),
+        expected: "one of :half_up, :half_even, :half_down, :up, :down, :ceiling or :floor"
+            .to_string(),
+        value: mode,
+    }
+}
+
+fn invalid_argument(
+    function: &str,
+    argument: &str,
+    expected: &str,
+    value: Decimal,
+) -> RuntimeError {
+    RuntimeError::InvalidArgument {
+        function: function.to_string(),
+        argument: argument.to_string(),
+        expected: expected.to_string(),
+        value: Value::Number(value),
+    }
+}
+
+fn overflow(function: &str) -> RuntimeError {
+    RuntimeError::NumberOverflow {
+        function: function.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/math", code)
+    }
+
+    fn number(number: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::Number(number.parse().unwrap()))
+    }
+
+    fn numbers(numbers: &[&str]) -> Result<Value, RuntimeError> {
+        Ok(Value::Array(
+            numbers
+                .iter()
+                .map(|number| Value::Number(number.parse().unwrap()))
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn test_rounding() {
+        assert_eq!(
+            run("[floor(number: 0 - 2.5), ceil(number: 0 - 2.5), trunc(number: 0 - 2.5), round(number: 0 - 2.5)]"),
+            numbers(&["-3", "-2", "-2", "-3"])
+        );
+        assert_eq!(
+            run("[round(number: 2.345, places: 2), round(number: 2.345, places: 2, mode: :half_even), round(number: 2.355, places: 2, mode: :half_even)]"),
+            numbers(&["2.35", "2.34", "2.36"])
+        );
+        assert_eq!(
+            run("[round(number: 2.1, mode: :up), round(number: 2.9, mode: :down), round(number: 0 - 2.1, mode: :ceiling), round(number: 0 - 2.1, mode: :floor), round(number: 2.5, mode: :half_down)]"),
+            numbers(&["3", "2", "-2", "-3", "2"])
+        );
+        assert_eq!(
+            run("let price = 19.999\nprice.round(places: 2)"),
+            number("20.00")
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            run("[abs(number: 0 - 3), sign(number: 0 - 3), sign(number: 0), min(number: 1, other: 2), max(number: 1, other: 2)]"),
+            numbers(&["3", "-1", "0", "1", "2"])
+        );
+        assert_eq!(
+            run("[clamp(number: 15, min: 0, max: 10), clamp(number: 0 - 5, min: 0, max: 10), clamp(number: 5, min: 0, max: 10)]"),
+            numbers(&["10", "0", "5"])
+        );
+        assert_eq!(
+            run("[pow(number: 2, exponent: 10), pow(number: 0.1, exponent: 3), pow(number: 2, exponent: 0 - 2), sqrt(number: 2.25)]"),
+            numbers(&["1024", "0.001", "0.25", "1.5"])
+        );
+        assert_eq!(
+            run("[div(number: 7, by: 2), rem(number: 7, by: 2), div(number: 0 - 7, by: 2), rem(number: 0 - 7, by: 2), rem(number: 7, by: 0 - 2), rem(number: 5.5, by: 2)]"),
+            numbers(&["3", "1", "-4", "1", "-1", "1.5"])
+        );
+        assert_eq!(run("circle_area(r: 2) > 12.566"), Ok(Value::Boolean(true)));
+        assert_eq!(run("pi"), Ok(Value::Number(Decimal::PI)));
+    }
+
+    #[test]
+    fn test_random() {
+        // the same seed gives the same numbers, on both backends
+        let rolls = run("seed(value: 42)\n[random_int(min: 1, max: 6), random_int(min: 1, max: 6), random_int(min: 1, max: 6), random()]");
+        assert_eq!(
+            rolls,
+            run("seed(value: 42)\n[random_int(min: 1, max: 6), random_int(min: 1, max: 6), random_int(min: 1, max: 6), random()]")
+        );
+
+        let Ok(Value::Array(rolls)) = rolls else {
+            panic!("expected an array, not {rolls:?}");
+        };
+        for roll in rolls.iter().take(3) {
+            assert!(*roll >= Value::Number(1.into()) && *roll <= Value::Number(6.into()));
+        }
+        assert!(rolls[3] >= Value::Number(0.into()) && rolls[3] < Value::Number(1.into()));
+
+        assert_eq!(run("random_int(min: 3, max: 3)"), number("3"));
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |code| run(code).map_err(|error| error.to_string());
+
+        assert_eq!(
+            error("sqrt(number: 0 - 4)"),
+            Err(
+                "argument number of sqrt must be a number that isn't negative, not -4 (a number)"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            error("div(number: 1, by: 0)"),
+            Err(
+                "argument by of div must be a number other than zero, not 0 (a number)".to_string()
+            )
+        );
+        assert_eq!(
+            error("pow(number: 10, exponent: 100)"),
+            Err("the result of pow is too large for a number".to_string())
+        );
+        assert!(matches!(
+            run("pow(number: 0, exponent: 0 - 1)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("pow(number: 0 - 8, exponent: 0.5)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("round(number: 1, mode: :sideways)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("clamp(number: 1, min: 2, max: 0)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("random_int(min: 2, max: 1)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("circle_area(r: 10000000000000000000)"),
+            Err(RuntimeError::NumberOverflow { .. })
+        ));
+    }
+}
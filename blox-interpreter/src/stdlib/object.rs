@@ -0,0 +1,310 @@
+//! `stdlib/object`: functions for reshaping objects, like database rows and
+//! form parameters. Every function takes the object as `object`, so they can
+//! also be called as methods, like `row.pick(keys: ['id', 'name'])`.
+//!
+//! Functions passed as `fn` are called with each field's `value` and `key`.
+
+use im::{OrdMap, Vector};
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, value::Callback, EvaluationContext, RuntimeError, Value};
+
+use super::call_predicate;
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/object")
+        .intrinsic(keys_intrinsic())
+        .intrinsic(values_intrinsic())
+        .intrinsic(entries_intrinsic())
+        .intrinsic(from_entries_intrinsic())
+        .intrinsic(has_intrinsic())
+        .intrinsic(get_intrinsic())
+        .intrinsic(merge_intrinsic())
+        .intrinsic(deep_merge_intrinsic())
+        .intrinsic(pick_intrinsic())
+        .intrinsic(omit_intrinsic())
+        .intrinsic(map_values_intrinsic())
+        .intrinsic(filter_intrinsic())
+}
+
+// in sorted order, like every object's keys
+#[blox_intrinsic]
+pub(crate) fn keys(
+    object: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    let mut keys = Vec::new();
+    for key in object.keys() {
+        keys.push(context.budget.charge(key.clone())?);
+    }
+    context.budget.charge(keys)
+}
+
+#[blox_intrinsic]
+pub(crate) fn values(
+    object: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vec<Value>, RuntimeError> {
+    context.budget.charge(object.values().cloned().collect())
+}
+
+// the fields as `[key, value]` pairs
+#[blox_intrinsic]
+pub(crate) fn entries(
+    object: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<Vec<Value>, RuntimeError> {
+    let mut entries = Vec::new();
+    for (key, value) in object {
+        let pair = Value::Array(Vector::from(vec![Value::String(key), value]));
+        entries.push(context.budget.charge(pair)?);
+    }
+    context.budget.charge(entries)
+}
+
+// the object with the fields of `[key, value]` pairs; later pairs replace
+// earlier ones with the same key
+#[blox_intrinsic]
+pub(crate) fn from_entries(
+    list: Vector<Value>,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let mut object = OrdMap::new();
+    for entry in &list {
+        match entry {
+            Value::Array(pair) if pair.len() == 2 => match (&pair[0], &pair[1]) {
+                (Value::String(key), value) => {
+                    object.insert(key.clone(), value.clone());
+                }
+                _ => return Err(invalid_entries(list)),
+            },
+            _ => return Err(invalid_entries(list)),
+        }
+    }
+    context.budget.charge(object)
+}
+
+#[blox_intrinsic]
+pub(crate) fn has(object: OrdMap<String, Value>, key: String) -> bool {
+    object.contains_key(&key)
+}
+
+// the field `key`, or `default` when there isn't one; void unless given
+#[blox_intrinsic]
+pub(crate) fn get(object: OrdMap<String, Value>, key: String, default: Option<Value>) -> Value {
+    object.get(&key).cloned().or(default).unwrap_or(Value::Void)
+}
+
+// the fields of both objects, with those of `other` replacing those of
+// `object` that have the same key
+#[blox_intrinsic]
+pub(crate) fn merge(
+    object: OrdMap<String, Value>,
+    other: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    // the fields of `object` are shared with the result, so only those of
+    // `other` are new
+    let other = context.budget.charge(other)?;
+    let mut merged = object;
+    merged.extend(other);
+    Ok(merged)
+}
+
+// like `merge`, but fields that are objects in both are merged themselves
+#[blox_intrinsic]
+pub(crate) fn deep_merge(
+    object: OrdMap<String, Value>,
+    other: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    merge_deeply(object, other, context)
+}
+
+// only the fields named in `keys`
+#[blox_intrinsic]
+pub(crate) fn pick(
+    object: OrdMap<String, Value>,
+    keys: Vec<String>,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let picked = keys
+        .into_iter()
+        .filter_map(|key| {
+            let value = object.get(&key)?.clone();
+            Some((key, value))
+        })
+        .collect();
+    context.budget.charge(picked)
+}
+
+// all but the fields named in `keys`
+#[blox_intrinsic]
+pub(crate) fn omit(object: OrdMap<String, Value>, keys: Vec<String>) -> OrdMap<String, Value> {
+    let mut object = object;
+    for key in keys {
+        object.remove(&key);
+    }
+    object
+}
+
+#[blox_intrinsic]
+pub(crate) fn map_values(
+    object: OrdMap<String, Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let mapped = object
+        .into_iter()
+        .map(|(key, value)| {
+            let value = r#fn.call(
+                [("value", value), ("key", Value::String(key.clone()))],
+                context,
+            )?;
+            Ok((key, value))
+        })
+        .collect::<Result<_, RuntimeError>>()?;
+    context.budget.charge(mapped)
+}
+
+// only the fields `fn` is true for
+#[blox_intrinsic]
+pub(crate) fn filter(
+    object: OrdMap<String, Value>,
+    r#fn: Callback,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let mut filtered = OrdMap::new();
+    for (key, value) in object {
+        let arguments = [
+            ("value", value.clone()),
+            ("key", Value::String(key.clone())),
+        ];
+        if call_predicate("filter", &r#fn, arguments, context)? {
+            filtered.insert(key, value);
+        }
+    }
+    context.budget.charge(filtered)
+}
+
+// charges for the fields of `other` at every level, like `merge`
+fn merge_deeply(
+    object: OrdMap<String, Value>,
+    other: OrdMap<String, Value>,
+    context: &mut EvaluationContext,
+) -> Result<OrdMap<String, Value>, RuntimeError> {
+    let other = context.budget.charge(other)?;
+    let mut merged = object;
+    for (key, value) in other {
+        let value = match (merged.remove(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(value)) => {
+                Value::Object(merge_deeply(existing, value, context)?)
+            }
+            (_, value) => value,
+        };
+        merged.insert(key, value);
+    }
+    Ok(merged)
+}
+
+fn invalid_entries(list: Vector<Value>) -> RuntimeError {
+    RuntimeError::InvalidArgument {
+        function: "from_entries".to_string(),
+        argument: "list".to_string(),
+        expected: "an array of [key, value] pairs with string keys".to_string(),
+        value: Value::Array(list),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/object", code)
+    }
+
+    fn object(fields: &[(&str, Value)]) -> Value {
+        Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_reading_objects() {
+        assert_eq!(
+            run("keys(object: { b: 1, a: 2 })"),
+            Ok(Value::from(vec!["a", "b"]))
+        );
+        assert_eq!(
+            run("values(object: { b: 1, a: 2 })"),
+            Ok(Value::from(vec![2, 1]))
+        );
+        assert_eq!(
+            run("entries(object: { a: 1 })"),
+            Ok(Value::Array(
+                vec![Value::Array(vec!["a".into(), 1.into()].into())].into()
+            ))
+        );
+        assert_eq!(
+            run("from_entries(list: [['a', 1], ['b', 2], ['a', 3]])"),
+            Ok(object(&[("a", 3.into()), ("b", 2.into())]))
+        );
+        assert!(matches!(
+            run("from_entries(list: [[1, 2]])"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert_eq!(run("{ a: 1 }.has(key: 'b')"), Ok(Value::Boolean(false)));
+        assert_eq!(
+            run("get(object: { a: 1 }, key: 'a', default: 0)"),
+            Ok(1.into())
+        );
+        assert_eq!(
+            run("get(object: { a: 1 }, key: 'b', default: 0)"),
+            Ok(0.into())
+        );
+        assert_eq!(run("get(object: { a: 1 }, key: 'b')"), Ok(Value::Void));
+    }
+
+    #[test]
+    fn test_reshaping_objects() {
+        assert_eq!(
+            run("merge(object: { a: 1, b: { c: 1 } }, other: { b: { d: 2 } })"),
+            Ok(object(&[
+                ("a", 1.into()),
+                ("b", object(&[("d", 2.into())]))
+            ]))
+        );
+        assert_eq!(
+            run("deep_merge(object: { a: 1, b: { c: 1 } }, other: { b: { d: 2 } })"),
+            Ok(object(&[
+                ("a", 1.into()),
+                ("b", object(&[("c", 1.into()), ("d", 2.into())]))
+            ]))
+        );
+        assert_eq!(
+            run("{ id: 1, name: 'Alice', password: 'x' }.pick(keys: ['id', 'name', 'email'])"),
+            Ok(object(&[("id", 1.into()), ("name", "Alice".into())]))
+        );
+        assert_eq!(
+            run("omit(object: { id: 1, password: 'x' }, keys: ['password'])"),
+            Ok(object(&[("id", 1.into())]))
+        );
+        assert_eq!(
+            run("map_values(object: { a: 1, b: 2 }, fn: |value, key| { key ++ ':' ++ value.to_string() })"),
+            Ok(object(&[("a", "a:1".into()), ("b", "b:2".into())]))
+        );
+        assert_eq!(
+            run("filter(object: { a: 1, b: 2 }, fn: |value| { value > 1 })"),
+            Ok(object(&[("b", 2.into())]))
+        );
+        assert!(matches!(
+            run("filter(object: { a: 'x' }, fn: |value| { value })"),
+            Err(RuntimeError::InvalidCallbackResult { .. })
+        ));
+    }
+}
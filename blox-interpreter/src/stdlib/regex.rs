@@ -0,0 +1,279 @@
+//! `stdlib/regex`: regular expressions. `compile` turns a pattern into a
+//! regex, and every other function takes one as `pattern`, so they can also
+//! be called as methods, like `compile(pattern: '^\d+$').is_match(text: id)`.
+//!
+//! Patterns use the syntax of the `regex` crate. Matching takes time linear in
+//! the length of the text, whatever the pattern, so patterns can safely come
+//! from users.
+
+use std::sync::Arc;
+
+use im::OrdMap;
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, value::Regex, EvaluationContext, RuntimeError, Value};
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/regex")
+        .intrinsic(compile_intrinsic())
+        .intrinsic(is_match_intrinsic())
+        .intrinsic(find_intrinsic())
+        .intrinsic(find_all_intrinsic())
+        .intrinsic(captures_intrinsic())
+        .intrinsic(replace_intrinsic())
+        .intrinsic(replace_all_intrinsic())
+        .intrinsic(split_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn compile(pattern: String) -> Result<Regex, RuntimeError> {
+    match regex::Regex::new(&pattern) {
+        Ok(regex) => Ok(Regex(Arc::new(regex))),
+        Err(error) => Err(RuntimeError::InvalidPattern {
+            message: pattern_error_message(&error),
+            pattern,
+        }),
+    }
+}
+
+#[blox_intrinsic]
+pub(crate) fn is_match(pattern: Regex, text: String) -> bool {
+    pattern.0.is_match(&text)
+}
+
+// the first text that matches, or void if none does
+#[blox_intrinsic]
+pub(crate) fn find(
+    pattern: Regex,
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<Option<String>, RuntimeError> {
+    match pattern.0.find(&text) {
+        Some(found) => Ok(Some(context.budget.charge(found.as_str().to_string())?)),
+        None => Ok(None),
+    }
+}
+
+#[blox_intrinsic]
+pub(crate) fn find_all(
+    pattern: Regex,
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    let mut found = Vec::new();
+    for matched in pattern.0.find_iter(&text) {
+        found.push(context.budget.charge(matched.as_str().to_string())?);
+    }
+    context.budget.charge(found)
+}
+
+// the named groups of the first match, like `{ year: '2024', month: '05' }`
+// for `(?<year>\d{4})-(?<month>\d{2})`. groups that took no part in the match
+// are void, and so is the result if nothing matched.
+#[blox_intrinsic]
+pub(crate) fn captures(
+    pattern: Regex,
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<Option<OrdMap<String, Value>>, RuntimeError> {
+    let Some(captures) = pattern.0.captures(&text) else {
+        return Ok(None);
+    };
+
+    let mut groups = OrdMap::new();
+    for name in pattern.0.capture_names().flatten() {
+        let value = match captures.name(name) {
+            Some(group) => Value::String(context.budget.charge(group.as_str().to_string())?),
+            None => Value::Void,
+        };
+        groups.insert(name.to_string(), value);
+    }
+    Ok(Some(context.budget.charge(groups)?))
+}
+
+// replaces the first match with `with`, where `$1` or `$name` stand for what
+// a group matched. `$$` is a dollar sign.
+#[blox_intrinsic]
+pub(crate) fn replace(
+    pattern: Regex,
+    text: String,
+    with: String,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    substitute(&pattern, &text, &with, 1, context)
+}
+
+#[blox_intrinsic]
+pub(crate) fn replace_all(
+    pattern: Regex,
+    text: String,
+    with: String,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    substitute(&pattern, &text, &with, usize::MAX, context)
+}
+
+// the text between matches
+#[blox_intrinsic]
+pub(crate) fn split(
+    pattern: Regex,
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    let mut parts = Vec::new();
+    for part in pattern.0.split(&text) {
+        parts.push(context.budget.charge(part.to_string())?);
+    }
+    context.budget.charge(parts)
+}
+
+// replaces up to `limit` matches. `with` can refer to groups any number of
+// times, so the replacements are charged to the budget as they're made.
+fn substitute(
+    pattern: &Regex,
+    text: &str,
+    with: &str,
+    limit: usize,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let mut replaced = String::with_capacity(text.len());
+    let mut end = 0;
+
+    for captures in pattern.0.captures_iter(text).take(limit) {
+        let Some(found) = captures.get(0) else {
+            continue;
+        };
+        replaced.push_str(&text[end..found.start()]);
+
+        let start = replaced.len();
+        captures.expand(with, &mut replaced);
+        context.budget.allocate(replaced.len() - start)?;
+
+        end = found.end();
+    }
+
+    replaced.push_str(&text[end..]);
+    Ok(replaced)
+}
+
+// syntax errors come with the pattern, marking where the problem is:
+//
+//     a(b
+//      ^
+//     error: unclosed group
+fn pattern_error_message(error: &regex::Error) -> String {
+    match error {
+        regex::Error::Syntax(message) => match message.split_once('\n') {
+            Some(("regex parse error:", diagram)) => diagram.to_string(),
+            _ => message.clone(),
+        },
+        regex::Error::CompiledTooBig(_) => "error: the pattern is too large".to_string(),
+        error => format!("error: {error}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/regex", code)
+    }
+
+    fn string(text: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::String(text.to_string()))
+    }
+
+    #[test]
+    fn test_matching() {
+        assert_eq!(
+            run("is_match(pattern: compile(pattern: '^[a-z]+$'), text: 'abc')"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            run("compile(pattern: '^[a-z]+$').is_match(text: 'ab1')"),
+            Ok(Value::Boolean(false))
+        );
+        assert_eq!(
+            run("compile(pattern: '[0-9]+').find(text: 'a12b345')"),
+            string("12")
+        );
+        assert_eq!(
+            run("compile(pattern: '[0-9]+').find(text: 'abc')"),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            run("find_all(pattern: compile(pattern: '[0-9]+'), text: 'a12b345')"),
+            Ok(Value::from(vec!["12", "345"]))
+        );
+        assert_eq!(
+            run("compile(pattern: '[0-9]') == compile(pattern: '[0-9]')"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            run("compile(pattern: '[0-9]')").map(|regex| regex.to_string()),
+            Ok("<regex: [0-9]>".to_string())
+        );
+    }
+
+    #[test]
+    fn test_captures() {
+        let date = "compile(pattern: '(?<year>[0-9]{4})-(?<month>[0-9]{2})(-(?<day>[0-9]{2}))?')";
+
+        assert_eq!(
+            run(&format!("{date}.captures(text: 'on 2024-05')")),
+            Ok(Value::Object(
+                [
+                    ("day".to_string(), Value::Void),
+                    ("month".to_string(), "05".into()),
+                    ("year".to_string(), "2024".into()),
+                ]
+                .into_iter()
+                .collect()
+            ))
+        );
+        assert_eq!(
+            run(&format!("{date}.captures(text: 'never')")),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            run(&format!(
+                "{date}.replace_all(text: '2024-05 and 2025-01', with: '$month/${{year}}')"
+            )),
+            string("05/2024 and 01/2025")
+        );
+        assert_eq!(
+            run("compile(pattern: '([a-z])([0-9])').replace(text: 'a1 b2', with: '$2$1$$')"),
+            string("1a$ b2")
+        );
+        assert_eq!(
+            run("split(pattern: compile(pattern: ' *, *'), text: 'a , b,c')"),
+            Ok(Value::from(vec!["a", "b", "c"]))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("compile(pattern: 'a(b')").map_err(|error| error.to_string()),
+            Err("invalid pattern:\n    a(b\n     ^\nerror: unclosed group".to_string())
+        );
+        assert_eq!(
+            run("is_match(pattern: 'a', text: 'a')").map_err(|error| error.to_string()),
+            Err("argument pattern of is_match must be a regex, not a (a string)".to_string())
+        );
+        assert!(matches!(
+            run("compile(pattern: '').replace_all(text: 'a'.repeat(count: 1000), with: 'b'.repeat(count: 1000))"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+        assert!(matches!(
+            run("compile(pattern: 'a').find_all(text: 'ab'.repeat(count: 100000))"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+        assert!(matches!(
+            run("compile(pattern: 'a').split(text: 'ab'.repeat(count: 100000))"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,359 @@
+//! `stdlib/string`: text processing. Every function takes the string as
+//! `text`, so they can also be called as methods, like `name.trim()`.
+//!
+//! Lengths and positions count graphemes, the characters a reader sees, so
+//! `'é'` and `'👍🏽'` are one character long however many code points they're
+//! made of.
+
+use rust_decimal::Decimal;
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use unicode_segmentation::UnicodeSegmentation;
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, EvaluationContext, RuntimeError, Value};
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/string")
+        .intrinsic(length_intrinsic())
+        .intrinsic(is_empty_intrinsic())
+        .intrinsic(split_intrinsic())
+        .intrinsic(super::list::join_intrinsic())
+        .intrinsic(trim_intrinsic())
+        .intrinsic(upper_intrinsic())
+        .intrinsic(lower_intrinsic())
+        .intrinsic(starts_with_intrinsic())
+        .intrinsic(ends_with_intrinsic())
+        .intrinsic(contains_intrinsic())
+        .intrinsic(replace_intrinsic())
+        .intrinsic(pad_start_intrinsic())
+        .intrinsic(pad_end_intrinsic())
+        .intrinsic(repeat_intrinsic())
+        .intrinsic(chars_intrinsic())
+        .intrinsic(slugify_intrinsic())
+        .intrinsic(truncate_intrinsic())
+        .intrinsic(to_number_intrinsic())
+        .intrinsic(to_string_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn length(text: String) -> Decimal {
+    text.graphemes(true).count().into()
+}
+
+#[blox_intrinsic]
+pub(crate) fn is_empty(text: String) -> bool {
+    text.is_empty()
+}
+
+// splits the text at every `with`, the separator `join` puts back. an empty
+// one splits the text into its characters.
+#[blox_intrinsic]
+pub(crate) fn split(
+    text: String,
+    with: String,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    if with.is_empty() {
+        return charge_pieces(graphemes(&text), context);
+    }
+
+    charge_pieces(text.split(&with).map(str::to_string).collect(), context)
+}
+
+#[blox_intrinsic]
+pub(crate) fn trim(text: String, context: &mut EvaluationContext) -> Result<String, RuntimeError> {
+    context.budget.charge(text.trim().to_string())
+}
+
+#[blox_intrinsic]
+pub(crate) fn upper(text: String, context: &mut EvaluationContext) -> Result<String, RuntimeError> {
+    context.budget.charge(text.to_uppercase())
+}
+
+#[blox_intrinsic]
+pub(crate) fn lower(text: String, context: &mut EvaluationContext) -> Result<String, RuntimeError> {
+    context.budget.charge(text.to_lowercase())
+}
+
+#[blox_intrinsic]
+pub(crate) fn starts_with(text: String, prefix: String) -> bool {
+    text.starts_with(&prefix)
+}
+
+#[blox_intrinsic]
+pub(crate) fn ends_with(text: String, suffix: String) -> bool {
+    text.ends_with(&suffix)
+}
+
+#[blox_intrinsic]
+pub(crate) fn contains(text: String, search: String) -> bool {
+    text.contains(&search)
+}
+
+// replaces every occurrence of `search`
+#[blox_intrinsic]
+pub(crate) fn replace(
+    text: String,
+    search: String,
+    with: String,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    if search.is_empty() {
+        return Ok(text);
+    }
+
+    context.budget.charge(text.replace(&search, &with))
+}
+
+// pads the text to `length` characters by adding `with`, a space unless
+// given, to its start
+#[blox_intrinsic]
+pub(crate) fn pad_start(
+    text: String,
+    length: usize,
+    with: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let padding = padding(&text, length, with, context)?;
+    Ok(padding + &text)
+}
+
+#[blox_intrinsic]
+pub(crate) fn pad_end(
+    text: String,
+    length: usize,
+    with: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let padding = padding(&text, length, with, context)?;
+    Ok(text + &padding)
+}
+
+#[blox_intrinsic]
+pub(crate) fn repeat(
+    text: String,
+    count: usize,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    // repeating would panic rather than fail if the result didn't fit
+    let Some(bytes) = text.len().checked_mul(count) else {
+        return Err(RuntimeError::InvalidArgument {
+            function: "repeat".to_string(),
+            argument: "count".to_string(),
+            expected: "a smaller number".to_string(),
+            value: Value::Number(count.into()),
+        });
+    };
+    context.budget.allocate(bytes)?;
+
+    Ok(text.repeat(count))
+}
+
+#[blox_intrinsic]
+pub(crate) fn chars(
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    charge_pieces(graphemes(&text), context)
+}
+
+// makes text fit for a URL: lowercase letters and digits, with accents
+// removed, separated by dashes. `'Crème Brûlée!'` becomes `'creme-brulee'`.
+#[blox_intrinsic]
+pub(crate) fn slugify(
+    text: String,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let mut slug = String::new();
+    let mut dash = false;
+
+    for c in text.nfkd().filter(|c| !is_combining_mark(*c)) {
+        if c.is_alphanumeric() {
+            if dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            dash = false;
+            slug.extend(c.to_lowercase());
+        } else {
+            dash = true;
+        }
+    }
+
+    context.budget.charge(slug)
+}
+
+// shortens text to at most `length` characters, ending it with `ellipsis`,
+// `…` unless given, when anything was cut off
+#[blox_intrinsic]
+pub(crate) fn truncate(
+    text: String,
+    length: usize,
+    ellipsis: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let ellipsis = ellipsis.unwrap_or_else(|| "…".to_string());
+    if text.graphemes(true).count() <= length {
+        return Ok(text);
+    }
+
+    let kept = length.saturating_sub(ellipsis.graphemes(true).count());
+    let mut truncated: String = text.graphemes(true).take(kept).collect();
+    truncated.extend(ellipsis.graphemes(true).take(length));
+    context.budget.charge(truncated)
+}
+
+// numbers are themselves, and strings are read as numbers. strings that
+// aren't numbers, like an empty form field, are void.
+#[blox_intrinsic]
+pub(crate) fn to_number(value: Value) -> Result<Option<Decimal>, RuntimeError> {
+    match &value {
+        Value::Number(number) => Ok(Some(*number)),
+        Value::String(text) => {
+            let text = text.trim();
+            Ok(Decimal::from_str_exact(text)
+                .or_else(|_| Decimal::from_scientific(text))
+                .ok())
+        }
+        _ => Err(RuntimeError::InvalidArgument {
+            function: "to_number".to_string(),
+            argument: "value".to_string(),
+            expected: "a number or a string".to_string(),
+            value,
+        }),
+    }
+}
+
+// strings are themselves, and anything else is written the way it's displayed
+#[blox_intrinsic]
+pub(crate) fn to_string(
+    value: Value,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    context.budget.charge(value.to_display_string())
+}
+
+// charges for the pieces text was split into, and the array they make up
+fn charge_pieces(
+    pieces: Vec<String>,
+    context: &mut EvaluationContext,
+) -> Result<Vec<String>, RuntimeError> {
+    for piece in &pieces {
+        context.budget.allocate(piece.len())?;
+    }
+    context.budget.charge(pieces)
+}
+
+fn graphemes(text: &str) -> Vec<String> {
+    text.graphemes(true).map(str::to_string).collect()
+}
+
+// `with` repeated, and cut short, to make up the characters `text` needs to
+// be `length` long
+fn padding(
+    text: &str,
+    length: usize,
+    with: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    let with = with.unwrap_or_else(|| " ".to_string());
+    let missing = length.saturating_sub(text.graphemes(true).count());
+    context.budget.allocate(missing)?;
+
+    Ok(with.graphemes(true).cycle().take(missing).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/string", code)
+    }
+
+    fn string(text: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::String(text.to_string()))
+    }
+
+    #[test]
+    fn test_unicode() {
+        // one grapheme each, of one, two and four code points
+        assert_eq!(run("length(text: 'é')"), Ok(Value::Number(1.into())));
+        assert_eq!(run("length(text: 'e\u{301}')"), Ok(Value::Number(1.into())));
+        assert_eq!(run("'👍🏽a'.length()"), Ok(Value::Number(2.into())));
+        assert_eq!(
+            run("chars(text: 'e\u{301}a')"),
+            Ok(Value::from(vec!["e\u{301}", "a"]))
+        );
+        assert_eq!(run("upper(text: 'straße')"), string("STRASSE"));
+        assert_eq!(run("truncate(text: '👍🏽👍🏽👍🏽', length: 2)"), string("👍🏽…"));
+        assert_eq!(
+            run("pad_start(text: 'é', length: 3, with: '·')"),
+            string("··é")
+        );
+        assert_eq!(
+            run("slugify(text: 'Crème Brûlée, 2 × Café!')"),
+            string("creme-brulee-2-cafe")
+        );
+    }
+
+    #[test]
+    fn test_string_functions() {
+        assert_eq!(
+            run("split(text: 'a,b,,c', with: ',')"),
+            Ok(Value::from(vec!["a", "b", "", "c"]))
+        );
+        assert_eq!(run("join(list: ['a', 'b'], with: '-')"), string("a-b"));
+        assert_eq!(run("'  hi\n'.trim().lower()"), string("hi"));
+        assert_eq!(
+            run("[starts_with(text: 'abc', prefix: 'ab'), ends_with(text: 'abc', suffix: 'ab'), 'abc'.contains(search: 'bc')]"),
+            Ok(Value::from(vec![true, false, true]))
+        );
+        assert_eq!(
+            run("replace(text: 'a-b-c', search: '-', with: '+')"),
+            string("a+b+c")
+        );
+        assert_eq!(
+            run("pad_end(text: '7', length: 3, with: 'ab')"),
+            string("7ab")
+        );
+        assert_eq!(run("'ab'.repeat(count: 3)"), string("ababab"));
+        assert_eq!(run("truncate(text: 'abc', length: 5)"), string("abc"));
+        assert_eq!(
+            run("truncate(text: 'abcdef', length: 5, ellipsis: '...')"),
+            string("ab...")
+        );
+
+        assert_eq!(
+            run("to_number(value: ' 2.50 ')"),
+            Ok(Value::Number(Decimal::new(250, 2)))
+        );
+        assert_eq!(
+            run("to_number(value: '1e3')"),
+            Ok(Value::Number(1000.into()))
+        );
+        assert_eq!(run("to_number(value: 'twelve')"), Ok(Value::Void));
+        assert_eq!(run("to_string(value: [1, 'a'])"), string("[1, a]"));
+        assert_eq!(run("to_string(value: 'a')"), string("a"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            run("length(text: 1)").map_err(|error| error.to_string()),
+            Err("argument text of length must be a string, not 1 (a number)".to_string())
+        );
+        assert!(matches!(
+            run("to_number(value: true)"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("repeat(text: 'abc', count: 1000000000)"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+        assert!(matches!(
+            run("pad_start(text: '', length: 1000000000)"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! `stdlib/test`: assertions for tests written in blox, which `blox test` runs.
+//! A failed assertion is an error, so it stops the test it's in:
+//!
+//! - `assert(condition: total > 0)` fails unless the condition is true
+//! - `assert_eq(actual: total, expected: 42)` fails unless the values are
+//!   equal, listing where arrays and objects differ
+//! - `assert_raises(fn: || { parse(text: 'oops') })` fails unless calling `fn`
+//!   raises an error, and returns its message otherwise
+//!
+//! Each takes an optional `message` that's shown when it fails.
+
+use blox_macros::blox_intrinsic;
+
+use crate::{
+    expression::truthiness, native::NativeModule, stack, value::Callback, EvaluationContext,
+    RuntimeError, Value,
+};
+
+// a diff of two large values stops after this many differences
+const SHOWN_DIFFERENCES: usize = 20;
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/test")
+        .intrinsic(assert_intrinsic())
+        .intrinsic(assert_eq_intrinsic())
+        .intrinsic(assert_raises_intrinsic())
+}
+
+#[blox_intrinsic]
+pub(crate) fn assert(condition: Value, message: Option<String>) -> Result<(), RuntimeError> {
+    match truthiness(&condition) {
+        Some(true) => Ok(()),
+        Some(false) => Err(failure(message, "the condition is false")),
+        None => Err(RuntimeError::InvalidArgument {
+            function: "assert".to_string(),
+            argument: "condition".to_string(),
+            expected: "a boolean".to_string(),
+            value: condition,
+        }),
+    }
+}
+
+#[blox_intrinsic]
+pub(crate) fn assert_eq(
+    actual: Value,
+    expected: Value,
+    message: Option<String>,
+) -> Result<(), RuntimeError> {
+    if actual == expected {
+        return Ok(());
+    }
+
+    let mut differences = Vec::new();
+    diff("", &actual, &expected, &mut differences);
+
+    let mut description = "the values differ".to_string();
+    for difference in differences.iter().take(SHOWN_DIFFERENCES) {
+        description.push_str("\n    ");
+        description.push_str(difference);
+    }
+    if differences.len() > SHOWN_DIFFERENCES {
+        let more = differences.len() - SHOWN_DIFFERENCES;
+        description.push_str(&format!("\n    ... {more} more"));
+    }
+
+    Err(failure(message, &description))
+}
+
+// the message of the error raised by calling `fn`, which mustn't succeed.
+// running out of a limit isn't something a test can expect, so those errors
+// aren't caught.
+#[blox_intrinsic]
+pub(crate) fn assert_raises(
+    r#fn: Callback,
+    message: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<String, RuntimeError> {
+    match r#fn.call(std::iter::empty(), context) {
+        Ok(value) => Err(failure(
+            message,
+            &format!("expected an error, but got {}", describe(&value)),
+        )),
+        Err(error) => match error.root_cause() {
+            RuntimeError::LimitExceeded(_) => Err(error),
+            cause => Ok(cause.to_string()),
+        },
+    }
+}
+
+// describes where `actual` differs from `expected`, one line for each value
+// that's different, like `.lines[1].sku: expected 'b', got 'c'`
+fn diff(path: &str, actual: &Value, expected: &Value, differences: &mut Vec<String>) {
+    if actual == expected {
+        return;
+    }
+
+    stack::maybe_grow(|| match (actual, expected) {
+        (Value::Array(actual), Value::Array(expected)) => {
+            for i in 0..actual.len().max(expected.len()) {
+                let path = format!("{path}[{i}]");
+                diff_member(&path, actual.get(i), expected.get(i), differences);
+            }
+        }
+        (Value::Object(actual), Value::Object(expected)) => {
+            let mut keys: Vec<_> = actual.keys().chain(expected.keys()).collect();
+            keys.sort();
+            keys.dedup();
+
+            for key in keys {
+                let path = format!("{path}.{key}");
+                diff_member(&path, actual.get(key), expected.get(key), differences);
+            }
+        }
+        (actual, expected) => {
+            let at = if path.is_empty() {
+                String::new()
+            } else {
+                format!("{path}: ")
+            };
+            differences.push(format!(
+                "{at}expected {}, got {}",
+                describe(expected),
+                describe(actual)
+            ));
+        }
+    })
+}
+
+// a member of an array or object that may be missing from either side
+fn diff_member(
+    path: &str,
+    actual: Option<&Value>,
+    expected: Option<&Value>,
+    differences: &mut Vec<String>,
+) {
+    match (actual, expected) {
+        (Some(actual), Some(expected)) => diff(path, actual, expected, differences),
+        (Some(actual), None) => {
+            differences.push(format!("{path}: unexpected {}", describe(actual)))
+        }
+        (None, Some(expected)) => {
+            differences.push(format!("{path}: missing {}", describe(expected)))
+        }
+        (None, None) => {}
+    }
+}
+
+// strings are quoted, so `'1'` and `1` can be told apart
+fn describe(value: &Value) -> String {
+    match value {
+        Value::String(string) => format!("'{string}'"),
+        Value::Void => "void".to_string(),
+        value => value.to_string(),
+    }
+}
+
+fn failure(message: Option<String>, description: &str) -> RuntimeError {
+    RuntimeError::AssertionFailed(match message {
+        Some(message) => format!("{message}: {description}"),
+        None => description.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/test", code)
+    }
+
+    fn failure_message(code: &str) -> String {
+        match run(code) {
+            Err(RuntimeError::AssertionFailed(message)) => message,
+            result => panic!("expected an assertion to fail, got {result:?}"),
+        }
+    }
+
+    #[test]
+    fn test_assertions() {
+        assert_eq!(
+            run("assert(condition: 1 < 2)\nassert_eq(actual: [1, { a: 'x' }], expected: [1, { a: 'x' }])"),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            failure_message("assert(condition: 2 < 1, message: 'ordering')"),
+            "ordering: the condition is false"
+        );
+        assert_eq!(
+            failure_message("assert_eq(actual: '1', expected: 1)"),
+            "the values differ\n    expected 1, got '1'"
+        );
+        assert_eq!(
+            run("let nothing = if false { 1 }\nassert_eq(actual: nothing, expected: nothing)"),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            failure_message("assert_eq(actual: if false { 1 }, expected: 1)"),
+            "the values differ\n    expected 1, got void"
+        );
+        assert_eq!(
+            failure_message(
+                "assert_eq(actual: { total: 41, lines: [{ sku: 'a' }, { sku: 'c' }], paid: true }, expected: { total: 42, lines: [{ sku: 'a' }, { sku: 'b' }, { sku: 'd' }] })"
+            ),
+            "the values differ\n    .lines[1].sku: expected 'b', got 'c'\n    .lines[2]: missing {sku: d}\n    .paid: unexpected true\n    .total: expected 42, got 41"
+        );
+        assert!(matches!(
+            run("assert(condition: 'yes')"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+    }
+
+    #[test]
+    fn test_assert_raises() {
+        assert_eq!(
+            run("assert_raises(fn: || { assert_eq(actual: 1, expected: 2) })"),
+            Ok(Value::String(
+                "assertion failed: the values differ\n    expected 2, got 1".to_string()
+            ))
+        );
+        assert_eq!(
+            failure_message("assert_raises(fn: || { 1 + 1 }, message: 'adding')"),
+            "adding: expected an error, but got 2"
+        );
+        assert!(matches!(
+            run("assert_raises(fn: || { 'ab'.repeat(count: 10000000) })"),
+            Err(RuntimeError::LimitExceeded(_))
+        ));
+    }
+}
@@ -0,0 +1,279 @@
+//! `stdlib/time`: instants and durations. Every function that takes a time
+//! takes it as `time`, so they can also be called as methods, like
+//! `now().in_zone(zone: 'Europe/Paris').format(pattern: '%H:%M')`.
+//!
+//! A time is an instant along with the time zone it's shown in. Times compare
+//! by instant, whatever their time zones, and move with durations:
+//! `deadline - now()` is a duration, and `now() + duration(days: 1)` is a
+//! time. Days are always 24 hours.
+//!
+//! `now` reads the evaluation context's clock, which tests can freeze; see
+//! `EvaluationContext::with_clock`.
+
+use jiff::{
+    civil::DateTime,
+    fmt::{
+        strtime,
+        temporal::{Pieces, PiecesOffset},
+    },
+    tz::TimeZone,
+    SignedDuration, Timestamp, Zoned,
+};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use blox_macros::blox_intrinsic;
+
+use crate::{native::NativeModule, EvaluationContext, RuntimeError, Value};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+pub(super) fn module() -> NativeModule {
+    NativeModule::new("stdlib/time")
+        .intrinsic(now_intrinsic())
+        .intrinsic(parse_intrinsic())
+        .intrinsic(format_intrinsic())
+        .intrinsic(in_zone_intrinsic())
+        .intrinsic(duration_intrinsic())
+        .intrinsic(seconds_intrinsic())
+}
+
+// the current time, in UTC unless `zone` is given
+#[blox_intrinsic]
+pub(crate) fn now(
+    zone: Option<String>,
+    context: &mut EvaluationContext,
+) -> Result<Zoned, RuntimeError> {
+    let time_zone = time_zone("now", zone)?;
+    Ok((context.clock)().to_zoned(time_zone))
+}
+
+// reads a time from `text`. without a pattern, that's RFC 3339, like
+// `2024-05-01T09:30:00+02:00`, optionally followed by a time zone like
+// `[Europe/Paris]`. with one, it's a strftime pattern like `%d/%m/%Y %H:%M`.
+// times that don't say where they are, like `2024-05-01 09:30`, are taken to
+// be in `zone`, or UTC.
+#[blox_intrinsic]
+pub(crate) fn parse(
+    text: String,
+    pattern: Option<String>,
+    zone: Option<String>,
+) -> Result<Zoned, RuntimeError> {
+    let time_zone = time_zone("parse", zone)?;
+    let parsed = match &pattern {
+        Some(pattern) => parse_with_pattern(&text, pattern, time_zone),
+        None => parse_rfc3339(&text, time_zone),
+    };
+
+    parsed.map_err(|error| RuntimeError::TimeError(format!("couldn't parse '{text}': {error}")))
+}
+
+// writes a time as RFC 3339, or with a strftime pattern like `%A %e %B`
+#[blox_intrinsic]
+pub(crate) fn format(time: Zoned, pattern: Option<String>) -> Result<String, RuntimeError> {
+    let Some(pattern) = pattern else {
+        return Ok(Value::Time(time).to_string());
+    };
+
+    strtime::format(pattern.as_str(), &time).map_err(|error| {
+        RuntimeError::TimeError(format!("couldn't format a time with '{pattern}': {error}"))
+    })
+}
+
+// the same instant in another time zone, like `America/New_York` or `UTC`
+#[blox_intrinsic]
+pub(crate) fn in_zone(time: Zoned, zone: String) -> Result<Zoned, RuntimeError> {
+    let time_zone = time_zone("in_zone", Some(zone))?;
+    Ok(time.with_time_zone(time_zone))
+}
+
+// a duration of the sum of its parts, which can be fractional or negative,
+// like `duration(hours: 1, minutes: 30)`
+#[blox_intrinsic]
+pub(crate) fn duration(
+    days: Option<Decimal>,
+    hours: Option<Decimal>,
+    minutes: Option<Decimal>,
+    seconds: Option<Decimal>,
+    milliseconds: Option<Decimal>,
+) -> Result<SignedDuration, RuntimeError> {
+    let parts = [
+        (days, 86_400 * NANOS_PER_SECOND),
+        (hours, 3_600 * NANOS_PER_SECOND),
+        (minutes, 60 * NANOS_PER_SECOND),
+        (seconds, NANOS_PER_SECOND),
+        (milliseconds, NANOS_PER_SECOND / 1_000),
+    ];
+
+    let mut nanos = Decimal::ZERO;
+    for (count, unit) in parts {
+        let part = count
+            .unwrap_or_default()
+            .checked_mul(unit.into())
+            .ok_or_else(too_long)?;
+        nanos = nanos.checked_add(part).ok_or_else(too_long)?;
+    }
+
+    // anything finer than a nanosecond is rounded away
+    let nanos = nanos.round().to_i128().ok_or_else(too_long)?;
+    let seconds = i64::try_from(nanos / i128::from(NANOS_PER_SECOND)).map_err(|_| too_long())?;
+    let subsec_nanos = (nanos % i128::from(NANOS_PER_SECOND)) as i32;
+    Ok(SignedDuration::new(seconds, subsec_nanos))
+}
+
+// the length of a duration in seconds, like `1.5` for 1500 milliseconds
+#[blox_intrinsic]
+pub(crate) fn seconds(duration: SignedDuration) -> Decimal {
+    Decimal::from_i128_with_scale(duration.as_nanos(), 9).normalize()
+}
+
+fn parse_rfc3339(text: &str, time_zone: TimeZone) -> Result<Zoned, jiff::Error> {
+    // with a time zone, like `2024-05-01T09:30:00+02:00[Europe/Paris]`
+    if let Ok(time) = text.parse::<Zoned>() {
+        return Ok(time);
+    }
+
+    // with an offset, which the time is then shown in
+    if let Ok(timestamp) = text.parse::<Timestamp>() {
+        let pieces = Pieces::parse(text)?;
+        let time_zone = match pieces.offset() {
+            Some(PiecesOffset::Numeric(offset)) => TimeZone::fixed(offset.offset()),
+            _ => TimeZone::UTC,
+        };
+        return Ok(timestamp.to_zoned(time_zone));
+    }
+
+    // with neither, like `2024-05-01T09:30` or `2024-05-01`
+    text.parse::<DateTime>()?.to_zoned(time_zone)
+}
+
+fn parse_with_pattern(
+    text: &str,
+    pattern: &str,
+    time_zone: TimeZone,
+) -> Result<Zoned, jiff::Error> {
+    let parsed = strtime::parse(pattern, text)?;
+    if parsed.offset().is_some() || parsed.iana_time_zone().is_some() {
+        return parsed.to_zoned();
+    }
+
+    // times of day that aren't given are midnight
+    parsed.to_datetime()?.to_zoned(time_zone)
+}
+
+fn time_zone(function: &str, zone: Option<String>) -> Result<TimeZone, RuntimeError> {
+    let Some(zone) = zone else {
+        return Ok(TimeZone::UTC);
+    };
+
+    TimeZone::get(&zone).map_err(|_| RuntimeError::InvalidArgument {
+        function: function.to_string(),
+        argument: "zone".to_string(),
+        expected: "a time zone name, like 'Europe/Paris'".to_string(),
+        value: Value::String(zone),
+    })
+}
+
+fn too_long() -> RuntimeError {
+    RuntimeError::TimeError("the duration is too long".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        super::super::run_with_module("stdlib/time", code)
+    }
+
+    fn string(text: &str) -> Result<Value, RuntimeError> {
+        Ok(Value::String(text.to_string()))
+    }
+
+    #[test]
+    fn test_parsing_and_formatting() {
+        assert_eq!(run("now().format()"), string("2024-05-01T12:00:00+00:00"));
+        assert_eq!(
+            run("now(zone: 'Europe/Paris').format(pattern: '%A %e %B, %H:%M %Z')"),
+            string("Wednesday  1 May, 14:00 CEST")
+        );
+        assert_eq!(
+            run("parse(text: '2024-05-01T09:30:00+02:00').format()"),
+            string("2024-05-01T09:30:00+02:00")
+        );
+        assert_eq!(
+            run("parse(text: '2024-12-01T09:30:00Z[Europe/Paris]').format(pattern: '%H:%M %Z')"),
+            string("10:30 CET")
+        );
+        assert_eq!(
+            run("parse(text: '2024-05-01', zone: 'America/New_York').format()"),
+            string("2024-05-01T00:00:00-04:00")
+        );
+        assert_eq!(
+            run("parse(text: '01/05/2024 09:30', pattern: '%d/%m/%Y %H:%M').format()"),
+            string("2024-05-01T09:30:00+00:00")
+        );
+        assert_eq!(
+            run("parse(text: '2024-05-01 09:30 +0530', pattern: '%F %H:%M %z').format()"),
+            string("2024-05-01T09:30:00+05:30")
+        );
+        assert_eq!(
+            run("now().in_zone(zone: 'Asia/Tokyo').format(pattern: '%F %T')"),
+            string("2024-05-01 21:00:00")
+        );
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(
+            run("(now() + duration(days: 1, hours: 1.5)).format()"),
+            string("2024-05-02T13:30:00+00:00")
+        );
+        assert_eq!(
+            run("(now() - parse(text: '2024-05-01T09:30:00+02:00')).seconds()"),
+            Ok(Value::Number(Decimal::from(16_200)))
+        );
+        assert_eq!(
+            run("(duration(minutes: 1) - duration(milliseconds: 1500)).to_string()"),
+            string("PT58.5S")
+        );
+        assert_eq!(
+            run("duration(hours: 0 - 2).seconds()"),
+            Ok(Value::Number(Decimal::from(-7_200)))
+        );
+        assert_eq!(
+            run("now() == parse(text: '2024-05-01T14:00:00+02:00[Europe/Paris]')"),
+            Ok(Value::Boolean(true))
+        );
+        assert_eq!(
+            run("[now() < now() + duration(seconds: 1), duration(days: 1) > duration(hours: 23)]"),
+            Ok(Value::from(vec![
+                Value::Boolean(true),
+                Value::Boolean(true)
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(
+            run("parse(text: 'yesterday')"),
+            Err(RuntimeError::TimeError(_))
+        ));
+        assert!(matches!(
+            run("now(zone: 'Mars/Olympus_Mons')"),
+            Err(RuntimeError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            run("duration(days: 1000000000000000000)"),
+            Err(RuntimeError::TimeError(_))
+        ));
+        assert!(matches!(
+            run("now() + duration(days: 10000000)"),
+            Err(RuntimeError::ArithmeticOverflow { .. })
+        ));
+        assert!(matches!(
+            run("now() + 1"),
+            Err(RuntimeError::InvalidOperands { .. })
+        ));
+    }
+}
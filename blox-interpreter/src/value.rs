@@ -1,28 +1,68 @@
+use im::{OrdMap, Vector};
+use jiff::{SignedDuration, Zoned};
 use rust_decimal::Decimal;
 use std::{
-    collections::{BTreeMap, HashMap},
-    sync::{atomic::AtomicUsize, Arc},
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::{atomic::AtomicUsize, Arc, OnceLock},
 };
 
-use blox_language::ast::{self, Identifier};
+use blox_language::{
+    ast::{self, Identifier},
+    location::Location,
+};
+
+use crate::{
+    bytecode::Chunk, call_function_value, methods::RECEIVER, module::Module, EvaluationContext,
+    RuntimeError, Scope,
+};
 
-use crate::{module::Module, RuntimeError, Scope};
+pub(crate) mod conversion;
+mod ordering;
+pub(crate) mod serialization;
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub enum Value {
     Void,
     Boolean(bool),
     Number(Decimal),
     String(String),
     Symbol(String),
-    Array(Vec<Value>),
-    Object(BTreeMap<String, Value>),
+    // arrays and objects are persistent collections, so cloning, slicing,
+    // appending and updating share structure instead of copying every member.
+    // objects use an ordered map so their keys keep a stable, sorted order.
+    Array(Vector<Value>),
+    Object(OrdMap<String, Value>),
+    // an instant, along with the time zone it's shown in
+    Time(Zoned),
+    Duration(SignedDuration),
+    Regex(Regex),
     Function(Function),
     Module(Module),
     Intrinsic(Intrinsic),
 }
 
 impl Value {
+    // what kind of value this is, as blox programs know it
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Void => "void",
+            Value::Boolean(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Symbol(_) => "symbol",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+            Value::Time(_) => "time",
+            Value::Duration(_) => "duration",
+            Value::Regex(_) => "regex",
+            Value::Function(_) => "function",
+            Value::Module(_) => "module",
+            Value::Intrinsic(_) => "intrinsic",
+        }
+    }
+
     pub fn to_display_string(&self) -> String {
         match self {
             Value::String(v) => v.clone(),
@@ -60,16 +100,52 @@ impl std::fmt::Display for Value {
                 }
                 write!(f, "}}")
             }
+            // RFC 3339, like `2024-05-01T09:30:00+02:00`
+            Value::Time(time) => {
+                write!(f, "{}", time.timestamp().display_with_offset(time.offset()))
+            }
+            // ISO 8601, like `PT1H30M`
+            Value::Duration(duration) => write!(f, "{duration}"),
+            Value::Regex(regex) => write!(f, "<regex: {}>", regex.as_str()),
             Value::Module(module) => write!(f, "<module: {}>", module.path),
             Value::Intrinsic(intrinsic) => write!(f, "{intrinsic}"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone)]
 pub struct Function {
-    pub definition: ast::Definition,
+    // shared, so that looking a function up doesn't copy its body
+    pub definition: Arc<ast::Definition>,
     pub closure: Arc<Scope>,
+    // the function's body compiled for the bytecode machine, filled in the
+    // first time it's called there and shared by every closure over the
+    // same definition
+    pub bytecode: Arc<OnceLock<Arc<Chunk>>>,
+    // tells apart functions made from the same definition, like the lambdas
+    // made by calling a function twice. copies of a function value share it.
+    pub id: usize,
+}
+
+impl Function {
+    pub fn new(definition: Arc<ast::Definition>, closure: Arc<Scope>) -> Self {
+        Self::with_bytecode(definition, closure, Arc::new(OnceLock::new()))
+    }
+
+    pub fn with_bytecode(
+        definition: Arc<ast::Definition>,
+        closure: Arc<Scope>,
+        bytecode: Arc<OnceLock<Arc<Chunk>>>,
+    ) -> Self {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+        Self {
+            definition,
+            closure,
+            bytecode,
+            id: NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+        }
+    }
 }
 
 impl std::fmt::Display for Function {
@@ -87,14 +163,64 @@ impl std::fmt::Display for Function {
     }
 }
 
-pub type IntrinsicFn =
-    dyn Fn(HashMap<Identifier, Value>) -> Result<Value, RuntimeError> + Send + Sync;
+// a compiled regular expression, made by `compile` from `stdlib/regex`.
+// compiling is the slow part, so it's shared by every copy of the value.
+#[derive(Debug, Clone)]
+pub struct Regex(pub(crate) Arc<regex::Regex>);
+
+impl Regex {
+    // the pattern it was compiled from
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+}
+
+// a function or intrinsic an intrinsic was passed, like the `fn` of `map`
+#[derive(Debug, Clone)]
+pub struct Callback(pub(crate) Value);
+
+impl Callback {
+    pub fn call<'a>(
+        &self,
+        arguments: impl IntoIterator<Item = (&'a str, Value)>,
+        context: &mut EvaluationContext,
+    ) -> Result<Value, RuntimeError> {
+        call_function_value(&self.0, arguments, context)
+    }
+}
+
+// the arguments an intrinsic was called with, by name
+pub type IntrinsicArguments = HashMap<Identifier, Value>;
+
+pub type IntrinsicFn = dyn Fn(IntrinsicArguments) -> Result<Value, RuntimeError> + Send + Sync;
+
+pub type IntrinsicFuture = Pin<Box<dyn Future<Output = Result<Value, RuntimeError>> + Send>>;
+
+// an intrinsic that does I/O, such as querying a database, without blocking
+// the thread it's awaited on
+pub type AsyncIntrinsicFn = dyn Fn(IntrinsicArguments) -> IntrinsicFuture + Send + Sync;
+
+// an intrinsic that runs blox code itself, like calling a function it was
+// passed (see `call_function_value`), or that needs to know where it was
+// called from, which is the `Location` it's passed
+pub type ContextIntrinsicFn = dyn Fn(IntrinsicArguments, &Location, &mut EvaluationContext) -> Result<Value, RuntimeError>
+    + Send
+    + Sync;
+
+#[derive(Clone)]
+pub enum IntrinsicFunction {
+    Sync(Arc<IntrinsicFn>),
+    Async(Arc<AsyncIntrinsicFn>),
+    WithContext(Arc<ContextIntrinsicFn>),
+}
 
 #[derive(Clone)]
 pub struct Intrinsic {
     pub id: usize,
     pub name: String,
-    pub function: Arc<IntrinsicFn>,
+    pub function: IntrinsicFunction,
+    // the argument a method call passes its receiver as, if not `RECEIVER`
+    pub receiver: Option<String>,
 }
 
 impl Intrinsic {
@@ -102,8 +228,74 @@ impl Intrinsic {
         Self {
             id: Self::next_id(),
             name: name.to_string(),
-            function,
+            function: IntrinsicFunction::Sync(function),
+            receiver: None,
+        }
+    }
+
+    pub fn new_async(name: &str, function: Arc<AsyncIntrinsicFn>) -> Self {
+        Self {
+            id: Self::next_id(),
+            name: name.to_string(),
+            function: IntrinsicFunction::Async(function),
+            receiver: None,
+        }
+    }
+
+    pub fn new_with_context(name: &str, function: Arc<ContextIntrinsicFn>) -> Self {
+        Self {
+            id: Self::next_id(),
+            name: name.to_string(),
+            function: IntrinsicFunction::WithContext(function),
+            receiver: None,
+        }
+    }
+
+    // makes method calls pass the receiver as the argument `name`, e.g. so
+    // that `names.join(with: ', ')` calls `join(list: names, with: ', ')`
+    pub fn with_receiver(mut self, name: &str) -> Self {
+        self.receiver = Some(name.to_string());
+        self
+    }
+
+    pub fn receiver(&self) -> &str {
+        self.receiver.as_deref().unwrap_or(RECEIVER)
+    }
+
+    // the interpreter itself is synchronous, so an async intrinsic's future
+    // is run to completion on the thread that called it. under
+    // `execute_program_async` that's one of the runtime's blocking threads.
+    pub(crate) fn call(
+        &self,
+        arguments: HashMap<Identifier, Value>,
+        call_site: &Location,
+        context: &mut EvaluationContext,
+    ) -> Result<Value, RuntimeError> {
+        let function = match &self.function {
+            IntrinsicFunction::Sync(function) => return function(arguments),
+            IntrinsicFunction::WithContext(function) => {
+                return function(arguments, call_site, context)
+            }
+            IntrinsicFunction::Async(function) => function,
+        };
+
+        if let Some(runtime) = &context.runtime {
+            return runtime.block_on(function(arguments));
         }
+
+        // blocking one of a runtime's worker threads would stall every other
+        // task scheduled on it
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(RuntimeError::BlockingAsyncCall(self.name.clone()));
+        }
+
+        // outside of any runtime, like in the console, the future gets one
+        // of its own
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|error| RuntimeError::AsyncRuntime(error.to_string()))?;
+        runtime.block_on(function(arguments))
     }
 
     fn next_id() -> usize {
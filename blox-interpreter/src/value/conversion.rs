@@ -0,0 +1,517 @@
+//! Conversions between blox values and rust types, used by intrinsics to take
+//! their arguments and return their results.
+//!
+//! Converting a value to a rust type fails with the value itself when it's of
+//! the wrong type.
+
+use std::collections::{BTreeMap, HashMap};
+
+use im::{OrdMap, Vector};
+use jiff::{SignedDuration, Zoned};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+
+use blox_language::ast::Identifier;
+
+use super::{serialization::decimal_from_f64, Callback, IntrinsicArguments, Regex, Value};
+use crate::RuntimeError;
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(string.to_string())
+    }
+}
+
+impl From<Decimal> for Value {
+    fn from(number: Decimal) -> Self {
+        Value::Number(number)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value::Boolean(boolean)
+    }
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Void
+    }
+}
+
+impl From<Vector<Value>> for Value {
+    fn from(members: Vector<Value>) -> Self {
+        Value::Array(members)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(members: Vec<T>) -> Self {
+        Value::Array(members.into_iter().map(Into::into).collect())
+    }
+}
+
+impl From<OrdMap<String, Value>> for Value {
+    fn from(fields: OrdMap<String, Value>) -> Self {
+        Value::Object(fields)
+    }
+}
+
+impl From<Zoned> for Value {
+    fn from(time: Zoned) -> Self {
+        Value::Time(time)
+    }
+}
+
+impl From<SignedDuration> for Value {
+    fn from(duration: SignedDuration) -> Self {
+        Value::Duration(duration)
+    }
+}
+
+impl From<Regex> for Value {
+    fn from(regex: Regex) -> Self {
+        Value::Regex(regex)
+    }
+}
+
+impl<T: Into<Value>> From<BTreeMap<String, T>> for Value {
+    fn from(fields: BTreeMap<String, T>) -> Self {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl<T: Into<Value>> From<HashMap<String, T>> for Value {
+    fn from(fields: HashMap<String, T>) -> Self {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, value.into()))
+                .collect(),
+        )
+    }
+}
+
+// integers are numbers, and only whole numbers in range convert back to them
+macro_rules! integer_conversions {
+    ($($integer:ty => $to_integer:ident),* $(,)?) => {
+        $(
+            impl From<$integer> for Value {
+                fn from(integer: $integer) -> Self {
+                    Value::Number(integer.into())
+                }
+            }
+
+            impl TryFrom<Value> for $integer {
+                type Error = Value;
+
+                fn try_from(value: Value) -> Result<Self, Self::Error> {
+                    match &value {
+                        Value::Number(number) if number.fract().is_zero() => {
+                            number.$to_integer().ok_or(value)
+                        }
+                        _ => Err(value),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+integer_conversions!(
+    i32 => to_i32,
+    i64 => to_i64,
+    u32 => to_u32,
+    u64 => to_u64,
+    usize => to_usize,
+);
+
+// fails with the float itself when it's not finite or too large for a number
+impl TryFrom<f64> for Value {
+    type Error = f64;
+
+    fn try_from(float: f64) -> Result<Self, Self::Error> {
+        decimal_from_f64(float).map(Value::Number).ok_or(float)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Void, Into::into)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::String(string) => Ok(string),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Decimal {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Number(number) => Ok(number),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Number(number) => number.to_f64().ok_or(value),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Callback {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Function(_) | Value::Intrinsic(_) => Ok(Callback(value)),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Zoned {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Time(time) => Ok(time),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for SignedDuration {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Duration(duration) => Ok(duration),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Regex {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Regex(regex) => Ok(regex),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Boolean(boolean) => Ok(boolean),
+            value => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vector<Value> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(members) => Ok(members),
+            value => Err(value),
+        }
+    }
+}
+
+// fails with the whole array if any of its members doesn't convert
+impl<T: TryFrom<Value>> TryFrom<Value> for Vec<T> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Array(members) => members
+                .iter()
+                .map(|member| T::try_from(member.clone()))
+                .collect::<Result<_, _>>()
+                .map_err(|_| value),
+            _ => Err(value),
+        }
+    }
+}
+
+impl TryFrom<Value> for OrdMap<String, Value> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Object(fields) => Ok(fields),
+            value => Err(value),
+        }
+    }
+}
+
+impl<T: TryFrom<Value>> TryFrom<Value> for BTreeMap<String, T> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, field)| Ok((key.clone(), T::try_from(field.clone())?)))
+                .collect::<Result<_, T::Error>>()
+                .map_err(|_| value),
+            _ => Err(value),
+        }
+    }
+}
+
+impl<T: TryFrom<Value>> TryFrom<Value> for HashMap<String, T> {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match &value {
+            Value::Object(fields) => fields
+                .iter()
+                .map(|(key, field)| Ok((key.clone(), T::try_from(field.clone())?)))
+                .collect::<Result<_, T::Error>>()
+                .map_err(|_| value),
+            _ => Err(value),
+        }
+    }
+}
+
+// takes the argument called `name` out of the arguments an intrinsic was
+// called with, converted to the type its rust function expects. this is what
+// `#[blox_intrinsic]` uses, so every intrinsic reports bad arguments the
+// same way.
+#[doc(hidden)]
+pub fn intrinsic_argument<T: TryFrom<Value>>(
+    function: &str,
+    arguments: &mut IntrinsicArguments,
+    name: &str,
+    expected: &str,
+) -> Result<T, RuntimeError> {
+    let value = arguments.remove(&Identifier {
+        name: name.to_string(),
+    });
+
+    // void is an argument like any other: `Value` takes it, and the other
+    // types reject it
+    match value {
+        Some(value) => convert_argument(function, name, expected, value),
+        None => Err(RuntimeError::MissingArgument {
+            function: function.to_string(),
+            argument: name.to_string(),
+        }),
+    }
+}
+
+// like `intrinsic_argument`, for arguments that can be left out or void
+#[doc(hidden)]
+pub fn optional_intrinsic_argument<T: TryFrom<Value>>(
+    function: &str,
+    arguments: &mut IntrinsicArguments,
+    name: &str,
+    expected: &str,
+) -> Result<Option<T>, RuntimeError> {
+    let value = arguments.remove(&Identifier {
+        name: name.to_string(),
+    });
+
+    match value {
+        None | Some(Value::Void) => Ok(None),
+        Some(value) => convert_argument(function, name, expected, value).map(Some),
+    }
+}
+
+fn convert_argument<T: TryFrom<Value>>(
+    function: &str,
+    name: &str,
+    expected: &str,
+    value: Value,
+) -> Result<T, RuntimeError> {
+    T::try_from(value.clone()).map_err(|_| RuntimeError::InvalidArgument {
+        function: function.to_string(),
+        argument: name.to_string(),
+        expected: expected.to_string(),
+        value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use blox_language::parser::Parser;
+    use blox_macros::blox_intrinsic;
+
+    use super::*;
+    use crate::{execute_program, Backend, EvaluationContext};
+
+    #[blox_intrinsic]
+    fn pad(text: String, width: Decimal, fill: Option<String>) -> Result<String, RuntimeError> {
+        let fill = fill.unwrap_or_else(|| " ".to_string());
+        let width = usize::try_from(width).map_err(|_| RuntimeError::InvalidArgument {
+            function: "pad".to_string(),
+            argument: "width".to_string(),
+            expected: "a positive whole number".to_string(),
+            value: Value::Number(width),
+        })?;
+
+        let mut text = text;
+        while text.chars().count() < width {
+            text.insert_str(0, &fill);
+        }
+        Ok(text)
+    }
+
+    #[blox_intrinsic(name = "is_empty")]
+    fn array_is_empty(list: Vector<Value>) -> bool {
+        list.is_empty()
+    }
+
+    #[blox_intrinsic]
+    async fn later(value: Value) -> Value {
+        value
+    }
+
+    fn run(code: &str) -> Result<Value, RuntimeError> {
+        let program = Parser::new("<test>", code)
+            .and_then(|parser| parser.parse())
+            .expect("parse error");
+        let mut results = [Backend::TreeWalker, Backend::Bytecode].map(|backend| {
+            let mut context = EvaluationContext::default().with_backend(backend);
+            for intrinsic in [
+                pad_intrinsic(),
+                array_is_empty_intrinsic(),
+                later_intrinsic(),
+            ] {
+                context
+                    .scope
+                    .insert_binding(
+                        &Identifier {
+                            name: intrinsic.name.clone(),
+                        },
+                        Value::Intrinsic(intrinsic),
+                    )
+                    .expect("insert binding");
+            }
+
+            // errors from intrinsics are traced with the intrinsic's frame
+            execute_program(&program, &mut context).map_err(RuntimeError::into_root_cause)
+        });
+
+        assert_eq!(results[0], results[1], "{code}");
+        std::mem::replace(&mut results[0], Ok(Value::Void))
+    }
+
+    #[test]
+    fn test_rust_types() {
+        assert_eq!(Value::from(7u64), Value::Number(7.into()));
+        assert_eq!(u64::try_from(Value::Number(7.into())), Ok(7));
+        assert!(u64::try_from(Value::Number((-7).into())).is_err());
+        assert!(i32::try_from(Value::Number(Decimal::new(15, 1))).is_err());
+
+        assert_eq!(Value::try_from(0.1), Ok(Value::Number(Decimal::new(1, 1))));
+        assert!(Value::try_from(f64::NAN).is_err());
+        assert_eq!(f64::try_from(Value::Number(Decimal::new(25, 1))), Ok(2.5));
+
+        let value = Value::from(vec![1, 2, 3]);
+        assert_eq!(Vec::<i32>::try_from(value.clone()), Ok(vec![1, 2, 3]));
+        assert_eq!(Vec::<String>::try_from(value.clone()), Err(value));
+
+        let fields = BTreeMap::from([("a".to_string(), true)]);
+        let value = Value::from(fields.clone());
+        assert_eq!(
+            BTreeMap::<String, bool>::try_from(value.clone()),
+            Ok(fields)
+        );
+        assert_eq!(
+            HashMap::<String, bool>::try_from(value).map(|fields| fields.len()),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_intrinsic_arguments() {
+        assert_eq!(
+            run("pad(text: '7', width: 3, fill: '0')"),
+            Ok(Value::String("007".to_string()))
+        );
+        assert_eq!(
+            run("pad(text: '7', width: 3)"),
+            Ok(Value::String("  7".to_string()))
+        );
+        assert_eq!(run("is_empty(list: [])"), Ok(Value::Boolean(true)));
+        assert_eq!(
+            run("later(value: :done)"),
+            Ok(Value::Symbol("done".to_string()))
+        );
+        assert_eq!(
+            run("let nothing = if false { 1 }\nlater(value: nothing)"),
+            Ok(Value::Void)
+        );
+        assert_eq!(
+            run("let nothing = if false { 1 }\npad(text: '7', width: 3, fill: nothing)"),
+            Ok(Value::String("  7".to_string()))
+        );
+
+        assert_eq!(
+            run("pad(width: 3)"),
+            Err(RuntimeError::MissingArgument {
+                function: "pad".to_string(),
+                argument: "text".to_string(),
+            })
+        );
+        assert_eq!(
+            run("pad(text: 7, width: 3)"),
+            Err(RuntimeError::InvalidArgument {
+                function: "pad".to_string(),
+                argument: "text".to_string(),
+                expected: "a string".to_string(),
+                value: Value::Number(7.into()),
+            })
+        );
+        assert_eq!(
+            run("is_empty(list: 'abc')")
+                .expect_err("list must be an array")
+                .to_string(),
+            "argument list of is_empty must be an array, not abc (a string)"
+        );
+        assert_eq!(
+            run("let nothing = if false { 1 }\npad(text: nothing, width: 3)"),
+            Err(RuntimeError::InvalidArgument {
+                function: "pad".to_string(),
+                argument: "text".to_string(),
+                expected: "a string".to_string(),
+                value: Value::Void,
+            })
+        );
+    }
+}
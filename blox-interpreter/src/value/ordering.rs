@@ -0,0 +1,268 @@
+//! Equality and ordering for Blox values.
+//!
+//! Every value can be compared with every other value, which gives sorting and
+//! deduplication a single total order to build on:
+//!
+//! - void is equal to void
+//! - booleans order `false` before `true`
+//! - numbers order numerically, so `1` and `1.0` are equal
+//! - strings and symbols order lexicographically by Unicode code point
+//! - arrays order element by element, with a shorter prefix first
+//! - objects order by their sorted `(key, value)` entries, the same way as arrays
+//! - times order by the instants they stand for, whatever their time zones,
+//!   and durations by their lengths
+//! - regexes order by their patterns
+//! - functions and intrinsics compare by identity: a function is only equal to
+//!   the very same function value, or a copy of it
+//! - modules compare by their paths
+//!
+//! Values of different types are never equal. For the total order they are
+//! ranked by type (in the order listed above), but [`Value::compare`] reports
+//! them as incomparable so that operators like `<` can reject them. The same
+//! goes for two functions, intrinsics or modules: sorting them gives the same
+//! order on every run, but that order means nothing to compare them by.
+//! Arrays and objects are incomparable as soon as they hold such a pair of
+//! members where it matters, like `[1]` and `['a']`.
+
+use std::cmp::Ordering;
+
+use super::{Function, Value};
+use crate::stack;
+
+impl Value {
+    /// Compares two values of the same type, returning `None` if the types
+    /// differ or are functions, intrinsics or modules, where the comparison
+    /// has no meaning. Arrays and objects compare their members the same way.
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Function(_), _) | (Value::Intrinsic(_), _) | (Value::Module(_), _) => None,
+            (Value::Array(lhs), Value::Array(rhs)) => {
+                for (lhs, rhs) in lhs.iter().zip(rhs) {
+                    match stack::maybe_grow(|| lhs.compare(rhs))? {
+                        Ordering::Equal => {}
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(lhs.len().cmp(&rhs.len()))
+            }
+            (Value::Object(lhs), Value::Object(rhs)) => {
+                for ((lhs_key, lhs), (rhs_key, rhs)) in lhs.iter().zip(rhs) {
+                    match lhs_key.cmp(rhs_key) {
+                        Ordering::Equal => {}
+                        ordering => return Some(ordering),
+                    }
+                    match stack::maybe_grow(|| lhs.compare(rhs))? {
+                        Ordering::Equal => {}
+                        ordering => return Some(ordering),
+                    }
+                }
+                Some(lhs.len().cmp(&rhs.len()))
+            }
+            (lhs, rhs) if lhs.type_rank() == rhs.type_rank() => Some(lhs.cmp(rhs)),
+            _ => None,
+        }
+    }
+
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Void => 0,
+            Value::Boolean(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Symbol(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+            Value::Time(_) => 7,
+            Value::Duration(_) => 8,
+            Value::Regex(_) => 9,
+            Value::Function(_) => 10,
+            Value::Intrinsic(_) => 11,
+            Value::Module(_) => 12,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Void, Value::Void) => Ordering::Equal,
+            (Value::Boolean(lhs), Value::Boolean(rhs)) => lhs.cmp(rhs),
+            (Value::Number(lhs), Value::Number(rhs)) => lhs.cmp(rhs),
+            (Value::String(lhs), Value::String(rhs)) => lhs.cmp(rhs),
+            (Value::Symbol(lhs), Value::Symbol(rhs)) => lhs.cmp(rhs),
+            (Value::Array(lhs), Value::Array(rhs)) => lhs.cmp(rhs),
+            (Value::Object(lhs), Value::Object(rhs)) => lhs.cmp(rhs),
+            (Value::Time(lhs), Value::Time(rhs)) => lhs.timestamp().cmp(&rhs.timestamp()),
+            (Value::Duration(lhs), Value::Duration(rhs)) => lhs.cmp(rhs),
+            (Value::Regex(lhs), Value::Regex(rhs)) => lhs.as_str().cmp(rhs.as_str()),
+            (Value::Function(lhs), Value::Function(rhs)) => lhs.cmp(rhs),
+            (Value::Intrinsic(lhs), Value::Intrinsic(rhs)) => lhs.id.cmp(&rhs.id),
+            (Value::Module(lhs), Value::Module(rhs)) => lhs.path.cmp(&rhs.path),
+            (lhs, rhs) => lhs.type_rank().cmp(&rhs.type_rank()),
+        }
+    }
+}
+
+impl Function {
+    // functions order by where they were defined, then by when they were made
+    fn identity(&self) -> (&str, usize, usize) {
+        let location = &self.definition.body.location;
+
+        (&location.file, location.range.start_byte, self.id)
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Eq for Function {}
+
+impl PartialOrd for Function {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Function {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.identity().cmp(&other.identity())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+
+    use crate::{Intrinsic, Value};
+
+    fn object(fields: &[(&str, Value)]) -> Value {
+        Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_structural_equality() {
+        assert_eq!(
+            object(&[("a", Value::Number(1.into()))]),
+            object(&[("a", Value::Number(1.into()))])
+        );
+        assert_ne!(
+            object(&[("a", Value::Number(1.into()))]),
+            object(&[("a", Value::Number(2.into()))])
+        );
+        assert_eq!(
+            Value::Number("1.0".parse().unwrap()),
+            Value::Number(1.into())
+        );
+        assert_ne!(Value::Number(1.into()), Value::Boolean(true));
+        assert_ne!(Value::String("a".into()), Value::Symbol("a".into()));
+    }
+
+    #[test]
+    fn test_compare() {
+        assert_eq!(
+            Value::String("apple".into()).compare(&Value::String("banana".into())),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::Array(vec![Value::Number(1.into())].into()).compare(&Value::Array(
+                vec![Value::Number(1.into()), Value::Void].into()
+            )),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            Value::Number(1.into()).compare(&Value::String("1".into())),
+            None
+        );
+    }
+
+    #[test]
+    fn test_compare_nested() {
+        let array = |members: Vec<Value>| Value::Array(members.into());
+        let function = || {
+            Value::Intrinsic(Intrinsic::new(
+                "nothing",
+                std::sync::Arc::new(|_| Ok(Value::Void)),
+            ))
+        };
+
+        assert_eq!(
+            array(vec![Value::Number(1.into())]).compare(&array(vec![Value::String("a".into())])),
+            None
+        );
+        assert_eq!(
+            array(vec![function()]).compare(&array(vec![function()])),
+            None
+        );
+        assert_eq!(
+            object(&[("a", Value::Number(1.into()))])
+                .compare(&object(&[("a", Value::Boolean(true))])),
+            None
+        );
+        assert_eq!(
+            array(vec![object(&[("a", Value::Number(1.into()))])])
+                .compare(&array(vec![object(&[("a", Value::String("1".into()))])])),
+            None
+        );
+
+        // members after the first difference don't matter
+        assert_eq!(
+            array(vec![Value::Number(1.into()), Value::Void]).compare(&array(vec![
+                Value::Number(2.into()),
+                Value::String("a".into())
+            ])),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            object(&[("a", Value::Number(1.into()))])
+                .compare(&object(&[("b", Value::String("1".into()))])),
+            Some(Ordering::Less)
+        );
+    }
+
+    #[test]
+    fn test_total_order() {
+        let mut values = vec![
+            Value::String("b".into()),
+            Value::Number(2.into()),
+            Value::Void,
+            Value::String("a".into()),
+            Value::Boolean(true),
+            Value::Number(1.into()),
+        ];
+        values.sort();
+
+        assert_eq!(
+            values,
+            vec![
+                Value::Void,
+                Value::Boolean(true),
+                Value::Number(1.into()),
+                Value::Number(2.into()),
+                Value::String("a".into()),
+                Value::String("b".into()),
+            ]
+        );
+    }
+}
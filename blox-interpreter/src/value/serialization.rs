@@ -0,0 +1,405 @@
+//! Serde support for blox values, so they can be exchanged with JSON and
+//! anything else serde can read or write.
+//!
+//! - void is a unit (`null` in JSON), booleans, strings, arrays and objects
+//!   are themselves
+//! - whole numbers are integers; other numbers are floats when the float is
+//!   written with exactly the same digits, and serde_json's arbitrary
+//!   precision numbers when it isn't, so JSON gets every digit, like `1.50`
+//!   or `0.3333333333333333333333333333`, and reads them back as the same
+//!   number. floats are read through their shortest decimal form, so `0.1` is
+//!   read as exactly 0.1.
+//! - symbols are tagged as a single-field object, `{"$symbol": "name"}`, and
+//!   so are times, `{"$time": "2024-05-01T09:30:00+02:00[Europe/Paris]"}`,
+//!   and durations, `{"$duration": "PT1H30M"}`
+//! - regexes, functions, intrinsics and modules can't be serialized
+
+use std::str::FromStr;
+
+use im::OrdMap;
+use rust_decimal::{
+    prelude::{FromPrimitive, ToPrimitive},
+    Decimal,
+};
+use serde::{
+    de::{self, MapAccess, SeqAccess, Visitor},
+    ser::{self, SerializeMap, SerializeSeq},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::Value;
+use crate::{error::with_article, stack, RuntimeError};
+
+// the key of the object that stands for a symbol
+pub const SYMBOL_TAG: &str = "$symbol";
+// ...for a time, holding an RFC 9557 timestamp that keeps the time zone
+pub const TIME_TAG: &str = "$time";
+// ...for a duration, in ISO 8601
+pub const DURATION_TAG: &str = "$duration";
+// the key serde_json reads and writes an arbitrary precision number under,
+// with its digits as the value
+const JSON_NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // arrays and objects can be nested deeper than the stack would allow
+        stack::maybe_grow(|| match self {
+            Value::Void => serializer.serialize_unit(),
+            Value::Boolean(boolean) => serializer.serialize_bool(*boolean),
+            Value::Number(number) => serialize_number(number, serializer),
+            Value::String(string) => serializer.serialize_str(string),
+            Value::Symbol(symbol) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(SYMBOL_TAG, symbol)?;
+                map.end()
+            }
+            Value::Time(time) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(TIME_TAG, &time.to_string())?;
+                map.end()
+            }
+            Value::Duration(duration) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(DURATION_TAG, &duration.to_string())?;
+                map.end()
+            }
+            Value::Array(members) => {
+                let mut seq = serializer.serialize_seq(Some(members.len()))?;
+                for member in members {
+                    seq.serialize_element(member)?;
+                }
+                seq.end()
+            }
+            Value::Object(fields) => {
+                let mut map = serializer.serialize_map(Some(fields.len()))?;
+                for (key, value) in fields {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            value @ (Value::Regex(_)
+            | Value::Function(_)
+            | Value::Module(_)
+            | Value::Intrinsic(_)) => Err(ser::Error::custom(format!(
+                "{} can't be serialized",
+                with_article(value.type_name())
+            ))),
+        })
+    }
+}
+
+fn serialize_number<S: Serializer>(number: &Decimal, serializer: S) -> Result<S::Ok, S::Error> {
+    if number.fract().is_zero() {
+        if let Some(integer) = number.to_i64() {
+            return serializer.serialize_i64(integer);
+        }
+        if let Some(integer) = number.to_u64() {
+            return serializer.serialize_u64(integer);
+        }
+    }
+
+    match number.to_f64() {
+        Some(float) if float.to_string() == number.to_string() => serializer.serialize_f64(float),
+        _ => serde_json::Number::from_str(&number.to_string())
+            .map_err(ser::Error::custom)?
+            .serialize(serializer),
+    }
+}
+
+// the decimal a float is written as, rather than the binary fraction it
+// actually holds
+pub(crate) fn decimal_from_f64(float: f64) -> Option<Decimal> {
+    if !float.is_finite() {
+        return None;
+    }
+
+    Decimal::from_str(&float.to_string()).ok()
+}
+
+// the number a JSON number stands for, rounded to the digits a number can
+// hold. `Decimal::from_str` does the rounding but doesn't read exponents, so
+// they're applied by moving the decimal point.
+fn decimal_from_json(text: &str) -> Option<Decimal> {
+    let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => {
+            let exponent = exponent
+                .parse::<i64>()
+                .unwrap_or(match exponent.starts_with('-') {
+                    true => i64::MIN,
+                    false => i64::MAX,
+                });
+            (mantissa, exponent)
+        }
+        None => (text, 0),
+    };
+    let (sign, mantissa) = match mantissa.strip_prefix('-') {
+        Some(mantissa) => ("-", mantissa),
+        None => ("", mantissa),
+    };
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+    let digits = format!("{integer}{fraction}");
+    let Some(first) = digits.find(|digit| digit != '0') else {
+        return Some(Decimal::ZERO);
+    };
+
+    // where the decimal point goes among the digits, which is far past the
+    // first significant one when the number is too large, and far before it
+    // when it's too small to be anything but zero
+    let point = (integer.len() as i64).saturating_add(exponent);
+    let magnitude = point.saturating_sub(first as i64);
+    if magnitude > 29 {
+        return None;
+    }
+    if magnitude < -29 {
+        return Some(Decimal::ZERO);
+    }
+
+    let plain = match usize::try_from(point) {
+        Ok(point) if point >= digits.len() => {
+            format!("{digits}{}", "0".repeat(point - digits.len()))
+        }
+        Ok(point) => format!("{}.{}", &digits[..point], &digits[point..]),
+        Err(_) => format!("0.{}{digits}", "0".repeat(point.unsigned_abs() as usize)),
+    };
+
+    Decimal::from_str(&format!("{sign}{plain}")).ok()
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a blox value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Void)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Void)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_bool<E: de::Error>(self, boolean: bool) -> Result<Value, E> {
+        Ok(Value::Boolean(boolean))
+    }
+
+    fn visit_i64<E: de::Error>(self, integer: i64) -> Result<Value, E> {
+        Ok(Value::Number(integer.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, integer: u64) -> Result<Value, E> {
+        Ok(Value::Number(integer.into()))
+    }
+
+    fn visit_i128<E: de::Error>(self, integer: i128) -> Result<Value, E> {
+        Decimal::from_i128(integer)
+            .map(Value::Number)
+            .ok_or_else(|| E::custom(format!("{integer} is too large for a number")))
+    }
+
+    fn visit_u128<E: de::Error>(self, integer: u128) -> Result<Value, E> {
+        Decimal::from_u128(integer)
+            .map(Value::Number)
+            .ok_or_else(|| E::custom(format!("{integer} is too large for a number")))
+    }
+
+    fn visit_f64<E: de::Error>(self, float: f64) -> Result<Value, E> {
+        decimal_from_f64(float)
+            .map(Value::Number)
+            .ok_or_else(|| E::custom(format!("{float} can't be a number")))
+    }
+
+    fn visit_str<E: de::Error>(self, string: &str) -> Result<Value, E> {
+        Ok(Value::String(string.to_string()))
+    }
+
+    fn visit_string<E: de::Error>(self, string: String) -> Result<Value, E> {
+        Ok(Value::String(string))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut members = im::Vector::new();
+        while let Some(member) = seq.next_element()? {
+            members.push_back(member);
+        }
+        Ok(Value::Array(members))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut fields = OrdMap::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key == JSON_NUMBER_TOKEN {
+                let digits: String = map.next_value()?;
+                return decimal_from_json(&digits)
+                    .map(Value::Number)
+                    .ok_or_else(|| {
+                        de::Error::custom(format!("{digits} is too large for a number"))
+                    });
+            }
+            fields.insert(key, map.next_value()?);
+        }
+
+        if fields.len() == 1 {
+            if let Some(Value::String(symbol)) = fields.get(SYMBOL_TAG) {
+                return Ok(Value::Symbol(symbol.clone()));
+            }
+            if let Some(Value::String(time)) = fields.get(TIME_TAG) {
+                if let Ok(time) = time.parse() {
+                    return Ok(Value::Time(time));
+                }
+            }
+            if let Some(Value::String(duration)) = fields.get(DURATION_TAG) {
+                if let Ok(duration) = duration.parse() {
+                    return Ok(Value::Duration(duration));
+                }
+            }
+        }
+
+        Ok(Value::Object(fields))
+    }
+}
+
+// converts anything serde can serialize into a blox value, e.g. a struct into
+// an object
+pub fn to_value<T: Serialize>(value: &T) -> Result<Value, RuntimeError> {
+    let json = serde_json::to_value(value)
+        .map_err(|error| RuntimeError::SerializationError(error.to_string()))?;
+
+    Value::deserialize(json).map_err(|error| RuntimeError::SerializationError(error.to_string()))
+}
+
+// converts a blox value into anything serde can deserialize
+pub fn from_value<T: de::DeserializeOwned>(value: &Value) -> Result<T, RuntimeError> {
+    let json = serde_json::to_value(value)
+        .map_err(|error| RuntimeError::SerializationError(error.to_string()))?;
+
+    T::deserialize(json).map_err(|error| RuntimeError::SerializationError(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        customer: String,
+        total: f64,
+        paid: bool,
+        lines: Vec<String>,
+        notes: Option<String>,
+        tags: BTreeMap<String, i32>,
+    }
+
+    fn number(number: &str) -> Value {
+        Value::Number(Decimal::from_str(number).expect("valid decimal"))
+    }
+
+    #[test]
+    fn test_json() {
+        let value = Value::Object(OrdMap::from(vec![
+            ("count".to_string(), number("3")),
+            ("price".to_string(), number("0.1")),
+            (
+                "exact".to_string(),
+                number("1.2345678901234567890123456789"),
+            ),
+            ("status".to_string(), Value::Symbol("paid".to_string())),
+            (
+                "lines".to_string(),
+                Value::Array(vec![Value::Boolean(true), Value::Void].into()),
+            ),
+        ]));
+
+        let json = serde_json::to_string(&value).expect("serializes");
+        assert_eq!(
+            json,
+            r#"{"count":3,"exact":1.2345678901234567890123456789,"lines":[true,null],"price":0.1,"status":{"$symbol":"paid"}}"#
+        );
+
+        let parsed: Value = serde_json::from_str(&json).expect("deserializes");
+        assert_eq!(parsed, value);
+        assert_eq!(
+            to_value(&value).expect("converts to a value"),
+            value,
+            "decimals survive serde_json::Value"
+        );
+        assert_eq!(
+            serde_json::to_string(&number("1.50")).expect("serializes"),
+            "1.50"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>("12345678901234567890123.5e-3").expect("deserializes"),
+            number("12345678901234567890.1235")
+        );
+
+        let time = Value::Time(
+            "2024-05-01T09:30:00+02:00[Europe/Paris]"
+                .parse()
+                .expect("valid time"),
+        );
+        let duration = Value::Duration(jiff::SignedDuration::from_mins(90));
+        let json = serde_json::to_string(&[&time, &duration]).expect("serializes");
+        assert_eq!(
+            json,
+            r#"[{"$time":"2024-05-01T09:30:00+02:00[Europe/Paris]"},{"$duration":"PT1H30M"}]"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&json).expect("deserializes"),
+            Value::Array(vec![time, duration].into())
+        );
+
+        assert!(serde_json::from_str::<Value>("1e300").is_err());
+        assert!(
+            serde_json::to_string(&Value::Intrinsic(crate::Intrinsic::new(
+                "f",
+                std::sync::Arc::new(|_| Ok(Value::Void))
+            )))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_rust_types() {
+        let order = Order {
+            id: 7,
+            customer: "Alice".to_string(),
+            total: 12.5,
+            paid: false,
+            lines: vec!["tea".to_string()],
+            notes: None,
+            tags: BTreeMap::from([("priority".to_string(), 1)]),
+        };
+
+        let value = to_value(&order).expect("converts to a value");
+        let Value::Object(fields) = &value else {
+            panic!("{value} should be an object");
+        };
+        assert_eq!(fields.get("total"), Some(&number("12.5")));
+        assert_eq!(fields.get("notes"), Some(&Value::Void));
+
+        assert_eq!(from_value::<Order>(&value), Ok(order));
+        assert!(matches!(
+            from_value::<Order>(&Value::Number(1.into())),
+            Err(RuntimeError::SerializationError(_))
+        ));
+    }
+}
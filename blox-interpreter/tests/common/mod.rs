@@ -3,11 +3,11 @@ use std::{
     sync::{Arc, RwLock},
 };
 
-use blox_interpreter::{execute_program, load_stdlib, EvaluationContext, Scope, Value};
+use blox_interpreter::{execute_program, load_stdlib, Backend, EvaluationContext, Scope, Value};
 use blox_language::{ast, error::ParseError, parser::Parser};
 
 pub fn parse(code: &str) -> Result<ast::Program, ParseError> {
-    let parser = Parser::new("<test>", code);
+    let parser = Parser::new("<test>", code)?;
     parser.parse()
 }
 
@@ -17,21 +17,25 @@ pub fn assert_result(code: &str, expected: Value) {
         Err(e) => panic!("Parsing error: {}", e),
     };
 
-    let mut context = EvaluationContext::new(
-        "..",
-        Arc::new(Scope::default()),
-        Arc::new(RwLock::new(BTreeMap::new())),
-    );
-    load_stdlib(&mut context);
+    // the test suite runs against both engines, which must behave identically
+    for backend in [Backend::TreeWalker, Backend::Bytecode] {
+        let mut context = EvaluationContext::new(
+            "..",
+            Arc::new(Scope::default()),
+            Arc::new(RwLock::new(BTreeMap::new())),
+        )
+        .with_backend(backend);
+        load_stdlib(&mut context).expect("the stdlib loads");
 
-    let result = execute_program(&program, &mut context);
+        let result = execute_program(&program, &mut context);
 
-    match &result {
-        Ok(value) => assert_eq!(
-            value, &expected,
-            "Expected: {expected}, got: {value}\nInput: {code}\nAST: {program:?}"
-        ),
-        Err(e) => panic!("Execution error: {}\n{program}", e),
+        match &result {
+            Ok(value) => assert_eq!(
+                value, &expected,
+                "Expected: {expected}, got: {value} ({backend:?})\nInput: {code}\nAST: {program:?}"
+            ),
+            Err(e) => panic!("Execution error ({backend:?}): {}\n{program}", e),
+        }
+        assert_eq!(result, Ok(expected.clone()));
     }
-    assert_eq!(result, Ok(expected));
 }
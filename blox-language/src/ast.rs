@@ -1,3 +1,4 @@
+mod address;
 mod argument;
 mod block;
 mod definition;
@@ -11,6 +12,7 @@ mod parameter;
 mod program;
 mod statement;
 
+pub use address::Address;
 pub use argument::Argument;
 pub use block::Block;
 pub use definition::Definition;
@@ -0,0 +1,15 @@
+// where a variable lives at runtime, filled in by the resolver after parsing
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum Address {
+    // looked up by name through the chain of scopes. top-level bindings are
+    // always dynamic so they can be redefined (e.g. in the REPL) or provided
+    // by the host.
+    #[default]
+    Dynamic,
+
+    // a local of an enclosing function call, given as the (depth, slot) of
+    // each function that declares the name, innermost first. a declaration
+    // may not have run yet, or may be in a branch that wasn't taken, so the
+    // first initialized slot is used, falling back to a lookup by name.
+    Local(Vec<(usize, usize)>),
+}
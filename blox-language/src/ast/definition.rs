@@ -1,10 +1,14 @@
-use super::{Block, Identifier, Parameter};
+use super::{Address, Block, Identifier, Parameter};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Definition {
     pub name: Option<Identifier>,
+    // where the name is bound
+    pub address: Address,
     pub parameters: Vec<Parameter>,
     pub body: Block,
+    // the number of slots needed for the parameters and locals of a call
+    pub locals: usize,
 }
 
 impl std::fmt::Display for Definition {
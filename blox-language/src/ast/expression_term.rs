@@ -18,7 +18,7 @@ pub use method_call::MethodCall;
 pub use object::Object;
 pub use object_index::ObjectIndex;
 
-use super::{Definition, Expression, Identifier};
+use super::{Address, Definition, Expression, Identifier};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ExpressionTerm {
@@ -29,7 +29,7 @@ pub enum ExpressionTerm {
     ObjectIndex(ObjectIndex),
     MethodCall(MethodCall),
     FunctionCall(FunctionCall),
-    Identifier(Identifier),
+    Identifier(Identifier, Address),
     Literal(Literal),
     Array(Array),
     Object(Object),
@@ -42,7 +42,7 @@ impl std::fmt::Display for ExpressionTerm {
             ExpressionTerm::Expression(v) => write!(f, "{v}"),
             ExpressionTerm::MethodCall(v) => write!(f, "{v}"),
             ExpressionTerm::FunctionCall(v) => write!(f, "{v}"),
-            ExpressionTerm::Identifier(v) => write!(f, "{v}"),
+            ExpressionTerm::Identifier(v, _) => write!(f, "{v}"),
             ExpressionTerm::Literal(v) => write!(f, "{v}"),
             ExpressionTerm::Array(v) => write!(f, "{v}"),
             ExpressionTerm::ArraySlice(v) => write!(f, "{v}"),
@@ -1,9 +1,9 @@
-use crate::ast::Argument;
+use crate::{ast::Argument, location::Location};
 
 use super::Expression;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct FunctionCall(pub Box<Expression>, pub Vec<Argument>);
+pub struct FunctionCall(pub Box<Expression>, pub Vec<Argument>, pub Location);
 
 impl std::fmt::Display for FunctionCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
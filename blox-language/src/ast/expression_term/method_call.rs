@@ -1,17 +1,24 @@
-use crate::ast::Argument;
+use crate::{ast::Argument, location::Location};
 
-use super::{Expression, Identifier};
+use super::{Address, Expression, Identifier};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct MethodCall {
     pub base: Box<Expression>,
     pub function: Identifier,
+    pub address: Address,
     pub arguments: Vec<Argument>,
+    pub optional: bool,
+    pub location: Location,
 }
 
 impl std::fmt::Display for MethodCall {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}.{}(", self.base, self.function)?;
+        if self.optional {
+            write!(f, "{}?.{}(", self.base, self.function)?;
+        } else {
+            write!(f, "{}.{}(", self.base, self.function)?;
+        }
         let arg_count = self.arguments.len();
         for (index, argument) in self.arguments.iter().enumerate() {
             if index != arg_count - 1 {
@@ -4,10 +4,15 @@ use crate::ast::{Expression, Identifier};
 pub struct ObjectIndex {
     pub base: Box<Expression>,
     pub index: Identifier,
+    pub optional: bool,
 }
 
 impl std::fmt::Display for ObjectIndex {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}.{}", self.base, self.index)
+        if self.optional {
+            write!(f, "{}?.{}", self.base, self.index)
+        } else {
+            write!(f, "{}.{}", self.base, self.index)
+        }
     }
 }
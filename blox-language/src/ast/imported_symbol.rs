@@ -1,7 +1,8 @@
-use super::Identifier;
+use super::{Address, Identifier};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct ImportedSymbol(pub Identifier, pub Option<Identifier>);
+// the address is where the symbol is bound, under its alias if it has one
+pub struct ImportedSymbol(pub Identifier, pub Option<Identifier>, pub Address);
 
 impl std::fmt::Display for ImportedSymbol {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -20,6 +20,7 @@ pub enum Operator {
     Assignment,
     Append,
     Pipe,
+    Coalesce,
 }
 
 impl std::fmt::Display for Operator {
@@ -41,6 +42,7 @@ impl std::fmt::Display for Operator {
             Operator::Assignment => write!(f, "="),
             Operator::Append => write!(f, "<<"),
             Operator::Pipe => write!(f, "|>"),
+            Operator::Coalesce => write!(f, "??"),
         }
     }
 }
@@ -1,7 +1,7 @@
-use super::Identifier;
+use super::{Address, Identifier};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Parameter(pub Identifier);
+pub struct Parameter(pub Identifier, pub Address);
 
 impl std::fmt::Display for Parameter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
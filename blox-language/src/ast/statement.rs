@@ -1,9 +1,10 @@
-use super::{Definition, Expression, Identifier, Import};
+use super::{Address, Definition, Expression, Identifier, Import};
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Statement {
     Definition(Definition),
-    Binding(Identifier, Expression),
+    Binding(Identifier, Address, Expression),
+    MutableBinding(Identifier, Address, Expression),
     Import(Import),
     Expression(Expression),
 }
@@ -12,7 +13,8 @@ impl std::fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Statement::Definition(def) => write!(f, "{}", def),
-            Statement::Binding(lhs, rhs) => write!(f, "let {} = {}", lhs.name, rhs),
+            Statement::Binding(lhs, _, rhs) => write!(f, "let {} = {}", lhs.name, rhs),
+            Statement::MutableBinding(lhs, _, rhs) => write!(f, "var {} = {}", lhs.name, rhs),
             Statement::Import(import) => write!(f, "{}", import),
             Statement::Expression(expr) => write!(f, "{}", expr),
         }
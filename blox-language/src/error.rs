@@ -1,12 +1,42 @@
+use crate::location::Location;
+
 #[derive(Debug, PartialEq)]
 pub enum ParseError {
     DecimalError(rust_decimal::Error),
+    // the source doesn't match the grammar; this is the first place it went wrong
+    SyntaxError(Location),
+    // a node doesn't have a child the grammar says it always has
+    MissingField {
+        kind: &'static str,
+        field: &'static str,
+        location: Location,
+    },
+    // a node of a kind the parser doesn't know how to turn into syntax
+    UnexpectedNode {
+        kind: &'static str,
+        location: Location,
+    },
+    // the grammar couldn't be loaded, like when it was built for another
+    // version of tree-sitter, so nothing can be parsed
+    ParserUnavailable(String),
 }
 
 impl std::fmt::Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ParseError::DecimalError(err) => write!(f, "{}", err),
+            ParseError::SyntaxError(location) => write!(f, "syntax error at {location}"),
+            ParseError::MissingField {
+                kind,
+                field,
+                location,
+            } => write!(f, "{kind} at {location} has no {field}"),
+            ParseError::UnexpectedNode { kind, location } => {
+                write!(f, "unexpected {kind} at {location}")
+            }
+            ParseError::ParserUnavailable(message) => {
+                write!(f, "the blox parser couldn't be loaded: {message}")
+            }
         }
     }
 }
@@ -2,13 +2,14 @@ pub mod ast;
 pub mod error;
 pub mod location;
 pub mod parser;
+pub mod resolver;
 
 #[cfg(test)]
 mod tests {
     use crate::{ast, error::ParseError, location::Location, parser::Parser};
 
     fn parse(input: &str) -> Result<ast::Program, ParseError> {
-        let parser = Parser::new("<test>", input);
+        let parser = Parser::new("<test>", input)?;
         parser.parse()
     }
 
@@ -17,7 +18,7 @@ mod tests {
         let actual = parse(&"let test = 55".to_string()).expect("parse error");
         assert_eq!(
             Location {
-                file: "<test>".to_string(),
+                file: "<test>".into(),
                 range: tree_sitter::Range {
                     start_byte: 0,
                     end_byte: 13,
@@ -37,6 +38,7 @@ mod tests {
                 ast::Identifier {
                     name: "test".to_string()
                 },
+                ast::Address::Dynamic,
                 ast::Expression::Term(ast::ExpressionTerm::Literal(ast::Literal::Number(
                     55.into()
                 )))
@@ -53,6 +55,7 @@ mod tests {
                 ast::Identifier {
                     name: "test".to_string()
                 },
+                ast::Address::Dynamic,
                 ast::Expression::BinaryExpression(
                     Box::new(ast::Expression::Term(ast::ExpressionTerm::Literal(
                         ast::Literal::Number(55.into())
@@ -75,6 +78,7 @@ mod tests {
                 ast::Identifier {
                     name: "test".to_string()
                 },
+                ast::Address::Dynamic,
                 ast::Expression::BinaryExpression(
                     Box::new(ast::Expression::BinaryExpression(
                         Box::new(ast::Expression::Term(ast::ExpressionTerm::Literal(
@@ -103,6 +107,7 @@ mod tests {
                 ast::Identifier {
                     name: "test".to_string()
                 },
+                ast::Address::Dynamic,
                 ast::Expression::Term(ast::ExpressionTerm::Literal(ast::Literal::Symbol(
                     "symbol".to_string()
                 )))
@@ -110,4 +115,46 @@ mod tests {
             actual.block.statements
         );
     }
+
+    #[test]
+    fn test_resolves_local_addresses() {
+        let actual = parse("def f(a) {\n  let b = a\n  |c| { a + b + c }\n}").expect("parse error");
+
+        let [ast::Statement::Definition(f)] = &actual.block.statements[..] else {
+            panic!("expected a definition: {actual:?}");
+        };
+        assert_eq!(ast::Address::Dynamic, f.address);
+        assert_eq!(ast::Address::Local(vec![(0, 0)]), f.parameters[0].1);
+        assert_eq!(2, f.locals);
+
+        let [ast::Statement::Binding(_, b, _), ast::Statement::Expression(ast::Expression::Term(ast::ExpressionTerm::Lambda(lambda)))] =
+            &f.body.statements[..]
+        else {
+            panic!("unexpected body: {f:?}");
+        };
+        assert_eq!(&ast::Address::Local(vec![(0, 1)]), b);
+        assert_eq!(1, lambda.locals);
+
+        let [ast::Statement::Expression(sum)] = &lambda.body.statements[..] else {
+            panic!("unexpected lambda body: {lambda:?}");
+        };
+        let ast::Expression::BinaryExpression(a_plus_b, _, c) = sum else {
+            panic!("unexpected sum: {sum:?}");
+        };
+        let ast::Expression::BinaryExpression(a, _, b) = &**a_plus_b else {
+            panic!("unexpected sum: {a_plus_b:?}");
+        };
+
+        for (expression, expected) in [
+            (&**a, ast::Address::Local(vec![(1, 0)])),
+            (&**b, ast::Address::Local(vec![(1, 1)])),
+            (&**c, ast::Address::Local(vec![(0, 0)])),
+        ] {
+            let ast::Expression::Term(ast::ExpressionTerm::Identifier(_, address)) = expression
+            else {
+                panic!("expected an identifier: {expression:?}");
+            };
+            assert_eq!(&expected, address);
+        }
+    }
 }
@@ -1,15 +1,39 @@
+use std::sync::Arc;
+
+// the file is shared by every location parsed from it, so locations are cheap
+// to copy into call stacks
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Location {
-    pub file: String,
+    pub file: Arc<str>,
     pub range: tree_sitter::Range,
 }
 
+impl Location {
+    // a location for syntax that wasn't parsed from a file, like a call made
+    // by a host program; it points at the start of `file`
+    pub fn synthetic(file: impl Into<Arc<str>>) -> Self {
+        let start = tree_sitter::Point { row: 0, column: 0 };
+
+        Location {
+            file: file.into(),
+            range: tree_sitter::Range {
+                start_byte: 0,
+                end_byte: 0,
+                start_point: start,
+                end_point: start,
+            },
+        }
+    }
+}
+
 impl std::fmt::Display for Location {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
             "{}:{}:{}",
-            self.file, self.range.start_point.row, self.range.start_point.column
+            self.file,
+            self.range.start_point.row + 1,
+            self.range.start_point.column + 1
         )
     }
 }
@@ -1,29 +1,38 @@
+use std::sync::Arc;
+
 use rust_decimal::Decimal;
 use tracing::trace;
 use tree_sitter::Node;
 
-use crate::{ast, error::ParseError, location::Location};
+use crate::{
+    ast,
+    error::ParseError,
+    location::Location,
+    resolver::{resolve_expression, resolve_program},
+};
 
 pub struct Parser<'a> {
-    file: String,
+    file: Arc<str>,
     source: &'a str,
     tree: tree_sitter::Tree,
 }
 
 impl<'a> Parser<'a> {
-    pub fn new(file: impl ToString, source: &'a str) -> Self {
+    pub fn new(file: impl ToString, source: &'a str) -> Result<Self, ParseError> {
         let mut ts_parser = tree_sitter::Parser::new();
         let language = tree_sitter_blox::LANGUAGE;
         ts_parser
             .set_language(&language.into())
-            .expect("Error loading Blox parser");
-        let tree = ts_parser.parse(source, None).unwrap();
+            .map_err(|error| ParseError::ParserUnavailable(error.to_string()))?;
+        let tree = ts_parser.parse(source, None).ok_or_else(|| {
+            ParseError::ParserUnavailable("tree-sitter didn't produce a tree".to_string())
+        })?;
 
-        Parser {
-            file: file.to_string(),
+        Ok(Parser {
+            file: file.to_string().into(),
             source,
             tree,
-        }
+        })
     }
 
     fn location(&self, node: Node<'_>) -> Location {
@@ -39,29 +48,69 @@ impl<'a> Parser<'a> {
 
         let root = self.tree.root_node();
         trace!(tree = root.to_sexp());
+        self.check_syntax(root)?;
 
-        let ast = self.parse_program(root)?;
+        let mut ast = self.parse_program(root)?;
+        resolve_program(&mut ast);
         trace!(?ast);
 
         Ok(ast)
     }
 
     pub fn parse_as_expression(&self) -> Result<ast::Expression, ParseError> {
-        let node = self
-            .tree
-            .root_node() // source file
+        let root = self.tree.root_node(); // source file
+        self.check_syntax(root)?;
+
+        let statement = root
             .child(0)
-            .unwrap() // expression statement
+            .ok_or_else(|| self.missing(root, "statement"))?;
+        let node = statement
             .child(0)
-            .unwrap(); // expression
+            .ok_or_else(|| self.missing(statement, "expression"))?;
 
-        self.parse_expression(node)
+        let mut expression = self.parse_expression(node)?;
+        resolve_expression(&mut expression);
+        Ok(expression)
     }
 
     fn value(&self, range: tree_sitter::Range) -> &str {
         self.source
             .get(range.start_byte..range.end_byte)
-            .expect("invalid range")
+            .unwrap_or_default()
+    }
+
+    // tree-sitter recovers from syntax errors by inserting `ERROR` and
+    // `MISSING` nodes into the tree; report the first of them instead of
+    // parsing around it
+    fn check_syntax(&self, root: Node<'_>) -> Result<(), ParseError> {
+        match first_error(root) {
+            Some(node) => Err(ParseError::SyntaxError(self.location(node))),
+            None => Ok(()),
+        }
+    }
+
+    fn field<'tree>(
+        &self,
+        node: Node<'tree>,
+        field: &'static str,
+    ) -> Result<Node<'tree>, ParseError> {
+        node.child_by_field_name(field)
+            .ok_or_else(|| self.missing(node, field))
+    }
+
+    fn missing(&self, node: Node<'_>, field: &'static str) -> ParseError {
+        ParseError::MissingField {
+            kind: node.kind(),
+            field,
+            location: self.location(node),
+        }
+    }
+
+    fn unexpected(&self, node: Node<'_>) -> ParseError {
+        ParseError::UnexpectedNode {
+            kind: node.kind(),
+            location: self.location(node),
+        }
     }
 
     fn parse_program(&self, node: Node<'_>) -> Result<ast::Program, ParseError> {
@@ -94,7 +143,15 @@ impl<'a> Parser<'a> {
             }
             "binding" => {
                 let (lhs, rhs) = self.parse_binding(node)?;
-                Ok(ast::Statement::Binding(lhs, rhs))
+                Ok(ast::Statement::Binding(lhs, ast::Address::Dynamic, rhs))
+            }
+            "mutable_binding" => {
+                let (lhs, rhs) = self.parse_binding(node)?;
+                Ok(ast::Statement::MutableBinding(
+                    lhs,
+                    ast::Address::Dynamic,
+                    rhs,
+                ))
             }
             "import" => {
                 let import = self.parse_import(node)?;
@@ -104,30 +161,29 @@ impl<'a> Parser<'a> {
                 let expression = self.parse_expression_container(node)?;
                 Ok(ast::Statement::Expression(expression))
             }
-            kind => unimplemented!("statement kind: {kind}"),
+            _ => Err(self.unexpected(node)),
         }
     }
 
     fn parse_definition(&self, node: Node<'_>) -> Result<ast::Definition, ParseError> {
-        let name = self.parse_identifier(
-            node.child_by_field_name("name")
-                .expect("definition has no name"),
-        )?;
+        let name = self.parse_identifier(self.field(node, "name")?)?;
 
-        let body = self.parse_block(
-            node.child_by_field_name("body")
-                .expect("definition has no body"),
-        )?;
+        let body = self.parse_block(self.field(node, "body")?)?;
 
         let mut parameters = vec![];
         for child in node.children_by_field_name("parameter", &mut node.walk()) {
-            parameters.push(ast::Parameter(self.parse_identifier(child)?));
+            parameters.push(ast::Parameter(
+                self.parse_identifier(child)?,
+                ast::Address::Dynamic,
+            ));
         }
 
         Ok(ast::Definition {
             name: Some(name),
+            address: ast::Address::Dynamic,
             parameters,
             body,
+            locals: 0,
         })
     }
 
@@ -135,15 +191,9 @@ impl<'a> Parser<'a> {
         &self,
         node: Node<'_>,
     ) -> Result<(ast::Identifier, ast::Expression), ParseError> {
-        let name = self.parse_identifier(
-            node.child_by_field_name("name")
-                .expect("binding has no name"),
-        )?;
+        let name = self.parse_identifier(self.field(node, "name")?)?;
 
-        let value = self.parse_expression(
-            node.child_by_field_name("value")
-                .expect("binding has no value"),
-        )?;
+        let value = self.parse_expression(self.field(node, "value")?)?;
 
         Ok((name, value))
     }
@@ -161,16 +211,13 @@ impl<'a> Parser<'a> {
             }
         }
 
-        let path = path.expect("expected path");
+        let path = path.ok_or_else(|| self.missing(node, "path"))?;
 
         Ok(ast::Import(symbols, path))
     }
 
     fn parse_imported_symbol(&self, node: Node<'_>) -> Result<ast::ImportedSymbol, ParseError> {
-        let name = self.parse_identifier(
-            node.child_by_field_name("identifier")
-                .expect("imported symbol has no identifier"),
-        )?;
+        let name = self.parse_identifier(self.field(node, "identifier")?)?;
 
         let mut alias = None;
 
@@ -178,16 +225,14 @@ impl<'a> Parser<'a> {
             alias = Some(self.parse_identifier(node)?);
         }
 
-        Ok(ast::ImportedSymbol(name, alias))
+        Ok(ast::ImportedSymbol(name, alias, ast::Address::Dynamic))
     }
 
     pub fn parse_expression_container(
         &self,
         node: Node<'_>,
     ) -> Result<ast::Expression, ParseError> {
-        let expression = self.parse_expression(node.child_by_field_name("expression").expect(
-            &format!("expression statement has no expression: {}", node.to_sexp()),
-        ))?;
+        let expression = self.parse_expression(self.field(node, "expression")?)?;
 
         Ok(expression)
     }
@@ -195,18 +240,20 @@ impl<'a> Parser<'a> {
     pub fn parse_lambda(&self, node: Node<'_>) -> Result<ast::Definition, ParseError> {
         let mut parameters = vec![];
         for child in node.children_by_field_name("parameter", &mut node.walk()) {
-            parameters.push(ast::Parameter(self.parse_identifier(child)?));
+            parameters.push(ast::Parameter(
+                self.parse_identifier(child)?,
+                ast::Address::Dynamic,
+            ));
         }
 
-        let body = self.parse_block(
-            node.child_by_field_name("body")
-                .expect("lambda has no body"),
-        )?;
+        let body = self.parse_block(self.field(node, "body")?)?;
 
         Ok(ast::Definition {
             name: None,
+            address: ast::Address::Dynamic,
             parameters,
             body,
+            locals: 0,
         })
     }
 
@@ -239,6 +286,7 @@ impl<'a> Parser<'a> {
             }
             "identifier" => ast::Expression::Term(ast::ExpressionTerm::Identifier(
                 self.parse_identifier(node)?,
+                ast::Address::Dynamic,
             )),
             "array" => {
                 return Ok(ast::Expression::Term(ast::ExpressionTerm::Array(
@@ -254,12 +302,9 @@ impl<'a> Parser<'a> {
             "lambda" => {
                 ast::Expression::Term(ast::ExpressionTerm::Lambda(self.parse_lambda(node)?))
             }
-            "group" => self.parse_expression(
-                node.child_by_field_name("expression")
-                    .expect("group without expression"),
-            )?,
+            "group" => self.parse_expression(self.field(node, "expression")?)?,
 
-            kind => unimplemented!("expression kind: {kind}"),
+            _ => return Err(self.unexpected(node)),
         };
 
         Ok(result)
@@ -269,20 +314,11 @@ impl<'a> Parser<'a> {
         &self,
         node: Node<'_>,
     ) -> Result<(Box<ast::Expression>, ast::Operator, Box<ast::Expression>), ParseError> {
-        let lhs = self.parse_expression(
-            node.child_by_field_name("lhs")
-                .expect("binary expression has no left"),
-        )?;
+        let lhs = self.parse_expression(self.field(node, "lhs")?)?;
 
-        let operator = self.parse_operator(
-            node.child_by_field_name("operator")
-                .expect("binary expression has no operator"),
-        )?;
+        let operator = self.parse_operator(self.field(node, "operator")?)?;
 
-        let rhs = self.parse_expression(
-            node.child_by_field_name("rhs")
-                .expect("binary expression has no right"),
-        )?;
+        let rhs = self.parse_expression(self.field(node, "rhs")?)?;
 
         Ok((Box::new(lhs), operator, Box::new(rhs)))
     }
@@ -299,7 +335,7 @@ impl<'a> Parser<'a> {
         for child in node.children(&mut cursor) {
             match child.kind() {
                 "boolean" => {
-                    if child.child(0).unwrap().kind() == "boolean_true" {
+                    if child.child(0).map(|value| value.kind()) == Some("boolean_true") {
                         return Ok(ast::Literal::Boolean(true));
                     } else {
                         return Ok(ast::Literal::Boolean(false));
@@ -321,7 +357,8 @@ impl<'a> Parser<'a> {
                 _ => {}
             }
         }
-        unreachable!()
+
+        Err(self.unexpected(node))
     }
 
     fn parse_operator(&self, node: Node<'_>) -> Result<ast::Operator, ParseError> {
@@ -343,7 +380,8 @@ impl<'a> Parser<'a> {
             "assignment" => Ok(ast::Operator::Assignment),
             "append" => Ok(ast::Operator::Append),
             "pipe" => Ok(ast::Operator::Pipe),
-            kind => unimplemented!("operator kind: {kind}"),
+            "coalesce" => Ok(ast::Operator::Coalesce),
+            _ => Err(self.unexpected(node)),
         }
     }
 
@@ -351,7 +389,9 @@ impl<'a> Parser<'a> {
         let s = self.value(node.range());
 
         // strip off the quotes at either end
-        let s = s.get(1..s.len() - 1).expect("expected inner pair");
+        let s = s
+            .get(1..s.len().saturating_sub(1))
+            .ok_or_else(|| self.unexpected(node))?;
 
         Ok(s.to_string())
     }
@@ -360,23 +400,15 @@ impl<'a> Parser<'a> {
         let s = self.value(node.range());
 
         // strip off the colon off the start
-        let s = s.get(1..s.len()).expect("expected inner pair");
+        let s = s.get(1..).ok_or_else(|| self.unexpected(node))?;
 
         Ok(s.to_string())
     }
 
     fn parse_method_call(&self, node: Node<'_>) -> Result<ast::MethodCall, ParseError> {
-        let base = Box::new(
-            self.parse_expression(
-                node.child_by_field_name("base")
-                    .expect("method call without base"),
-            )?,
-        );
-
-        let function = self.parse_identifier(
-            node.child_by_field_name("function")
-                .expect("method call without function"),
-        )?;
+        let base = Box::new(self.parse_expression(self.field(node, "base")?)?);
+
+        let function = self.parse_identifier(self.field(node, "function")?)?;
 
         let mut arguments = vec![];
         for child in node.children_by_field_name("argument", &mut node.walk()) {
@@ -386,24 +418,22 @@ impl<'a> Parser<'a> {
         Ok(ast::MethodCall {
             base,
             function,
+            address: ast::Address::Dynamic,
             arguments,
+            optional: self.is_optional(node),
+            location: self.location(node),
         })
     }
 
     fn parse_function_call(&self, node: Node<'_>) -> Result<ast::FunctionCall, ParseError> {
-        let function = Box::new(
-            self.parse_expression(
-                node.child_by_field_name("function")
-                    .expect("function call without callee"),
-            )?,
-        );
+        let function = Box::new(self.parse_expression(self.field(node, "function")?)?);
 
         let mut arguments = vec![];
         for child in node.children_by_field_name("argument", &mut node.walk()) {
             arguments.push(self.parse_argument(child)?);
         }
 
-        Ok(ast::FunctionCall(function, arguments))
+        Ok(ast::FunctionCall(function, arguments, self.location(node)))
     }
 
     fn parse_array(&self, node: Node<'_>) -> Result<ast::Array, ParseError> {
@@ -418,12 +448,7 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_array_slice(&self, node: Node<'_>) -> Result<ast::ArraySlice, ParseError> {
-        let base = Box::new(
-            self.parse_expression(
-                node.child_by_field_name("base")
-                    .expect("array slice with no base"),
-            )?,
-        );
+        let base = Box::new(self.parse_expression(self.field(node, "base")?)?);
 
         let start_node = node.child_by_field_name("start");
         let end_node = node.child_by_field_name("end");
@@ -442,19 +467,14 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_array_index(&self, node: Node<'_>) -> Result<ast::ArrayIndex, ParseError> {
-        let base = self.parse_expression(
-            node.child_by_field_name("base")
-                .expect("array index with no base"),
-        )?;
+        let base = self.parse_expression(self.field(node, "base")?)?;
 
-        let index = self.parse_expression(
-            node.child_by_field_name("index")
-                .expect("array index with no index"),
-        )?;
+        let index = self.parse_expression(self.field(node, "index")?)?;
 
         Ok(ast::ArrayIndex {
             base: Box::new(base),
             index: Box::new(index),
+            optional: self.is_optional(node),
         })
     }
 
@@ -470,46 +490,35 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_object_member(&self, node: Node<'_>) -> Result<(String, ast::Expression), ParseError> {
-        let key = self.parse_identifier(
-            node.child_by_field_name("key")
-                .expect("object member without key"),
-        )?;
+        let key = self.parse_identifier(self.field(node, "key")?)?;
 
-        let value = self.parse_expression(
-            node.child_by_field_name("value")
-                .expect("object member without value"),
-        )?;
+        let value = self.parse_expression(self.field(node, "value")?)?;
 
         Ok((key.name, value))
     }
 
     fn parse_object_index(&self, node: Node<'_>) -> Result<ast::ObjectIndex, ParseError> {
-        let base = self.parse_expression(
-            node.child_by_field_name("base")
-                .expect("object index without base"),
-        )?;
+        let base = self.parse_expression(self.field(node, "base")?)?;
 
-        let index = self.parse_identifier(
-            node.child_by_field_name("index")
-                .expect("object index without index"),
-        )?;
+        let index = self.parse_identifier(self.field(node, "index")?)?;
 
         Ok(ast::ObjectIndex {
             base: Box::new(base),
             index,
+            optional: self.is_optional(node),
         })
     }
 
+    // optional chaining (`?.` and `?[`) is marked with an `optional` field on
+    // object index, array index and method call nodes
+    fn is_optional(&self, node: Node<'_>) -> bool {
+        node.child_by_field_name("optional").is_some()
+    }
+
     fn parse_if_expression(&self, node: Node<'_>) -> Result<ast::If, ParseError> {
-        let condition = self.parse_expression(
-            node.child_by_field_name("condition")
-                .expect("if expression without condition"),
-        )?;
+        let condition = self.parse_expression(self.field(node, "condition")?)?;
 
-        let body = self.parse_block(
-            node.child_by_field_name("body")
-                .expect("if expression without body"),
-        )?;
+        let body = self.parse_block(self.field(node, "body")?)?;
 
         let mut elseif_branches = vec![];
         let mut cursor = node.walk();
@@ -534,37 +543,43 @@ impl<'a> Parser<'a> {
         &self,
         node: Node<'_>,
     ) -> Result<(ast::Expression, ast::Block), ParseError> {
-        let condition = self.parse_expression(
-            node.child_by_field_name("condition")
-                .expect("else if without condition"),
-        )?;
+        let condition = self.parse_expression(self.field(node, "condition")?)?;
 
-        let body = self.parse_block(
-            node.child_by_field_name("body")
-                .expect("else if without body"),
-        )?;
+        let body = self.parse_block(self.field(node, "body")?)?;
 
         Ok((condition, body))
     }
 
     fn parse_else_expression(&self, node: Node<'_>) -> Result<ast::Block, ParseError> {
-        let block =
-            self.parse_block(node.child_by_field_name("body").expect("else without body"))?;
+        let block = self.parse_block(self.field(node, "body")?)?;
 
         Ok(block)
     }
 
     fn parse_argument(&self, node: Node<'_>) -> Result<ast::Argument, ParseError> {
-        let name = self.parse_identifier(
-            node.child_by_field_name("name")
-                .expect("argument without name"),
-        )?;
+        let name = self.parse_identifier(self.field(node, "name")?)?;
 
-        let value = self.parse_expression(
-            node.child_by_field_name("value")
-                .expect("argument without value"),
-        )?;
+        let value = self.parse_expression(self.field(node, "value")?)?;
 
         Ok(ast::Argument(name, value))
     }
 }
+
+fn first_error(node: Node<'_>) -> Option<Node<'_>> {
+    if node.is_error() || node.is_missing() {
+        return Some(node);
+    }
+
+    if !node.has_error() {
+        return None;
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if let Some(error) = first_error(child) {
+            return Some(error);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,264 @@
+//! Assigns every variable an address after parsing, so the interpreter can
+//! find function locals by index instead of searching scopes by name.
+//!
+//! Each function call gets one slot for each of its parameters and every name
+//! declared anywhere in its body, including inside `if` branches, which don't
+//! introduce scopes of their own. Nested functions get their own slots. Names
+//! that no enclosing function declares are left dynamic.
+
+use std::collections::HashMap;
+
+use crate::ast;
+
+pub fn resolve_program(program: &mut ast::Program) {
+    Resolver::default().block(&mut program.block);
+}
+
+pub fn resolve_expression(expression: &mut ast::Expression) {
+    Resolver::default().expression(expression);
+}
+
+// the slots of each enclosing function, innermost last
+#[derive(Default)]
+struct Resolver {
+    frames: Vec<HashMap<String, usize>>,
+}
+
+impl Resolver {
+    // the address of a name that's being read or assigned to
+    fn variable(&self, name: &ast::Identifier) -> ast::Address {
+        let slots: Vec<(usize, usize)> = self
+            .frames
+            .iter()
+            .rev()
+            .enumerate()
+            .filter_map(|(depth, frame)| frame.get(&name.name).map(|slot| (depth, *slot)))
+            .collect();
+
+        if slots.is_empty() {
+            ast::Address::Dynamic
+        } else {
+            ast::Address::Local(slots)
+        }
+    }
+
+    // the address of a name that's being declared in the current function
+    fn declaration(&self, name: &ast::Identifier) -> ast::Address {
+        match self.frames.last() {
+            Some(frame) => ast::Address::Local(vec![(0, frame[&name.name])]),
+            None => ast::Address::Dynamic,
+        }
+    }
+
+    fn definition(&mut self, definition: &mut ast::Definition) {
+        let mut frame = HashMap::new();
+        for parameter in &definition.parameters {
+            declare(&mut frame, &parameter.0);
+        }
+        collect_block(&definition.body, &mut frame);
+
+        self.frames.push(frame);
+        for parameter in &mut definition.parameters {
+            parameter.1 = self.declaration(&parameter.0);
+        }
+        self.block(&mut definition.body);
+
+        let frame = self.frames.pop().expect("function frame missing");
+        definition.locals = frame.len();
+    }
+
+    fn block(&mut self, block: &mut ast::Block) {
+        for statement in &mut block.statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &mut ast::Statement) {
+        match statement {
+            ast::Statement::Binding(name, address, rhs)
+            | ast::Statement::MutableBinding(name, address, rhs) => {
+                self.expression(rhs);
+                *address = self.declaration(name);
+            }
+            ast::Statement::Definition(definition) => {
+                if let Some(name) = &definition.name {
+                    definition.address = self.declaration(name);
+                }
+                self.definition(definition);
+            }
+            ast::Statement::Import(import) => {
+                for symbol in &mut import.0 {
+                    let name = symbol.1.as_ref().unwrap_or(&symbol.0);
+                    symbol.2 = self.declaration(name);
+                }
+            }
+            ast::Statement::Expression(expression) => self.expression(expression),
+        }
+    }
+
+    fn expression(&mut self, expression: &mut ast::Expression) {
+        match expression {
+            ast::Expression::Term(term) => self.term(term),
+            ast::Expression::BinaryExpression(lhs, _, rhs) => {
+                self.expression(lhs);
+                self.expression(rhs);
+            }
+        }
+    }
+
+    fn term(&mut self, term: &mut ast::ExpressionTerm) {
+        match term {
+            ast::ExpressionTerm::Identifier(name, address) => *address = self.variable(name),
+            ast::ExpressionTerm::Expression(expression) => self.expression(expression),
+            ast::ExpressionTerm::If(if_term) => {
+                self.expression(&mut if_term.condition);
+                self.block(&mut if_term.body);
+                for (condition, body) in &mut if_term.elseif_branches {
+                    self.expression(condition);
+                    self.block(body);
+                }
+                if let Some(else_branch) = &mut if_term.else_branch {
+                    self.block(else_branch);
+                }
+            }
+            ast::ExpressionTerm::ArraySlice(array_slice) => {
+                self.expression(&mut array_slice.base);
+                if let Some(start) = &mut array_slice.start {
+                    self.expression(start);
+                }
+                if let Some(end) = &mut array_slice.end {
+                    self.expression(end);
+                }
+            }
+            ast::ExpressionTerm::ArrayIndex(array_index) => {
+                self.expression(&mut array_index.base);
+                self.expression(&mut array_index.index);
+            }
+            ast::ExpressionTerm::ObjectIndex(object_index) => {
+                self.expression(&mut object_index.base);
+            }
+            ast::ExpressionTerm::MethodCall(method_call) => {
+                method_call.address = self.variable(&method_call.function);
+                self.expression(&mut method_call.base);
+                for argument in &mut method_call.arguments {
+                    self.expression(&mut argument.1);
+                }
+            }
+            ast::ExpressionTerm::FunctionCall(ast::FunctionCall(callee, arguments, _)) => {
+                self.expression(callee);
+                for argument in arguments {
+                    self.expression(&mut argument.1);
+                }
+            }
+            ast::ExpressionTerm::Literal(_) => {}
+            ast::ExpressionTerm::Array(ast::Array(members)) => {
+                for member in members {
+                    self.expression(member);
+                }
+            }
+            ast::ExpressionTerm::Object(ast::Object(members)) => {
+                for (_, value) in members {
+                    self.expression(value);
+                }
+            }
+            ast::ExpressionTerm::Lambda(definition) => self.definition(definition),
+        }
+    }
+}
+
+fn declare(frame: &mut HashMap<String, usize>, name: &ast::Identifier) {
+    let next = frame.len();
+    frame.entry(name.name.clone()).or_insert(next);
+}
+
+// finds the names declared by a function body, without looking inside the
+// functions it defines
+fn collect_block(block: &ast::Block, frame: &mut HashMap<String, usize>) {
+    for statement in &block.statements {
+        match statement {
+            ast::Statement::Binding(name, _, rhs)
+            | ast::Statement::MutableBinding(name, _, rhs) => {
+                collect_expression(rhs, frame);
+                declare(frame, name);
+            }
+            ast::Statement::Definition(definition) => {
+                if let Some(name) = &definition.name {
+                    declare(frame, name);
+                }
+            }
+            ast::Statement::Import(import) => {
+                for symbol in &import.0 {
+                    declare(frame, symbol.1.as_ref().unwrap_or(&symbol.0));
+                }
+            }
+            ast::Statement::Expression(expression) => collect_expression(expression, frame),
+        }
+    }
+}
+
+fn collect_expression(expression: &ast::Expression, frame: &mut HashMap<String, usize>) {
+    let term = match expression {
+        ast::Expression::Term(term) => term,
+        ast::Expression::BinaryExpression(lhs, _, rhs) => {
+            collect_expression(lhs, frame);
+            collect_expression(rhs, frame);
+            return;
+        }
+    };
+
+    match term {
+        ast::ExpressionTerm::Expression(expression) => collect_expression(expression, frame),
+        ast::ExpressionTerm::If(if_term) => {
+            collect_expression(&if_term.condition, frame);
+            collect_block(&if_term.body, frame);
+            for (condition, body) in &if_term.elseif_branches {
+                collect_expression(condition, frame);
+                collect_block(body, frame);
+            }
+            if let Some(else_branch) = &if_term.else_branch {
+                collect_block(else_branch, frame);
+            }
+        }
+        ast::ExpressionTerm::ArraySlice(array_slice) => {
+            collect_expression(&array_slice.base, frame);
+            if let Some(start) = &array_slice.start {
+                collect_expression(start, frame);
+            }
+            if let Some(end) = &array_slice.end {
+                collect_expression(end, frame);
+            }
+        }
+        ast::ExpressionTerm::ArrayIndex(array_index) => {
+            collect_expression(&array_index.base, frame);
+            collect_expression(&array_index.index, frame);
+        }
+        ast::ExpressionTerm::ObjectIndex(object_index) => {
+            collect_expression(&object_index.base, frame);
+        }
+        ast::ExpressionTerm::MethodCall(method_call) => {
+            collect_expression(&method_call.base, frame);
+            for argument in &method_call.arguments {
+                collect_expression(&argument.1, frame);
+            }
+        }
+        ast::ExpressionTerm::FunctionCall(ast::FunctionCall(callee, arguments, _)) => {
+            collect_expression(callee, frame);
+            for argument in arguments {
+                collect_expression(&argument.1, frame);
+            }
+        }
+        ast::ExpressionTerm::Array(ast::Array(members)) => {
+            for member in members {
+                collect_expression(member, frame);
+            }
+        }
+        ast::ExpressionTerm::Object(ast::Object(members)) => {
+            for (_, value) in members {
+                collect_expression(value, frame);
+            }
+        }
+        ast::ExpressionTerm::Identifier(..)
+        | ast::ExpressionTerm::Literal(_)
+        | ast::ExpressionTerm::Lambda(_) => {}
+    }
+}
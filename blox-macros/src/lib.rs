@@ -0,0 +1,275 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_macro_input, FnArg, GenericArgument, ItemFn, LitStr, Pat, PathArguments, ReturnType, Type,
+};
+
+// turns a rust function into a blox intrinsic. the function is left as it
+// is, and a `<name>_intrinsic()` function is added next to it that returns an
+// `Intrinsic` calling it:
+//
+//     #[blox_intrinsic]
+//     fn pad(text: String, width: Decimal, fill: Option<String>) -> Result<String, RuntimeError>
+//
+// each parameter is taken from the argument of the same name and converted
+// with `TryFrom<Value>`; `Option` parameters may be left out or void. the
+// function returns anything that converts `Into<Value>`, or a `Result` of
+// one. async functions become async intrinsics.
+//
+// a `&mut EvaluationContext` parameter is passed the context the intrinsic
+// was called in, so it can run blox code like a lambda it was given, and a
+// `&Location` parameter is passed where it was called from.
+//
+// the intrinsic is called by the function's name in blox, unless it's given
+// another with `#[blox_intrinsic(name = "...")]`.
+#[proc_macro_attribute]
+pub fn blox_intrinsic(attribute: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name = None;
+    let attribute_parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<LitStr>()?.value());
+            Ok(())
+        } else {
+            Err(meta.error("expected `name = \"...\"`"))
+        }
+    });
+    parse_macro_input!(attribute with attribute_parser);
+
+    let function = parse_macro_input!(item as ItemFn);
+    match expand(name, function) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(name: Option<String>, function: ItemFn) -> syn::Result<TokenStream2> {
+    let signature = &function.sig;
+    if !signature.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &signature.generics,
+            "intrinsics can't be generic",
+        ));
+    }
+
+    let ident = &signature.ident;
+    let visibility = &function.vis;
+    let name = name.unwrap_or_else(|| ident.to_string());
+    let constructor = format_ident!("{}_intrinsic", ident);
+
+    let mut extractions = Vec::new();
+    let mut variables = Vec::new();
+    let mut arguments = Vec::new();
+    let mut takes_context = false;
+    for input in &signature.inputs {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new_spanned(input, "intrinsics can't take self"));
+        };
+        let Pat::Ident(pattern) = input.pat.as_ref() else {
+            return Err(syn::Error::new_spanned(
+                &input.pat,
+                "intrinsic parameters must be plain names",
+            ));
+        };
+
+        let variable = &pattern.ident;
+        if is_context(&input.ty) {
+            if signature.asyncness.is_some() {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "async intrinsics can't take the evaluation context",
+                ));
+            }
+
+            extractions.push(quote!(let #variable = __context;));
+            variables.push(variable);
+            takes_context = true;
+            continue;
+        }
+        if is_call_site(&input.ty) {
+            if signature.asyncness.is_some() {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "async intrinsics can't take their call site",
+                ));
+            }
+
+            extractions.push(quote!(let #variable = __call_site;));
+            variables.push(variable);
+            takes_context = true;
+            continue;
+        }
+
+        let argument = variable.to_string();
+        let argument = argument.trim_start_matches("r#").to_string();
+        let (helper, ty) = match option_type(&input.ty) {
+            Some(ty) => (quote!(optional_intrinsic_argument), ty),
+            None => (quote!(intrinsic_argument), input.ty.as_ref()),
+        };
+        let expected = expected_type(ty);
+
+        extractions.push(quote! {
+            let #variable = ::blox_interpreter::#helper::<#ty>(
+                #name,
+                &mut __arguments,
+                #argument,
+                #expected,
+            )?;
+        });
+        variables.push(variable);
+        arguments.push(argument);
+    }
+
+    let call = match signature.asyncness {
+        Some(_) => quote!(#ident(#(#variables),*).await),
+        None => quote!(#ident(#(#variables),*)),
+    };
+    let result = match returns_result(&signature.output) {
+        true => quote!(#call.map(::std::convert::Into::<::blox_interpreter::Value>::into)),
+        false => quote!(Ok(::std::convert::Into::<::blox_interpreter::Value>::into(#call))),
+    };
+
+    let intrinsic = match signature.asyncness {
+        // the arguments are converted before the future is made, so that it
+        // doesn't borrow them
+        Some(_) => quote! {
+            ::blox_interpreter::Intrinsic::new_async(
+                #name,
+                ::std::sync::Arc::new(
+                    |mut __arguments: ::blox_interpreter::IntrinsicArguments|
+                        -> ::blox_interpreter::IntrinsicFuture {
+                        let __converted = (|| -> ::std::result::Result<
+                            _,
+                            ::blox_interpreter::RuntimeError,
+                        > {
+                            #(#extractions)*
+                            Ok((#(#variables,)*))
+                        })();
+
+                        ::std::boxed::Box::pin(async move {
+                            let (#(#variables,)*) = __converted?;
+                            #result
+                        })
+                    },
+                ),
+            )
+        },
+        None if takes_context => quote! {
+            ::blox_interpreter::Intrinsic::new_with_context(
+                #name,
+                ::std::sync::Arc::new(
+                    |mut __arguments: ::blox_interpreter::IntrinsicArguments,
+                     __call_site: &::blox_interpreter::Location,
+                     __context: &mut ::blox_interpreter::EvaluationContext|
+                        -> ::std::result::Result<
+                            ::blox_interpreter::Value,
+                            ::blox_interpreter::RuntimeError,
+                        > {
+                    #(#extractions)*
+                    #result
+                },
+                ),
+            )
+        },
+        None => quote! {
+            ::blox_interpreter::Intrinsic::new(
+                #name,
+                ::std::sync::Arc::new(
+                    |mut __arguments: ::blox_interpreter::IntrinsicArguments|
+                        -> ::std::result::Result<
+                            ::blox_interpreter::Value,
+                            ::blox_interpreter::RuntimeError,
+                        > {
+                    #(#extractions)*
+                    #result
+                },
+                ),
+            )
+        },
+    };
+
+    // method calls pass the receiver as the first argument
+    let receiver = arguments
+        .first()
+        .map(|argument| quote!(.with_receiver(#argument)));
+
+    Ok(quote! {
+        #function
+
+        #[allow(unused_mut, clippy::result_large_err)]
+        #visibility fn #constructor() -> ::blox_interpreter::Intrinsic {
+            #intrinsic #receiver
+        }
+    })
+}
+
+fn last_segment(ty: &Type) -> Option<&syn::PathSegment> {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last(),
+        _ => None,
+    }
+}
+
+// whether `ty` is `&mut EvaluationContext`
+fn is_context(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => {
+            last_segment(&reference.elem)
+                .is_some_and(|segment| segment.ident == "EvaluationContext")
+        }
+        _ => false,
+    }
+}
+
+// whether `ty` is `&Location`
+fn is_call_site(ty: &Type) -> bool {
+    match ty {
+        Type::Reference(reference) if reference.mutability.is_none() => {
+            last_segment(&reference.elem).is_some_and(|segment| segment.ident == "Location")
+        }
+        _ => false,
+    }
+}
+
+// `T` when `ty` is `Option<T>`
+fn option_type(ty: &Type) -> Option<&Type> {
+    let segment = last_segment(ty).filter(|segment| segment.ident == "Option")?;
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+
+    match arguments.args.first() {
+        Some(GenericArgument::Type(ty)) => Some(ty),
+        _ => None,
+    }
+}
+
+fn returns_result(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Type(_, ty) => {
+            last_segment(ty).is_some_and(|segment| segment.ident == "Result")
+        }
+        ReturnType::Default => false,
+    }
+}
+
+// how a parameter's type is described to blox programs that pass the wrong
+// kind of value
+fn expected_type(ty: &Type) -> String {
+    let name = last_segment(ty).map(|segment| segment.ident.to_string());
+
+    match name.as_deref() {
+        Some("String") => "a string".to_string(),
+        Some("Decimal" | "f64") => "a number".to_string(),
+        Some("i32" | "i64" | "u32" | "u64" | "usize") => "a whole number".to_string(),
+        Some("bool") => "a boolean".to_string(),
+        Some("Vector" | "Vec") => "an array".to_string(),
+        Some("OrdMap" | "BTreeMap" | "HashMap") => "an object".to_string(),
+        Some("Value") => "a value".to_string(),
+        Some("Callback") => "a function".to_string(),
+        Some("Zoned") => "a time".to_string(),
+        Some("SignedDuration") => "a duration".to_string(),
+        Some("Regex") => "a regex".to_string(),
+        _ => ty.to_token_stream().to_string(),
+    }
+}
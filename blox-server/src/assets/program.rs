@@ -32,9 +32,8 @@ impl std::error::Error for BloxLoaderError {}
 impl blox_assets::Loader<BloxProgram> for BloxLoader {
     fn load(path: &str, content: &[u8], _extension: &str) -> anyhow::Result<BloxProgram> {
         let input = String::from_utf8(content.to_vec())?;
-        let parser = Parser::new(path, &input);
-        parser
-            .parse()
+        Parser::new(path, &input)
+            .and_then(|parser| parser.parse())
             .map_err(|err| Box::new(BloxLoaderError(format!("{:?}", err))).into())
             .map(BloxProgram)
     }
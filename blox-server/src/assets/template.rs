@@ -17,7 +17,7 @@ impl Template {
                 handlebars.register_template("template", template.clone());
 
                 let mut data = HashMap::new();
-                for (identifier, value) in scope.bindings.read().unwrap().iter() {
+                for (identifier, value) in scope.own_bindings()? {
                     data.insert(identifier.name.clone(), value.to_display_string());
                 }
 
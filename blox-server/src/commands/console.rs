@@ -1,40 +1,37 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use blox_assets::AssetManager;
-use blox_interpreter::{start_repl, Intrinsic, Value};
+use blox_interpreter::{blox_intrinsic, start_repl, Value};
 use blox_language::ast::Identifier;
+use tokio::runtime::Handle;
 use tracing::info;
 
 use crate::environment::create_context;
 
+#[blox_intrinsic]
+fn print(message: Option<Value>) {
+    if let Some(message) = message {
+        info!(message = message.to_string());
+    }
+}
+
 pub async fn console_command(directory: &str) -> Result<(), anyhow::Error> {
     let assets = AssetManager::new(&directory)?;
     let assets = Arc::new(Mutex::new(assets));
 
-    let context = create_context(assets);
+    let mut context = create_context(assets)?;
+    context.runtime = Some(Handle::current());
 
     context.scope.insert_binding(
         &Identifier {
             name: "print".to_string(),
         },
-        Value::Intrinsic(Intrinsic::new(
-            "print",
-            Arc::new(|arguments: HashMap<Identifier, Value>| {
-                if let Some(message) = arguments.get(&Identifier {
-                    name: "message".to_string(),
-                }) {
-                    info!(message = message.to_string());
-                }
-
-                Ok(Value::Void)
-            }),
-        )),
-    );
-
-    start_repl(context)?;
+        Value::Intrinsic(print_intrinsic()),
+    )?;
+
+    // the repl blocks on reading lines, so it runs on a blocking thread, where
+    // async intrinsics like database queries can wait on the runtime
+    tokio::task::spawn_blocking(move || start_repl(context)).await??;
 
     Ok(())
 }
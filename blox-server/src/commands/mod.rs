@@ -1,4 +1,9 @@
 mod console;
 mod server;
+mod test;
 
-pub use self::{console::console_command, server::server_command};
+pub use self::{
+    console::console_command,
+    server::server_command,
+    test::{test_command, TestFormat},
+};
@@ -1,13 +1,15 @@
 use std::{
     net::SocketAddr,
     sync::{Arc, Mutex},
+    time::Duration,
 };
 
+use anyhow::anyhow;
 use blox_assets::{types::AssetPath, AssetError, AssetManager};
-use blox_interpreter::{execute_program, Scope, Value};
+use blox_interpreter::{execute_program_async, Limits, Scope, Value};
 use blox_language::ast::Identifier;
 use http_body_util::Full;
-use hyper::{body::Bytes, server::conn::http1, service::service_fn, Request, Response};
+use hyper::{body::Bytes, server::conn::http1, service::service_fn, Request, Response, StatusCode};
 use hyper_util::rt::{TokioIo, TokioTimer};
 use tokio::net::TcpListener;
 use tracing::{debug, error, info, instrument};
@@ -18,11 +20,22 @@ use crate::{
     router::request_asset_path,
 };
 
+// the most work a single request's handler may do, so that a runaway handler
+// can't hold on to a worker forever
+fn request_limits() -> Limits {
+    Limits {
+        max_steps: Some(10_000_000),
+        max_call_depth: Some(10_000),
+        max_bytes: Some(256 * 1024 * 1024),
+        timeout: Some(Duration::from_secs(10)),
+    }
+}
+
 pub async fn server_command(port: u16, path: String) -> Result<(), anyhow::Error> {
     let assets = AssetManager::new(&path)?;
     let assets = Arc::new(Mutex::new(assets));
 
-    let environment = Arc::new(BloxEnvironment::new(assets.clone()));
+    let environment = Arc::new(BloxEnvironment::new(assets.clone())?);
     environment.start();
 
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
@@ -58,30 +71,60 @@ pub async fn handle_request(
     let method = request.method();
     let uri = request.uri();
 
+    let path_and_query = uri.path_and_query().map_or(uri.path(), |p| p.as_str());
     tracing::Span::current()
         .record("method", &method.as_str())
-        .record("uri", &uri.path_and_query().unwrap().to_string().as_str());
+        .record("uri", &path_and_query);
 
-    let (path, bindings) = request_asset_path(request.method(), request.uri())?;
+    let (path, bindings) = match request_asset_path(request.method(), request.uri()) {
+        Ok(route) => route,
+        Err(error) => {
+            info!(error = error.to_string().as_str(), "No route");
+
+            let mut response = Response::new(error.to_string().into());
+            *response.status_mut() = StatusCode::NOT_FOUND;
+            return Ok(response);
+        }
+    };
 
     info!(?path, "Requesting asset");
 
+    // the asset manager is only locked while loading assets, not while the
+    // handler runs, so requests can be handled concurrently
     let assets = environment.assets();
-    let mut assets = assets.lock().unwrap();
+    let lock_assets = || {
+        assets
+            .lock()
+            .map_err(|_| anyhow!("asset manager lock poisoned"))
+    };
 
     let scope = Arc::new(Scope::default());
     for (name, value) in bindings {
-        scope.insert_binding(&Identifier { name: name.clone() }, Value::String(value))
+        scope.insert_binding(&Identifier { name: name.clone() }, Value::String(value))?;
     }
 
-    let mut context = environment.context().read().unwrap().child();
+    let mut context = environment
+        .context()
+        .read()
+        .map_err(|_| anyhow!("evaluation context lock poisoned"))?
+        .child()
+        .with_limits(request_limits());
 
     debug!(?path, "Loading asset");
     match path {
         AssetPath::Route(ref _vec) => {
-            match assets.load::<BloxProgram>(&path) {
+            let program = lock_assets()?.load::<BloxProgram>(&path);
+            match program {
                 Ok(program) => {
-                    execute_program(&program.into(), &mut context)?;
+                    if let Err(error) =
+                        execute_program_async(Arc::new(program.into()), &mut context).await
+                    {
+                        error!(
+                            error = error.to_string().as_str(),
+                            "Error while running handler"
+                        );
+                        return Ok(Response::new(error.to_string().into()));
+                    }
                 }
 
                 Err(error) => {
@@ -97,7 +140,8 @@ pub async fn handle_request(
                 }
             }
 
-            match assets.load::<Template>(&path) {
+            let template = lock_assets()?.load::<Template>(&path);
+            match template {
                 Ok(template) => match template.render(&scope) {
                     Ok(body) => Ok(Response::new(body.into())),
                     Err(error) => {
@@ -120,7 +164,7 @@ pub async fn handle_request(
             }
         }
         AssetPath::Static(_) => {
-            let asset = assets.load::<StaticFile>(&path)?;
+            let asset = lock_assets()?.load::<StaticFile>(&path)?;
             Ok(asset.into())
         }
         path => {
@@ -0,0 +1,492 @@
+use std::{
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use blox_interpreter::{Engine, EngineError, Limits};
+use blox_language::{ast, parser::Parser};
+use clap::ValueEnum;
+use serde_json::json;
+use tokio::runtime::Handle;
+
+// how `blox test` reports its results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TestFormat {
+    #[default]
+    Human,
+    Json,
+    Junit,
+}
+
+// a test: a function named `test_*` defined in a `*_test.blox` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TestCase {
+    // relative to the app directory
+    file: PathBuf,
+    name: String,
+    // why the file couldn't be parsed, when it couldn't. its tests can't be
+    // found, so a case named `parse` stands in for them and fails.
+    parse_error: Option<String>,
+}
+
+impl TestCase {
+    // what filters match against, like `orders/total_test.blox::test_discount`
+    fn id(&self) -> String {
+        format!("{}::{}", self.file.display(), self.name)
+    }
+}
+
+#[derive(Debug)]
+struct TestResult {
+    case: TestCase,
+    duration: Duration,
+    // the error that failed the test
+    failure: Option<String>,
+}
+
+#[derive(Debug)]
+struct TestRun {
+    results: Vec<TestResult>,
+    filtered_out: usize,
+}
+
+impl TestRun {
+    fn failed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count()
+    }
+}
+
+// the most work a single test may do, so that a runaway test fails rather
+// than hanging the run
+fn test_limits() -> Limits {
+    Limits {
+        max_steps: Some(100_000_000),
+        max_call_depth: Some(10_000),
+        max_bytes: Some(1024 * 1024 * 1024),
+        timeout: Some(Duration::from_secs(60)),
+    }
+}
+
+// runs the tests in `directory` whose ids contain `filter`, returning whether
+// they all passed
+pub async fn test_command(
+    directory: &str,
+    filter: Option<String>,
+    format: TestFormat,
+) -> Result<bool, anyhow::Error> {
+    let directory = PathBuf::from(directory);
+    let runtime = Handle::current();
+
+    // tests run on a blocking thread, where async intrinsics like database
+    // queries can wait on the runtime
+    let run = tokio::task::spawn_blocking(move || {
+        run_tests(&directory, filter.as_deref(), Some(runtime))
+    })
+    .await??;
+
+    let report = match format {
+        TestFormat::Human => human_report(&run),
+        TestFormat::Json => json_report(&run)?,
+        TestFormat::Junit => junit_report(&run),
+    };
+    print!("{report}");
+
+    Ok(run.failed() == 0)
+}
+
+fn run_tests(
+    directory: &Path,
+    filter: Option<&str>,
+    runtime: Option<Handle>,
+) -> Result<TestRun, anyhow::Error> {
+    let (cases, filtered_out): (Vec<_>, Vec<_>) = discover(directory)?
+        .into_iter()
+        .partition(|case| filter.is_none_or(|filter| case.id().contains(filter)));
+
+    let results = cases
+        .into_iter()
+        .map(|case| {
+            let started = Instant::now();
+            let failure = match &case.parse_error {
+                Some(error) => Some(error.clone()),
+                None => run_test(directory, &case, runtime.clone())
+                    .err()
+                    .map(|error| error.to_string()),
+            };
+
+            TestResult {
+                case,
+                duration: started.elapsed(),
+                failure,
+            }
+        })
+        .collect();
+
+    Ok(TestRun {
+        results,
+        filtered_out: filtered_out.len(),
+    })
+}
+
+// each test gets an engine of its own and loads its file afresh, so nothing
+// one test does can be seen by another
+fn run_test(directory: &Path, case: &TestCase, runtime: Option<Handle>) -> Result<(), EngineError> {
+    let mut engine = Engine::builder()
+        .import_root(directory)
+        .limits(test_limits())
+        .build()?;
+    engine.context_mut().runtime = runtime;
+
+    engine.load_file(directory.join(&case.file))?;
+    engine.call(&case.name, std::iter::empty())?;
+    Ok(())
+}
+
+// the tests under `directory`, in order of their files' paths and then of
+// their definitions. a file that can't be parsed gets a failing case instead.
+fn discover(directory: &Path) -> Result<Vec<TestCase>, anyhow::Error> {
+    let mut files = Vec::new();
+    find_test_files(directory, directory, &mut files)?;
+    files.sort();
+
+    let mut cases = Vec::new();
+    for file in files {
+        let source = fs::read_to_string(directory.join(&file))?;
+        let program =
+            match Parser::new(&file.to_string_lossy(), &source).and_then(|parser| parser.parse()) {
+                Ok(program) => program,
+                Err(error) => {
+                    cases.push(TestCase {
+                        file: file.clone(),
+                        name: "parse".to_string(),
+                        parse_error: Some(format!("could not parse {}: {error}", file.display())),
+                    });
+                    continue;
+                }
+            };
+
+        for statement in &program.block.statements {
+            let ast::Statement::Definition(ast::Definition {
+                name: Some(name), ..
+            }) = statement
+            else {
+                continue;
+            };
+
+            if name.name.starts_with("test_") {
+                cases.push(TestCase {
+                    file: file.clone(),
+                    name: name.name.clone(),
+                    parse_error: None,
+                });
+            }
+        }
+    }
+
+    Ok(cases)
+}
+
+// hidden directories, like `.git`, are skipped
+fn find_test_files(
+    root: &Path,
+    directory: &Path,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), std::io::Error> {
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        if entry.file_type()?.is_dir() {
+            find_test_files(root, &path, files)?;
+        } else if name.ends_with("_test.blox") {
+            files.push(path.strip_prefix(root).unwrap_or(&path).to_path_buf());
+        }
+    }
+
+    Ok(())
+}
+
+// like cargo's: a line for each test, then what made the failures fail
+fn human_report(run: &TestRun) -> String {
+    let mut report = String::new();
+
+    for result in &run.results {
+        let status = if result.failure.is_some() {
+            "FAILED"
+        } else {
+            "ok"
+        };
+        let _ = writeln!(report, "test {} ... {status}", result.case.id());
+    }
+
+    let failures: Vec<_> = run
+        .results
+        .iter()
+        .filter_map(|result| Some((&result.case, result.failure.as_ref()?)))
+        .collect();
+    if !failures.is_empty() {
+        let _ = writeln!(report, "\nfailures:");
+        for (case, failure) in &failures {
+            let _ = writeln!(report, "\n---- {} ----\n{failure}", case.id());
+        }
+    }
+
+    let outcome = if failures.is_empty() { "ok" } else { "FAILED" };
+    let _ = writeln!(
+        report,
+        "\ntest result: {outcome}. {} passed; {} failed; {} filtered out",
+        run.results.len() - failures.len(),
+        failures.len(),
+        run.filtered_out
+    );
+    report
+}
+
+fn json_report(run: &TestRun) -> Result<String, anyhow::Error> {
+    let tests: Vec<_> = run
+        .results
+        .iter()
+        .map(|result| {
+            json!({
+                "file": result.case.file.to_string_lossy(),
+                "name": result.case.name,
+                "passed": result.failure.is_none(),
+                "duration_ms": result.duration.as_secs_f64() * 1000.0,
+                "failure": result.failure,
+            })
+        })
+        .collect();
+
+    let report = json!({
+        "passed": run.results.len() - run.failed(),
+        "failed": run.failed(),
+        "filtered_out": run.filtered_out,
+        "tests": tests,
+    });
+    Ok(serde_json::to_string_pretty(&report)? + "\n")
+}
+
+// JUnit XML, which CI services can show; a test suite for each file
+fn junit_report(run: &TestRun) -> String {
+    let mut report = String::new();
+    let total: Duration = run.results.iter().map(|result| result.duration).sum();
+
+    let _ = writeln!(report, r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+    let _ = writeln!(
+        report,
+        r#"<testsuites name="blox" tests="{}" failures="{}" time="{:.3}">"#,
+        run.results.len(),
+        run.failed(),
+        total.as_secs_f64()
+    );
+
+    for suite in run.results.chunk_by(|a, b| a.case.file == b.case.file) {
+        let file = xml_escape(&suite[0].case.file.to_string_lossy());
+        let failures = suite
+            .iter()
+            .filter(|result| result.failure.is_some())
+            .count();
+        let time: Duration = suite.iter().map(|result| result.duration).sum();
+        let _ = writeln!(
+            report,
+            r#"  <testsuite name="{file}" tests="{}" failures="{failures}" time="{:.3}">"#,
+            suite.len(),
+            time.as_secs_f64()
+        );
+
+        for result in suite {
+            let name = xml_escape(&result.case.name);
+            let time = result.duration.as_secs_f64();
+            match &result.failure {
+                None => {
+                    let _ = writeln!(
+                        report,
+                        r#"    <testcase name="{name}" classname="{file}" time="{time:.3}"/>"#
+                    );
+                }
+                Some(failure) => {
+                    let message = xml_escape(failure.lines().next().unwrap_or_default());
+                    let _ = writeln!(
+                        report,
+                        r#"    <testcase name="{name}" classname="{file}" time="{time:.3}">"#
+                    );
+                    let _ = writeln!(
+                        report,
+                        r#"      <failure message="{message}">{}</failure>"#,
+                        xml_escape(failure)
+                    );
+                    let _ = writeln!(report, "    </testcase>");
+                }
+            }
+        }
+
+        let _ = writeln!(report, "  </testsuite>");
+    }
+
+    let _ = writeln!(report, "</testsuites>");
+    report
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an app directory holding `files`, removed when dropped
+    struct App(PathBuf);
+
+    impl App {
+        fn new(name: &str, files: &[(&str, &str)]) -> Self {
+            let directory =
+                std::env::temp_dir().join(format!("blox-test-{name}-{}", std::process::id()));
+            for (path, source) in files {
+                let path = directory.join(path);
+                fs::create_dir_all(path.parent().expect("file has a directory"))
+                    .expect("create directory");
+                fs::write(path, source).expect("write file");
+            }
+            App(directory)
+        }
+    }
+
+    impl Drop for App {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn app() -> App {
+        App::new(
+            "orders",
+            &[
+                (
+                    "lib/orders.blox",
+                    "def total(prices) { prices.length() * 10 }",
+                ),
+                (
+                    "lib/orders_test.blox",
+                    "import { total } from 'lib/orders'\nimport { assert, assert_eq } from 'stdlib/test'\ndef test_total() { assert_eq(actual: total(prices: [1, 2]), expected: 20) }\ndef test_empty() { assert_eq(actual: total(prices: []), expected: 1) }\ndef helper() { assert(condition: false) }",
+                ),
+                (
+                    "prices_test.blox",
+                    "import { assert_raises } from 'stdlib/test'\ndef test_raises() { assert_raises(fn: || { 1 + 'a' }) }",
+                ),
+                (".hidden/skipped_test.blox", "def test_hidden() { 1 }"),
+            ],
+        )
+    }
+
+    fn ids(run: &TestRun) -> Vec<String> {
+        run.results.iter().map(|result| result.case.id()).collect()
+    }
+
+    #[test]
+    fn test_discovery_and_filtering() {
+        let app = app();
+
+        let run = run_tests(&app.0, None, None).expect("tests run");
+        assert_eq!(
+            ids(&run),
+            [
+                "lib/orders_test.blox::test_total",
+                "lib/orders_test.blox::test_empty",
+                "prices_test.blox::test_raises",
+            ]
+        );
+        assert_eq!(run.failed(), 1);
+        assert_eq!(
+            run.results[1]
+                .failure
+                .as_deref()
+                .and_then(|failure| failure.lines().next()),
+            Some("assertion failed: the values differ")
+        );
+
+        let run = run_tests(&app.0, Some("orders_test.blox::test_t"), None).expect("tests run");
+        assert_eq!(ids(&run), ["lib/orders_test.blox::test_total"]);
+        assert_eq!(run.filtered_out, 2);
+        assert!(
+            human_report(&run).ends_with("\ntest result: ok. 1 passed; 0 failed; 2 filtered out\n")
+        );
+    }
+
+    #[test]
+    fn test_unparseable_files() {
+        let app = App::new(
+            "unparseable",
+            &[
+                ("broken_test.blox", "def test_broken( {"),
+                ("fine_test.blox", "def test_fine() { 1 }"),
+            ],
+        );
+
+        let run = run_tests(&app.0, None, None).expect("tests run");
+        assert_eq!(
+            ids(&run),
+            ["broken_test.blox::parse", "fine_test.blox::test_fine"]
+        );
+        assert_eq!(run.failed(), 1);
+        assert!(run.results[0]
+            .failure
+            .as_deref()
+            .is_some_and(|failure| failure.starts_with("could not parse broken_test.blox: ")));
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let run = TestRun {
+            results: vec![TestResult {
+                case: TestCase {
+                    file: PathBuf::from("orders_test.blox"),
+                    name: "test_total".to_string(),
+                    parse_error: None,
+                },
+                duration: Duration::from_millis(2),
+                failure: Some("assertion failed: expected 'a' & <b>\n    at x".to_string()),
+            }],
+            filtered_out: 0,
+        };
+
+        assert_eq!(
+            junit_report(&run),
+            [
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<testsuites name="blox" tests="1" failures="1" time="0.002">"#,
+                r#"  <testsuite name="orders_test.blox" tests="1" failures="1" time="0.002">"#,
+                r#"    <testcase name="test_total" classname="orders_test.blox" time="0.002">"#,
+                r#"      <failure message="assertion failed: expected &apos;a&apos; &amp; &lt;b&gt;">assertion failed: expected &apos;a&apos; &amp; &lt;b&gt;"#,
+                r#"    at x</failure>"#,
+                r#"    </testcase>"#,
+                r#"  </testsuite>"#,
+                r#"</testsuites>"#,
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
@@ -2,8 +2,8 @@ use std::sync::{Arc, Mutex, RwLock};
 
 use ::tokio::spawn;
 use blox_assets::AssetManager;
-use blox_interpreter::{load_stdlib, EvaluationContext, Scope};
-use tracing::info;
+use blox_interpreter::{load_stdlib, EvaluationContext, RuntimeError, Scope};
+use tracing::{error, info};
 
 pub struct BloxEnvironment {
     assets: Arc<Mutex<AssetManager>>,
@@ -11,10 +11,10 @@ pub struct BloxEnvironment {
 }
 
 impl BloxEnvironment {
-    pub fn new(assets: Arc<Mutex<AssetManager>>) -> Self {
-        let context = create_context(assets.clone());
+    pub fn new(assets: Arc<Mutex<AssetManager>>) -> Result<Self, RuntimeError> {
+        let context = create_context(assets.clone())?;
         let context = Arc::new(RwLock::new(context));
-        BloxEnvironment { assets, context }
+        Ok(BloxEnvironment { assets, context })
     }
 
     pub fn context(&self) -> Arc<RwLock<EvaluationContext>> {
@@ -35,14 +35,20 @@ impl BloxEnvironment {
                 on_change.notified().await;
                 info!("Assets changed");
 
-                let mut context = context.write().unwrap();
-                *context = create_context(assets.clone());
+                // programs keep running against the old context until a
+                // change makes the stdlib loadable again
+                match create_context(assets.clone()) {
+                    Ok(new_context) => *context.write().unwrap() = new_context,
+                    Err(err) => error!("Could not reload the environment: {err}"),
+                }
             }
         });
     }
 }
 
-pub(crate) fn create_context(assets: Arc<Mutex<AssetManager>>) -> EvaluationContext {
+pub(crate) fn create_context(
+    assets: Arc<Mutex<AssetManager>>,
+) -> Result<EvaluationContext, RuntimeError> {
     let asset_manager = assets.lock().unwrap();
     let base_dir = asset_manager
         .base_dir()
@@ -54,7 +60,11 @@ pub(crate) fn create_context(assets: Arc<Mutex<AssetManager>>) -> EvaluationCont
     let mut loader_context =
         EvaluationContext::new(base_dir, Arc::new(Scope::default()), import_cache.clone());
 
-    load_stdlib(&mut loader_context);
+    load_stdlib(&mut loader_context)?;
 
-    EvaluationContext::new(base_dir, Arc::new(Scope::default()), import_cache)
+    Ok(EvaluationContext::new(
+        base_dir,
+        Arc::new(Scope::default()),
+        import_cache,
+    ))
 }
@@ -1,6 +1,7 @@
 use clap::{command, Parser};
-use commands::{console_command, server_command};
-use tracing_subscriber::EnvFilter;
+use commands::{console_command, server_command, test_command, TestFormat};
+use tracing::Level;
+use tracing_subscriber::{filter::filter_fn, fmt, prelude::*, EnvFilter};
 
 mod assets;
 mod commands;
@@ -30,15 +31,36 @@ enum Commands {
         #[arg(default_value = ".")]
         directory: String,
     },
+
+    #[command(about = "Run the `test_*` functions in an app's `*_test.blox` files")]
+    Test {
+        #[arg(default_value = ".")]
+        directory: String,
+        // only run tests whose `file::name` contains this
+        #[arg(short, long)]
+        filter: Option<String>,
+        #[arg(long, value_enum, default_value_t)]
+        format: TestFormat,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let matches = Args::parse();
 
-    tracing_subscriber::fmt()
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+    // without `RUST_LOG`, errors are shown, and so is what programs log
+    // through `stdlib/log`. its `blox` target is matched exactly, since an
+    // `EnvFilter` directive for it would also match `blox_server` and every
+    // other target starting with `blox`.
+    match EnvFilter::try_from_default_env() {
+        Ok(filter) => tracing_subscriber::fmt().with_env_filter(filter).init(),
+        Err(_) => tracing_subscriber::registry()
+            .with(fmt::layer().with_filter(filter_fn(|metadata| {
+                let level = *metadata.level();
+                level == Level::ERROR || (metadata.target() == "blox" && level <= Level::INFO)
+            })))
+            .init(),
+    }
 
     match matches.command {
         Commands::Server { port, directory } => {
@@ -51,5 +73,21 @@ async fn main() {
                 .await
                 .expect("console command failed");
         }
+        Commands::Test {
+            directory,
+            filter,
+            format,
+        } => {
+            // failing tests and tests that couldn't be run both fail the
+            // command, so CI notices either
+            match test_command(&directory, filter, format).await {
+                Ok(true) => {}
+                Ok(false) => std::process::exit(1),
+                Err(error) => {
+                    eprintln!("error: {error:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
     }
 }
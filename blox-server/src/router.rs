@@ -3,11 +3,18 @@ use hyper::{Method, Uri};
 use tracing::debug;
 
 #[derive(Debug, PartialEq)]
-pub enum RoutingError {}
+pub enum RoutingError {
+    // there's no action for this method on this path, e.g. `POST /lists/1`
+    UnsupportedRoute { method: Method, path: String },
+}
 
 impl std::fmt::Display for RoutingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "routing error")
+        match self {
+            RoutingError::UnsupportedRoute { method, path } => {
+                write!(f, "no route for {method} {path}")
+            }
+        }
     }
 }
 
@@ -75,7 +82,12 @@ pub fn request_asset_path(
                     route_parts.push(RoutePathPart::Action(Action::Create));
                 }
 
-                (method, part) => unimplemented!("method={} part={:?}", method, part),
+                (method, _) => {
+                    return Err(RoutingError::UnsupportedRoute {
+                        method: method.clone(),
+                        path: uri.path().to_string(),
+                    })
+                }
             }
         }
     }
@@ -87,8 +99,9 @@ pub fn request_asset_path(
 mod test {
     use blox_assets::types::{Action, AssetPath, Bindings, RoutePathPart};
     use hyper::{Method, Uri};
+    use proptest::prelude::*;
 
-    use super::request_asset_path;
+    use super::{request_asset_path, RoutingError};
 
     fn uri_with_path_and_query(path_and_query: &str) -> Uri {
         Uri::builder()
@@ -173,4 +186,47 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn test_unsupported_routes() {
+        assert_eq!(
+            request_asset_path(&Method::PATCH, &uri_with_path_and_query("/lists/1")),
+            Err(RoutingError::UnsupportedRoute {
+                method: Method::PATCH,
+                path: "/lists/1".to_string()
+            })
+        );
+
+        assert_eq!(
+            request_asset_path(&Method::PUT, &uri_with_path_and_query("/lists")),
+            Err(RoutingError::UnsupportedRoute {
+                method: Method::PUT,
+                path: "/lists".to_string()
+            })
+        );
+    }
+
+    fn arbitrary_method() -> impl Strategy<Value = Method> {
+        prop_oneof![
+            Just(Method::GET),
+            Just(Method::POST),
+            Just(Method::PUT),
+            Just(Method::DELETE),
+            Just(Method::PATCH),
+            Just(Method::HEAD),
+            Just(Method::OPTIONS),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_arbitrary_requests_do_not_panic(
+            method in arbitrary_method(),
+            path in "(/[a-z0-9_.%-]{0,8}){0,6}/?(\\?[a-z=&]{0,10})?",
+        ) {
+            if let Ok(uri) = Uri::builder().path_and_query(path.as_str()).build() {
+                let _ = request_asset_path(&method, &uri);
+            }
+        }
+    }
 }